@@ -0,0 +1,81 @@
+/// Parse command line arguments
+pub(crate) fn parse() -> Args {
+    use clap::Parser;
+    Args::parse()
+}
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    version,
+    about = "Command-line client for the temporary cabinet service.",
+    long_about = None
+)]
+pub(crate) struct Args {
+    /// Base URL of the temporary cabinet server
+    #[arg(
+        short,
+        long,
+        env = "TEMPORARY_CABINET_SERVER",
+        default_value = "http://127.0.0.1:8765"
+    )]
+    pub(crate) server: String,
+
+    #[command(subcommand)]
+    pub(crate) command: Command,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub(crate) enum Command {
+    /// Show how many cabinets are free
+    Usage,
+
+    /// Apply for a cabinet and lock a message and files in it
+    Send {
+        /// Files to store in the cabinet
+        files: Vec<std::path::PathBuf>,
+
+        /// Message to store in the cabinet
+        #[arg(short, long)]
+        message: Option<String>,
+
+        /// Link to store in the cabinet, repeat it for several
+        #[arg(short, long = "link")]
+        links: Vec<String>,
+
+        /// Hours to keep the cabinet before it expires (0-24)
+        #[arg(long, default_value_t = 1)]
+        hours: i32,
+
+        /// Password required to pick up the items
+        #[arg(short, long, env = "TEMPORARY_CABINET_PASSWORD")]
+        password: String,
+    },
+
+    /// List and download the items in a cabinet
+    Pick {
+        /// Cabinet code
+        code: i64,
+
+        /// Password of the cabinet
+        #[arg(short, long, env = "TEMPORARY_CABINET_PASSWORD")]
+        password: String,
+
+        /// Directory where files are downloaded to
+        #[arg(short, long, default_value = ".")]
+        output: std::path::PathBuf,
+
+        /// Only list the items without downloading them
+        #[arg(long)]
+        list: bool,
+    },
+
+    /// Empty a cabinet and give it back
+    Delete {
+        /// Cabinet code
+        code: i64,
+
+        /// Password of the cabinet
+        #[arg(short, long, env = "TEMPORARY_CABINET_PASSWORD")]
+        password: String,
+    },
+}
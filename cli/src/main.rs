@@ -0,0 +1,153 @@
+mod arg;
+
+use arg::Command;
+use client::{CabinetItemCategory, Client, ClientError, SaveRequest};
+
+/// Command-line entrypoint
+/// - Parse arguments
+/// - Run the selected command against the server
+/// - Exit with a non-zero code on failure
+#[tokio::main]
+async fn main() {
+    let args = arg::parse();
+    let client = Client::new(args.server);
+    let result = match args.command {
+        Command::Usage => usage(&client).await,
+        Command::Send {
+            files,
+            message,
+            links,
+            hours,
+            password,
+        } => send(&client, &files, message, &links, hours, &password).await,
+        Command::Pick {
+            code,
+            password,
+            output,
+            list,
+        } => pick(&client, code, &password, &output, list).await,
+        Command::Delete { code, password } => delete(&client, code, &password).await,
+    };
+    if let Err(e) = result {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+/// Command error
+enum CliError {
+    Client(ClientError),
+    Io(std::path::PathBuf, std::io::Error),
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Client(e) => write!(f, "{e}"),
+            CliError::Io(path, e) => write!(f, "'{}': {e}", path.display()),
+        }
+    }
+}
+
+impl From<ClientError> for CliError {
+    fn from(value: ClientError) -> Self {
+        CliError::Client(value)
+    }
+}
+
+/// Print cabinet usage
+async fn usage(client: &Client) -> Result<(), CliError> {
+    let usage = client.usage().await?;
+    println!(
+        "Total: {}, used: {}, free: {}",
+        usage.total, usage.used, usage.free
+    );
+    Ok(())
+}
+
+/// Apply for a cabinet and lock the message, links and files in it
+async fn send(
+    client: &Client,
+    files: &[std::path::PathBuf],
+    message: Option<String>,
+    links: &[String],
+    hours: i32,
+    password: &str,
+) -> Result<(), CliError> {
+    let mut request = SaveRequest::new(password).hours(hours);
+    if let Some(message) = message {
+        request = request.message(message);
+    }
+    for link in links {
+        request = request.link(link);
+    }
+    for file in files {
+        let filename = file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let content = tokio::fs::read(file)
+            .await
+            .map_err(|e| CliError::Io(file.clone(), e))?;
+        request = request.file(filename, content);
+    }
+
+    let cabinet = client.apply().await?;
+    let cabinet = client.save(&cabinet, request).await?;
+    println!("{}", cabinet.code);
+    if let Some(expire_at) = cabinet.expire_at {
+        eprintln!("Cabinet '{}' expires at {}", cabinet.code, expire_at);
+    }
+    Ok(())
+}
+
+/// List the items of a cabinet, print text items and links and download files
+async fn pick(
+    client: &Client,
+    code: i64,
+    password: &str,
+    output: &std::path::Path,
+    list: bool,
+) -> Result<(), CliError> {
+    let items = client.items(code, password).await?;
+    for item in items {
+        if list {
+            println!(
+                "{}\t{}\t{}\t{}",
+                item.id, item.category, item.size, item.name
+            );
+            continue;
+        }
+        if let Some(url) = item.url {
+            println!("{url}");
+            continue;
+        }
+        let content = client.download(code, item.id, password).await?;
+        if item.category == CabinetItemCategory::Text {
+            println!("{}", String::from_utf8_lossy(&content));
+            continue;
+        }
+
+        // Never trust the stored name to stay inside the output folder
+        let filename = std::path::Path::new(&item.name)
+            .file_name()
+            .map(|name| name.to_os_string())
+            .unwrap_or_else(|| item.id.to_string().into());
+        let path = output.join(filename);
+        tokio::fs::create_dir_all(output)
+            .await
+            .map_err(|e| CliError::Io(output.to_path_buf(), e))?;
+        tokio::fs::write(&path, content)
+            .await
+            .map_err(|e| CliError::Io(path.clone(), e))?;
+        eprintln!("Downloaded '{}' ({} bytes)", path.display(), item.size);
+    }
+    Ok(())
+}
+
+/// Empty and reclaim a cabinet
+async fn delete(client: &Client, code: i64, password: &str) -> Result<(), CliError> {
+    client.delete(code, password).await?;
+    eprintln!("Cabinet '{code}' reclaimed");
+    Ok(())
+}
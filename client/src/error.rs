@@ -0,0 +1,78 @@
+use domain::error::DomainError;
+use domain::error::crypto::CryptoError;
+
+/// Client error
+#[derive(Debug)]
+pub enum ClientError {
+    /// The request could not be sent or the response could not be read
+    Request(reqwest::Error),
+    /// The server answered with an error body
+    Api {
+        status: reqwest::StatusCode,
+        code: String,
+        message: String,
+    },
+    /// The server answered with an unexpected status and body
+    Http {
+        status: reqwest::StatusCode,
+        text: String,
+    },
+    /// The password could not be encrypted
+    Crypto(CryptoError),
+}
+
+impl ClientError {
+    /// Error code returned by the server, e.g. `Cabinet::InvalidPassword`
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            ClientError::Api { code, .. } => Some(code),
+            _ => None,
+        }
+    }
+
+    /// Whether the server answered with the given domain error, by variant, the values it holds
+    /// are not compared
+    ///
+    /// ```
+    /// use domain::error::cabinet::CabinetError;
+    /// # fn check(e: client::ClientError) -> bool {
+    /// e.is(&CabinetError::InvalidPassword.into())
+    /// # }
+    /// ```
+    pub fn is(&self, error: &DomainError) -> bool {
+        self.code() == Some(interface::error::error_code(error))
+    }
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::Request(e) => write!(f, "Request failed: {e}"),
+            ClientError::Api { code, message, .. } => write!(f, "{message} ({code})"),
+            ClientError::Http { status, text } => write!(f, "Unexpected response {status}: {text}"),
+            ClientError::Crypto(e) => write!(f, "Crypto error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Request(e) => Some(e),
+            ClientError::Crypto(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(value: reqwest::Error) -> Self {
+        ClientError::Request(value)
+    }
+}
+
+impl From<CryptoError> for ClientError {
+    fn from(value: CryptoError) -> Self {
+        ClientError::Crypto(value)
+    }
+}
@@ -0,0 +1,325 @@
+//! Async client for the temporary cabinet HTTP API.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), client::ClientError> {
+//! let client = client::Client::new("http://127.0.0.1:8765");
+//! let cabinet = client.apply().await?;
+//! let request = client::SaveRequest::new("1234")
+//!     .hours(6)
+//!     .message("build 42")
+//!     .file("app.tar.gz", std::fs::read("app.tar.gz").unwrap());
+//! let cabinet = client.save(&cabinet, request).await?;
+//! println!("Cabinet code: {}", cabinet.code);
+//! # Ok(())
+//! # }
+//! ```
+
+mod error;
+
+pub use domain::entity::cabinet::{CabinetItemCategory, CabinetStatus, CabinetUsage};
+pub use error::ClientError;
+pub use interface::{CabinetArchiveEntryView, CabinetItemView, CabinetSnippet, CabinetView};
+
+use interface::CabinetCredential;
+use interface::envelope::{Envelope, ErrorEnvelope};
+use reqwest::multipart::{Form, Part};
+
+/// Temporary cabinet api client
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    server: String,
+}
+
+impl Client {
+    /// Create a client for the server at `server`, e.g. `http://127.0.0.1:8765`
+    pub fn new(server: impl Into<String>) -> Self {
+        Self::with_http_client(server, reqwest::Client::new())
+    }
+
+    /// Create a client using a preconfigured [`reqwest::Client`]
+    pub fn with_http_client(server: impl Into<String>, http: reqwest::Client) -> Self {
+        let server = server.into();
+        Self {
+            http,
+            server: server.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+impl Client {
+    /// Get cabinet usage
+    pub async fn usage(&self) -> Result<CabinetUsage, ClientError> {
+        let response = self.http.get(self.url("/cabinet/usage")).send().await?;
+        data(response).await
+    }
+
+    /// Apply for a cabinet, the returned cabinet is held for a few minutes
+    pub async fn apply(&self) -> Result<CabinetView, ClientError> {
+        let response = self.http.post(self.url("/cabinet/apply")).send().await?;
+        data(response).await
+    }
+
+    /// Save items to a held cabinet and lock it
+    pub async fn save(
+        &self,
+        cabinet: &CabinetView,
+        request: SaveRequest,
+    ) -> Result<CabinetView, ClientError> {
+        let (public_key, password) = self.encrypt_password(&request.password).await?;
+        let mut form = Form::new()
+            .text("hold_token", cabinet.hold_token.clone().unwrap_or_default())
+            .text("public_key", public_key)
+            .text("password", password);
+        if let Some(hours) = request.hours {
+            form = form.text("hours", hours.to_string());
+        }
+        if let Some(message) = request.message {
+            form = form.text("message", message);
+        }
+        for snippet in &request.snippets {
+            form = form.text("snippets", serde_json::json!(snippet).to_string());
+        }
+        for link in request.links {
+            form = form.text("links", link);
+        }
+        if request.strip_metadata {
+            form = form.text("strip_metadata", "true");
+        }
+        for (name, content) in request.files {
+            form = form.part("files", Part::bytes(content).file_name(name));
+        }
+        let response = self
+            .http
+            .post(self.url(&format!("/cabinet/{}", cabinet.code)))
+            .multipart(form)
+            .send()
+            .await?;
+        data(response).await
+    }
+
+    /// List the items in a cabinet
+    pub async fn items(
+        &self,
+        code: i64,
+        password: &str,
+    ) -> Result<Vec<CabinetItemView>, ClientError> {
+        let credential = self.credential(password).await?;
+        let response = self
+            .http
+            .post(self.url(&format!("/cabinet/{code}/items")))
+            .json(&credential)
+            .send()
+            .await?;
+        data(response).await
+    }
+
+    /// Download the content of a cabinet item
+    pub async fn download(
+        &self,
+        code: i64,
+        item_id: i64,
+        password: &str,
+    ) -> Result<Vec<u8>, ClientError> {
+        let credential = self.credential(password).await?;
+        let response = self
+            .http
+            .post(self.url(&format!("/cabinet/{code}/item/{item_id}/content")))
+            .query(&[("mode", "file")])
+            .json(&credential)
+            .send()
+            .await?;
+        Ok(check(response).await?.bytes().await?.to_vec())
+    }
+
+    /// List the files of a ZIP or tar item
+    pub async fn entries(
+        &self,
+        code: i64,
+        item_id: i64,
+        password: &str,
+    ) -> Result<Vec<CabinetArchiveEntryView>, ClientError> {
+        let credential = self.credential(password).await?;
+        let response = self
+            .http
+            .post(self.url(&format!("/cabinet/{code}/item/{item_id}/entries")))
+            .json(&credential)
+            .send()
+            .await?;
+        data(response).await
+    }
+
+    /// Download one file of a ZIP or tar item, `path` as listed by [`Client::entries`]
+    pub async fn extract(
+        &self,
+        code: i64,
+        item_id: i64,
+        path: &str,
+        password: &str,
+    ) -> Result<Vec<u8>, ClientError> {
+        let credential = self.credential(password).await?;
+        let response = self
+            .http
+            .post(self.url(&format!("/cabinet/{code}/item/{item_id}/entry")))
+            .query(&[("path", path)])
+            .json(&credential)
+            .send()
+            .await?;
+        Ok(check(response).await?.bytes().await?.to_vec())
+    }
+
+    /// Empty a cabinet and give it back
+    pub async fn delete(&self, code: i64, password: &str) -> Result<(), ClientError> {
+        let credential = self.credential(password).await?;
+        let response = self
+            .http
+            .delete(self.url(&format!("/cabinet/{code}")))
+            .json(&credential)
+            .send()
+            .await?;
+        check(response).await?;
+        Ok(())
+    }
+}
+
+impl Client {
+    fn url(&self, path: &str) -> String {
+        format!("{}/api/v1{}", self.server, path)
+    }
+
+    /// Fetch a one-time public key and encrypt the password with it
+    async fn encrypt_password(&self, password: &str) -> Result<(String, String), ClientError> {
+        use domain::service::crypto::{encrypt_plaintext_to_hex, hex2pk};
+        let response = self.http.get(self.url("/crypto/pk")).send().await?;
+        let public_key: String = data(response).await?;
+        let pk = hex2pk(&public_key)?;
+        let password = encrypt_plaintext_to_hex(&pk, password)?;
+        Ok((public_key, password))
+    }
+
+    async fn credential(&self, password: &str) -> Result<CabinetCredential, ClientError> {
+        let (public_key, password) = self.encrypt_password(password).await?;
+        Ok(CabinetCredential {
+            public_key,
+            password,
+        })
+    }
+}
+
+/// Items to save into a cabinet
+#[derive(Clone)]
+pub struct SaveRequest {
+    password: String,
+    hours: Option<i32>,
+    message: Option<String>,
+    snippets: Vec<CabinetSnippet>,
+    links: Vec<String>,
+    strip_metadata: bool,
+    files: Vec<(String, Vec<u8>)>,
+}
+
+/// Password and file contents are left out
+impl std::fmt::Debug for SaveRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let files = self.files.iter().map(|(name, _)| name).collect::<Vec<_>>();
+        let snippets = self
+            .snippets
+            .iter()
+            .map(|snippet| (&snippet.name, &snippet.language))
+            .collect::<Vec<_>>();
+        f.debug_struct("SaveRequest")
+            .field("password", &domain::entity::REDACTED)
+            .field("hours", &self.hours)
+            .field("message", &self.message.as_ref().map(String::len))
+            .field("snippets", &snippets)
+            .field("links", &self.links)
+            .field("strip_metadata", &self.strip_metadata)
+            .field("files", &files)
+            .finish()
+    }
+}
+
+impl SaveRequest {
+    /// Create a request locked by `password`
+    pub fn new(password: impl Into<String>) -> Self {
+        Self {
+            password: password.into(),
+            hours: None,
+            message: None,
+            snippets: Vec::new(),
+            links: Vec::new(),
+            strip_metadata: false,
+            files: Vec::new(),
+        }
+    }
+
+    /// Hours to keep the cabinet (0-24), the server defaults to 1
+    pub fn hours(mut self, hours: i32) -> Self {
+        self.hours = Some(hours);
+        self
+    }
+
+    /// Text message to store
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Add a named text snippet, `language` enables syntax highlighting when viewing it
+    pub fn snippet(
+        mut self,
+        name: impl Into<String>,
+        language: Option<&str>,
+        content: impl Into<String>,
+    ) -> Self {
+        self.snippets.push(CabinetSnippet {
+            name: Some(name.into()),
+            language: language.map(str::to_string),
+            content: content.into(),
+        });
+        self
+    }
+
+    /// Add a link, an `http` or `https` address
+    pub fn link(mut self, url: impl Into<String>) -> Self {
+        self.links.push(url.into());
+        self
+    }
+
+    /// Strip EXIF, XMP and IPTC from images and properties from Office documents before storing
+    pub fn strip_metadata(mut self, strip_metadata: bool) -> Self {
+        self.strip_metadata = strip_metadata;
+        self
+    }
+
+    /// Add a file to store
+    pub fn file(mut self, name: impl Into<String>, content: impl Into<Vec<u8>>) -> Self {
+        self.files.push((name.into(), content.into()));
+        self
+    }
+}
+
+/// Unwrap the `data` of a successful response
+async fn data<T: serde::de::DeserializeOwned>(
+    response: reqwest::Response,
+) -> Result<T, ClientError> {
+    let envelope: Envelope<T> = check(response).await?.json().await?;
+    Ok(envelope.data)
+}
+
+/// Turn a non-success response into the server's error
+async fn check(response: reqwest::Response) -> Result<reqwest::Response, ClientError> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let text = response.text().await?;
+    match serde_json::from_str::<ErrorEnvelope>(&text) {
+        Ok(body) => Err(ClientError::Api {
+            status,
+            code: body.error.code,
+            message: body.error.message,
+        }),
+        Err(_) => Err(ClientError::Http { status, text }),
+    }
+}
@@ -0,0 +1,1420 @@
+use client::{CabinetItemCategory, CabinetStatus, Client, ClientError, SaveRequest};
+use domain::error::cabinet::CabinetError;
+
+/// Start the real api router on a random port with a fresh database
+async fn serve(cabinet_number: u64) -> Client {
+    let server = serve_url(cabinet_number).await;
+    Client::new(server)
+}
+
+/// Start the real api router and return its base url
+async fn serve_url(cabinet_number: u64) -> String {
+    serve_state(cabinet_number, |_| {}).await
+}
+
+/// Start the real api router with a customized state and return its base url
+async fn serve_state(
+    cabinet_number: u64,
+    configure: impl FnOnce(&mut interface::ServerState),
+) -> String {
+    use migration::{Migrator, MigratorTrait};
+
+    let data_folder = std::env::temp_dir().join(format!(
+        "temporary-cabinet-client-{}",
+        uuid::Uuid::new_v4().simple()
+    ));
+    std::fs::create_dir_all(&data_folder).unwrap();
+    let database_url = format!(
+        "sqlite://{}?mode=rwc",
+        data_folder.join("db.sqlite").display()
+    );
+    // Same pool size as the server, handlers hold a transaction while querying
+    let mut connect_opts = sea_orm::ConnectOptions::new(database_url);
+    connect_opts.max_connections(10);
+    let connection = sea_orm::Database::connect(connect_opts).await.unwrap();
+    Migrator::up(&connection, None).await.unwrap();
+
+    let mut state = interface::ServerState::new(connection, data_folder, cabinet_number);
+    configure(&mut state);
+    let router = axum::Router::new()
+        .merge(interface::health::router())
+        .merge(interface::metrics::router())
+        .nest("/api", interface::router())
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    format!("http://{addr}")
+}
+
+/// Answer clamd INSTREAM scans, content containing `EICAR` is infected
+async fn fake_clamd() -> infrastructure::clamd::ClamdAddress {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut command = [0u8; 10];
+                stream.read_exact(&mut command).await.unwrap();
+                assert_eq!(&command, b"zINSTREAM\0");
+                let mut content = Vec::new();
+                loop {
+                    let length = stream.read_u32().await.unwrap() as usize;
+                    if length == 0 {
+                        break;
+                    }
+                    let mut chunk = vec![0u8; length];
+                    stream.read_exact(&mut chunk).await.unwrap();
+                    content.extend_from_slice(&chunk);
+                }
+                let reply = if content.windows(5).any(|w| w == b"EICAR") {
+                    "stream: Eicar-Test-Signature FOUND\0"
+                } else {
+                    "stream: OK\0"
+                };
+                stream.write_all(reply.as_bytes()).await.unwrap();
+            });
+        }
+    });
+    address.parse().unwrap()
+}
+
+/// One-time public key and the password `1234` encrypted with it
+async fn credential(server: &str) -> serde_json::Value {
+    use domain::service::crypto::{encrypt_plaintext_to_hex, hex2pk};
+
+    let response = reqwest::get(format!("{server}/api/v1/crypto/pk"))
+        .await
+        .unwrap();
+    let body: serde_json::Value = response.json().await.unwrap();
+    let public_key = body["data"].as_str().unwrap().to_string();
+    let password = encrypt_plaintext_to_hex(&hex2pk(&public_key).unwrap(), "1234").unwrap();
+    serde_json::json!({ "public_key": public_key, "password": password })
+}
+
+/// Request an item's content directly, to look at the response headers
+async fn content(server: &str, code: i64, item_id: i64, mode: &str) -> reqwest::Response {
+    let credential = credential(server).await;
+    reqwest::Client::new()
+        .post(format!(
+            "{server}/api/v1/cabinet/{code}/item/{item_id}/content?mode={mode}"
+        ))
+        .json(&credential)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn save_and_pick_up() {
+    let client = serve(10).await;
+
+    let cabinet = client.apply().await.unwrap();
+    assert_eq!(cabinet.status, CabinetStatus::Hold);
+    assert!(cabinet.hold_token.is_some());
+
+    let request = SaveRequest::new("1234")
+        .hours(2)
+        .message("hello")
+        .file("a.txt", b"file a".to_vec())
+        .file("b.bin", vec![0u8, 1, 2, 3]);
+    let saved = client.save(&cabinet, request).await.unwrap();
+    assert_eq!(saved.code, cabinet.code);
+    assert_eq!(saved.status, CabinetStatus::Occupied);
+    assert!(saved.hold_token.is_none());
+
+    let usage = client.usage().await.unwrap();
+    assert_eq!((usage.total, usage.used, usage.free), (10, 1, 9));
+
+    let items = client.items(cabinet.code, "1234").await.unwrap();
+    let names = items.iter().map(|i| i.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["message.txt", "a.txt", "b.bin"]);
+    assert_eq!(items[0].category, CabinetItemCategory::Text);
+    assert_eq!(items[1].category, CabinetItemCategory::File);
+    assert_eq!(items[2].size, 4);
+
+    let message = client
+        .download(cabinet.code, items[0].id, "1234")
+        .await
+        .unwrap();
+    assert_eq!(message, b"hello");
+    let file = client
+        .download(cabinet.code, items[2].id, "1234")
+        .await
+        .unwrap();
+    assert_eq!(file, [0u8, 1, 2, 3]);
+
+    client.delete(cabinet.code, "1234").await.unwrap();
+    let usage = client.usage().await.unwrap();
+    assert_eq!(usage.used, 0);
+}
+
+#[tokio::test]
+async fn wrong_password_is_rejected() {
+    let client = serve(10).await;
+    let cabinet = client.apply().await.unwrap();
+    client
+        .save(&cabinet, SaveRequest::new("1234").message("secret"))
+        .await
+        .unwrap();
+
+    let error = client.items(cabinet.code, "4321").await.unwrap_err();
+    assert!(error.is(&CabinetError::InvalidPassword.into()));
+    assert_eq!(error.code(), Some("Cabinet::InvalidPassword"));
+    assert!(matches!(
+        error,
+        ClientError::Api { status, .. } if status == reqwest::StatusCode::FORBIDDEN
+    ));
+
+    let error = client.delete(cabinet.code, "4321").await.unwrap_err();
+    assert!(error.is(&CabinetError::InvalidPassword.into()));
+}
+
+#[tokio::test]
+async fn unknown_cabinet_is_not_found() {
+    let client = serve(10).await;
+    let error = client.items(100000, "1234").await.unwrap_err();
+    assert!(error.is(&CabinetError::NotFound.into()));
+    assert!(matches!(error, ClientError::Api { .. }));
+}
+
+#[tokio::test]
+async fn hold_token_must_match() {
+    let client = serve(10).await;
+    let mut cabinet = client.apply().await.unwrap();
+    cabinet.hold_token = Some("not-the-token".to_string());
+    let error = client
+        .save(&cabinet, SaveRequest::new("1234").message("hi"))
+        .await
+        .unwrap_err();
+    assert!(error.is(&CabinetError::NotYourHoldCabinet(cabinet.code).into()));
+}
+
+#[tokio::test]
+async fn legacy_api_is_deprecated() {
+    let server = serve_url(10).await;
+    let http = reqwest::Client::new();
+
+    let response = http.get(format!("{server}/api/ping")).send().await.unwrap();
+    assert_eq!(response.headers()["deprecation"], "true");
+    assert_eq!(
+        response.headers()["link"],
+        "</api/v1/ping>; rel=\"successor-version\""
+    );
+    assert_eq!(response.text().await.unwrap(), "pong");
+
+    let response = http
+        .get(format!("{server}/api/v1/ping"))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.headers().get("deprecation").is_none());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body, serde_json::json!({ "data": "pong" }));
+
+    let response = http
+        .post(format!("{server}/api/v1/cabinet/100000/items"))
+        .json(&serde_json::json!({}))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_client_error());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "InvalidRequest");
+}
+
+#[tokio::test]
+async fn file_names_are_sanitized() {
+    let server = serve_url(10).await;
+    let client = Client::new(&server);
+    let cabinet = client.apply().await.unwrap();
+    let request = SaveRequest::new("1234")
+        .file("..\\..\\con.txt", b"a".to_vec())
+        .file("报告 \"2025\";\tX-Injected: 1.pdf", b"b".to_vec())
+        .file(format!("{}.txt", "长".repeat(100)), b"c".to_vec());
+    client.save(&cabinet, request).await.unwrap();
+
+    let items = client.items(cabinet.code, "1234").await.unwrap();
+    let names = items.iter().map(|i| i.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names[0], "_con.txt");
+    assert_eq!(names[1], "报告 _2025_;X-Injected_ 1.pdf");
+    assert_eq!(names[2], format!("{}.txt", "长".repeat(83)));
+
+    let response = content(&server, cabinet.code, items[1].id, "file").await;
+    assert_eq!(
+        response.headers()["content-disposition"],
+        "attachment; filename=\"__ _2025_;X-Injected_ 1.pdf\"; \
+         filename*=UTF-8''%E6%8A%A5%E5%91%8A%20_2025_%3BX-Injected_%201.pdf"
+    );
+}
+
+#[tokio::test]
+async fn items_are_previewed_inline_by_type() {
+    let server = serve_url(10).await;
+    let client = Client::new(&server);
+    let cabinet = client.apply().await.unwrap();
+    let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+    let request = SaveRequest::new("1234")
+        .message("hello")
+        .file("photo.dat", png)
+        .file("notes.md", b"# notes".to_vec())
+        .file("tool.bin", vec![0u8, 159, 146, 150]);
+    client.save(&cabinet, request).await.unwrap();
+
+    let items = client.items(cabinet.code, "1234").await.unwrap();
+    let types = items
+        .iter()
+        .map(|i| (i.mime_type.as_str(), i.previewable))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        [
+            ("text/plain; charset=utf-8", true),
+            ("image/png", true),
+            ("text/markdown; charset=utf-8", false),
+            ("application/octet-stream", false),
+        ]
+    );
+
+    let response = content(&server, cabinet.code, items[1].id, "inline").await;
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/png");
+    assert_eq!(
+        response.headers()["content-security-policy"],
+        "sandbox; default-src 'none'"
+    );
+    assert!(
+        response.headers()["content-disposition"]
+            .to_str()
+            .unwrap()
+            .starts_with("inline; ")
+    );
+
+    let response = content(&server, cabinet.code, items[3].id, "inline").await;
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn items_of_other_cabinets_are_not_found() {
+    let server = serve_url(10).await;
+    let client = Client::new(&server);
+    let own = client.apply().await.unwrap();
+    let request = SaveRequest::new("1234").file("mine.txt", b"mine".to_vec());
+    client.save(&own, request).await.unwrap();
+    let other = client.apply().await.unwrap();
+    let request = SaveRequest::new("5678")
+        .file("secret.txt", b"secret".to_vec())
+        .snippet("secret.rs", Some("rust"), "fn secret() {}");
+    client.save(&other, request).await.unwrap();
+    let mine = client.items(own.code, "1234").await.unwrap();
+    let theirs = client.items(other.code, "5678").await.unwrap();
+    let file = theirs.iter().find(|i| i.name == "secret.txt").unwrap();
+    let snippet = theirs.iter().find(|i| i.name == "secret.rs").unwrap();
+
+    let response = content(&server, own.code, mine[0].id, "file").await;
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    // The password of one cabinet opens none of the items of another
+    for (item, mode) in [(file, "file"), (file, "inline"), (snippet, "highlighted")] {
+        let response = content(&server, own.code, item.id, mode).await;
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND, "{mode}");
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "Cabinet::CabinetItemNotFound");
+    }
+}
+
+#[tokio::test]
+async fn item_ids_stay_within_their_cabinet() {
+    use domain::entity::cabinet::CabinetItem;
+
+    let server = serve_url(10).await;
+    let client = Client::new(&server);
+    let cabinet = client.apply().await.unwrap();
+    let mut request = SaveRequest::new("1234").message("hello");
+    for index in 0..CabinetItem::MAX_ITEMS {
+        request = request.snippet(format!("{index}.txt"), None, "x");
+    }
+    let error = client.save(&cabinet, request).await.unwrap_err();
+    assert!(error.is(&CabinetError::TooManyItems(CabinetItem::MAX_ITEMS).into()));
+
+    let mut request = SaveRequest::new("1234").message("hello");
+    for index in 1..CabinetItem::MAX_ITEMS {
+        request = request.snippet(format!("{index}.txt"), None, "x");
+    }
+    client.save(&cabinet, request).await.unwrap();
+    let items = client.items(cabinet.code, "1234").await.unwrap();
+    assert_eq!(items.len(), CabinetItem::MAX_ITEMS);
+    // Past the tenth item the IDs used to run into those of the next cabinet
+    let first = CabinetItem::item_id(cabinet.code, 1);
+    let last = CabinetItem::item_id(cabinet.code, CabinetItem::MAX_ITEMS as i64);
+    assert!(items.iter().all(|item| (first..=last).contains(&item.id)));
+    assert!(last < CabinetItem::item_id(cabinet.code + 1, 1));
+}
+
+#[tokio::test]
+async fn thumbnails_are_generated_for_images() {
+    let server = serve_url(10).await;
+    let client = Client::new(&server);
+    let cabinet = client.apply().await.unwrap();
+    let mut png = std::io::Cursor::new(Vec::new());
+    image::RgbImage::from_pixel(800, 400, image::Rgb([200, 30, 30]))
+        .write_to(&mut png, image::ImageFormat::Png)
+        .unwrap();
+    let request = SaveRequest::new("1234")
+        .file("red.png", png.into_inner())
+        .file("notes.txt", b"no picture".to_vec());
+    client.save(&cabinet, request).await.unwrap();
+    let items = client.items(cabinet.code, "1234").await.unwrap();
+    assert!(items[0].thumbnail);
+    assert!(!items[1].thumbnail);
+
+    let thumbnail = |item_id: i64| {
+        let server = server.clone();
+        async move {
+            let credential = credential(&server).await;
+            reqwest::Client::new()
+                .get(format!(
+                    "{server}/api/v1/cabinet/{}/item/{item_id}/thumbnail",
+                    cabinet.code
+                ))
+                .query(&credential)
+                .send()
+                .await
+                .unwrap()
+        }
+    };
+    // Generated in the background after saving
+    let mut response = thumbnail(items[0].id).await;
+    for _ in 0..50 {
+        if response.status() != reqwest::StatusCode::NOT_FOUND {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        response = thumbnail(items[0].id).await;
+    }
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/webp");
+    let bytes = response.bytes().await.unwrap();
+    let decoded = image::load_from_memory_with_format(&bytes, image::ImageFormat::WebP).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (320, 160));
+
+    let response = thumbnail(items[1].id).await;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn image_metadata_is_stripped_on_request() {
+    use image::ImageDecoder;
+
+    let client = serve(10).await;
+    let mut jpeg = std::io::Cursor::new(Vec::new());
+    image::RgbImage::from_pixel(40, 20, image::Rgb([30, 200, 30]))
+        .write_to(&mut jpeg, image::ImageFormat::Jpeg)
+        .unwrap();
+    let jpeg = jpeg.into_inner();
+    // EXIF with the orientation rotated by 90 degrees and a GPS note, then XMP
+    let mut exif =
+        b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01\0\x06\0\0\0\0\0\0".to_vec();
+    exif.extend_from_slice(b"GPS 52.5200 N 13.4050 E");
+    let xmp = b"http://ns.adobe.com/xap/1.0/\0<x:xmpmeta>Jane Doe</x:xmpmeta>".to_vec();
+    let mut photo = jpeg[..2].to_vec();
+    for payload in [exif, xmp] {
+        photo.extend_from_slice(&[0xFF, 0xE1]);
+        photo.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+        photo.extend_from_slice(&payload);
+    }
+    photo.extend_from_slice(&jpeg[2..]);
+
+    let cabinet = client.apply().await.unwrap();
+    let request = SaveRequest::new("1234")
+        .file("photo.jpg", photo.clone())
+        .file("notes.txt", b"GPS 52.5200 N".to_vec());
+    let saved = client.save(&cabinet, request).await.unwrap();
+    assert_eq!(saved.stripped_bytes, None);
+    assert_eq!(saved.metadata_kept, None);
+    let items = client.items(cabinet.code, "1234").await.unwrap();
+    let content = client
+        .download(cabinet.code, items[0].id, "1234")
+        .await
+        .unwrap();
+    assert_eq!(content, photo);
+
+    let cabinet = client.apply().await.unwrap();
+    let request = SaveRequest::new("1234")
+        .strip_metadata(true)
+        .file("photo.jpg", photo.clone())
+        .file("notes.txt", b"GPS 52.5200 N".to_vec());
+    let saved = client.save(&cabinet, request).await.unwrap();
+    let items = client.items(cabinet.code, "1234").await.unwrap();
+    let content = client
+        .download(cabinet.code, items[0].id, "1234")
+        .await
+        .unwrap();
+    assert_eq!(saved.stripped_bytes, Some(photo.len() - content.len()));
+    assert_eq!(saved.metadata_kept, Some(Vec::new()));
+    assert_eq!(items[0].size as usize, content.len());
+    let text = String::from_utf8_lossy(&content);
+    assert!(!text.contains("52.5200") && !text.contains("Jane Doe"));
+
+    let mut decoder =
+        image::codecs::jpeg::JpegDecoder::new(std::io::Cursor::new(&content)).unwrap();
+    assert_eq!(
+        decoder.orientation().unwrap(),
+        image::metadata::Orientation::Rotate90
+    );
+    assert_eq!(decoder.dimensions(), (40, 20));
+    let notes = client
+        .download(cabinet.code, items[1].id, "1234")
+        .await
+        .unwrap();
+    assert_eq!(notes, b"GPS 52.5200 N");
+}
+
+#[tokio::test]
+async fn infected_items_are_rejected() {
+    use infrastructure::clamd::ClamdScanner;
+
+    let address = fake_clamd().await;
+    let server = serve_state(10, |state| {
+        let scanner = ClamdScanner::new(address, std::time::Duration::from_secs(5));
+        state.scanner = Some(std::sync::Arc::new(scanner));
+    })
+    .await;
+    let client = Client::new(&server);
+    let cabinet = client.apply().await.unwrap();
+    let request = SaveRequest::new("1234").message("hello").file(
+        "eicar.com",
+        b"X5O!P%@AP[4\\PZX54(P^)7CC)7}$EICAR-STANDARD-ANTIVIRUS-TEST-FILE!".to_vec(),
+    );
+    let error = client.save(&cabinet, request).await.unwrap_err();
+    assert!(
+        error.is(
+            &CabinetError::InfectedItem("eicar.com".into(), "Eicar-Test-Signature".into()).into()
+        )
+    );
+    assert!(matches!(
+        error,
+        ClientError::Api { status, .. } if status == reqwest::StatusCode::UNPROCESSABLE_ENTITY
+    ));
+
+    // Still held, a clean upload goes through
+    let request = SaveRequest::new("1234")
+        .message("hello")
+        .file("notes.txt", b"clean".to_vec());
+    let saved = client.save(&cabinet, request).await.unwrap();
+    assert_eq!(saved.status, CabinetStatus::Occupied);
+}
+
+#[tokio::test]
+async fn scanner_outage_fails_closed_unless_configured() {
+    use infrastructure::clamd::ClamdScanner;
+
+    // Nothing listens on the port once the listener is dropped
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address: infrastructure::clamd::ClamdAddress =
+        listener.local_addr().unwrap().to_string().parse().unwrap();
+    drop(listener);
+    let serve_with = |fail_open: bool| {
+        let address = address.clone();
+        serve_state(10, move |state| {
+            let scanner = ClamdScanner::new(address, std::time::Duration::from_secs(5));
+            state.scanner = Some(std::sync::Arc::new(scanner));
+            state.scan_fail_open = fail_open;
+        })
+    };
+
+    let client = Client::new(serve_with(false).await);
+    let cabinet = client.apply().await.unwrap();
+    let error = client
+        .save(&cabinet, SaveRequest::new("1234").message("hello"))
+        .await
+        .unwrap_err();
+    assert!(error.is(&CabinetError::ScannerUnavailable.into()));
+    assert!(matches!(
+        error,
+        ClientError::Api { status, .. } if status == reqwest::StatusCode::SERVICE_UNAVAILABLE
+    ));
+
+    let client = Client::new(serve_with(true).await);
+    let cabinet = client.apply().await.unwrap();
+    let saved = client
+        .save(&cabinet, SaveRequest::new("1234").message("hello"))
+        .await
+        .unwrap();
+    assert_eq!(saved.status, CabinetStatus::Occupied);
+}
+
+#[tokio::test]
+async fn snippets_are_highlighted() {
+    let server = serve_url(10).await;
+    let client = Client::new(&server);
+    let cabinet = client.apply().await.unwrap();
+    let request = SaveRequest::new("1234")
+        .message("see the config")
+        .snippet(
+            "main.rs",
+            Some("Rust"),
+            "fn main() { let s = \"<b>\"; } // done",
+        )
+        .snippet("run.txt", None, "cargo run");
+    client.save(&cabinet, request).await.unwrap();
+
+    let items = client.items(cabinet.code, "1234").await.unwrap();
+    let snippets = items
+        .iter()
+        .map(|i| (i.category.clone(), i.name.as_str(), i.language.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        snippets,
+        [
+            (CabinetItemCategory::Text, "message.txt", None),
+            (CabinetItemCategory::Text, "main.rs", Some("rust")),
+            (CabinetItemCategory::Text, "run.txt", None),
+        ]
+    );
+
+    let response = content(&server, cabinet.code, items[2].id, "text").await;
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/plain; charset=utf-8"
+    );
+    assert_eq!(response.text().await.unwrap(), "cargo run");
+
+    let response = content(&server, cabinet.code, items[1].id, "highlighted").await;
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/html; charset=utf-8"
+    );
+    assert_eq!(
+        response.headers()["content-security-policy"],
+        "sandbox; default-src 'none'; style-src 'unsafe-inline'"
+    );
+    let html = response.text().await.unwrap();
+    assert!(html.contains("<span class=\"hl-keyword\">fn</span> main()"));
+    assert!(html.contains("<span class=\"hl-string\">&quot;&lt;b&gt;&quot;</span>"));
+    assert!(html.contains("<span class=\"hl-comment\">// done</span>"));
+
+    let invalid = reqwest::multipart::Form::new().text("snippets", "not json");
+    let response = reqwest::Client::new()
+        .post(format!("{server}/api/v1/cabinet/{}", cabinet.code))
+        .multipart(invalid)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "Cabinet::InvalidSnippet");
+    assert!(
+        body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("Snippet 1")
+    );
+}
+
+#[tokio::test]
+async fn links_are_validated_and_listed() {
+    let server = serve_url(10).await;
+    let client = Client::new(&server);
+    let cabinet = client.apply().await.unwrap();
+    let request = SaveRequest::new("1234")
+        .link("https://example.com/docs?page=2")
+        .link("http://bücher.example:8080/");
+    client.save(&cabinet, request).await.unwrap();
+
+    let items = client.items(cabinet.code, "1234").await.unwrap();
+    let links = items
+        .iter()
+        .map(|i| {
+            (
+                i.category.clone(),
+                i.name.as_str(),
+                i.url.as_deref(),
+                i.host.as_deref(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        links,
+        [
+            (
+                CabinetItemCategory::Link,
+                "example.com",
+                Some("https://example.com/docs?page=2"),
+                Some("example.com"),
+            ),
+            (
+                CabinetItemCategory::Link,
+                "xn--bcher-kva.example",
+                Some("http://xn--bcher-kva.example:8080/"),
+                Some("xn--bcher-kva.example:8080"),
+            ),
+        ]
+    );
+    assert_eq!(items[0].mime_type, "text/uri-list");
+    let response = content(&server, cabinet.code, items[0].id, "text").await;
+    assert_eq!(
+        response.text().await.unwrap(),
+        "https://example.com/docs?page=2"
+    );
+
+    // The second link is the invalid one
+    for link in [
+        "javascript:alert(1)",
+        "https://bank.example@evil.example/",
+        "not a link",
+    ] {
+        let form = reqwest::multipart::Form::new()
+            .text("links", "https://example.com/")
+            .text("links", link);
+        let response = reqwest::Client::new()
+            .post(format!("{server}/api/v1/cabinet/{}", cabinet.code))
+            .multipart(form)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "Cabinet::InvalidLink");
+    }
+}
+
+#[tokio::test]
+async fn link_titles_are_not_fetched_from_private_addresses() {
+    use infrastructure::link_title::HttpTitleFetcher;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    // A page a fetcher without guard would happily read
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let page = format!("http://{}/", listener.local_addr().unwrap());
+    let reached = std::sync::Arc::new(AtomicBool::new(false));
+    let reached_by_fetcher = reached.clone();
+    tokio::spawn(async move {
+        use tokio::io::AsyncWriteExt;
+        while let Ok((mut stream, _)) = listener.accept().await {
+            reached_by_fetcher.store(true, Ordering::SeqCst);
+            let body = "<html><title>Internal dashboard</title></html>";
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\ncontent-length: {}\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        }
+    });
+
+    let server = serve_state(10, |state| {
+        let fetcher = HttpTitleFetcher::new(std::time::Duration::from_secs(5));
+        state.title_fetcher = Some(std::sync::Arc::new(fetcher));
+    })
+    .await;
+    let client = Client::new(&server);
+    let cabinet = client.apply().await.unwrap();
+    client
+        .save(&cabinet, SaveRequest::new("1234").link(page.as_str()))
+        .await
+        .unwrap();
+
+    let items = client.items(cabinet.code, "1234").await.unwrap();
+    assert_eq!(items[0].name, "127.0.0.1");
+    assert_eq!(items[0].url.as_deref(), Some(page.as_str()));
+    assert!(!reached.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn compressible_items_are_stored_compressed() {
+    let server = serve_state(10, |state| state.compression_level = Some(3)).await;
+    let client = Client::new(&server);
+    let cabinet = client.apply().await.unwrap();
+    let log = "2026-10-19 INFO request served in 3 ms\n".repeat(200);
+    // Random bytes do not compress and are stored as is
+    let noise = (0..256)
+        .flat_map(|_| uuid::Uuid::new_v4().into_bytes())
+        .collect::<Vec<_>>();
+    let request = SaveRequest::new("1234")
+        .file("app.log", log.as_bytes())
+        .file("noise.bin", noise.clone());
+    client.save(&cabinet, request).await.unwrap();
+
+    let items = client.items(cabinet.code, "1234").await.unwrap();
+    assert_eq!(items[0].size, log.len() as i64);
+
+    let response = content(&server, cabinet.code, items[0].id, "file").await;
+    assert!(response.headers().get("content-encoding").is_none());
+    assert_eq!(response.headers()["vary"], "accept-encoding");
+    assert_eq!(response.bytes().await.unwrap(), log.as_bytes());
+    let content = client
+        .download(cabinet.code, items[0].id, "1234")
+        .await
+        .unwrap();
+    assert_eq!(content, log.as_bytes());
+
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{server}/api/v1/cabinet/{}/item/{}/content?mode=file",
+            cabinet.code, items[0].id
+        ))
+        .header("accept-encoding", "gzip, zstd;q=0.9")
+        .json(&credential(&server).await)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["content-encoding"], "zstd");
+    let compressed = response.bytes().await.unwrap();
+    assert!(compressed.len() < log.len() / 10);
+    assert_eq!(zstd::decode_all(&compressed[..]).unwrap(), log.as_bytes());
+
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{server}/api/v1/cabinet/{}/item/{}/content?mode=file",
+            cabinet.code, items[1].id
+        ))
+        .header("accept-encoding", "zstd")
+        .json(&credential(&server).await)
+        .send()
+        .await
+        .unwrap();
+    assert!(response.headers().get("content-encoding").is_none());
+    assert!(response.headers().get("vary").is_none());
+    assert_eq!(response.bytes().await.unwrap(), noise);
+}
+
+/// Contents stored under the objects folder
+fn stored_objects(data_folder: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let objects = data_folder.join("files").join("objects");
+    std::fs::read_dir(objects)
+        .into_iter()
+        .flatten()
+        .flat_map(|prefix| std::fs::read_dir(prefix.unwrap().path()).unwrap())
+        .map(|object| object.unwrap().path())
+        .collect()
+}
+
+#[tokio::test]
+async fn identical_contents_are_stored_once() {
+    use domain::entity::cabinet::CabinetItem;
+
+    let mut data_folder = std::path::PathBuf::new();
+    let server = serve_state(10, |state| data_folder = state.data_folder.clone()).await;
+    let client = Client::new(&server);
+    let installer = b"MZ installer".repeat(100);
+    let first = client.apply().await.unwrap();
+    let request = SaveRequest::new("1234")
+        .message("for the team")
+        .file("setup.exe", installer.clone());
+    client.save(&first, request).await.unwrap();
+    let second = client.apply().await.unwrap();
+    let request = SaveRequest::new("5678").file("installer.exe", installer.clone());
+    client.save(&second, request).await.unwrap();
+
+    let first_items = client.items(first.code, "1234").await.unwrap();
+    let second_items = client.items(second.code, "5678").await.unwrap();
+    let hash = CabinetItem::content_hash(&installer);
+    assert_eq!(first_items[1].hash.as_deref(), Some(hash.as_str()));
+    assert_eq!(second_items[0].hash.as_deref(), Some(hash.as_str()));
+    let objects = stored_objects(&data_folder);
+    assert_eq!(objects.len(), 2);
+    assert!(
+        objects.contains(
+            &data_folder
+                .join("files")
+                .join("objects")
+                .join(&hash[..2])
+                .join(&hash[2..])
+        )
+    );
+
+    client.delete(first.code, "1234").await.unwrap();
+    assert_eq!(stored_objects(&data_folder).len(), 1);
+    let content = client
+        .download(second.code, second_items[0].id, "5678")
+        .await
+        .unwrap();
+    assert_eq!(content, installer);
+
+    client.delete(second.code, "5678").await.unwrap();
+    assert!(stored_objects(&data_folder).is_empty());
+}
+
+#[tokio::test]
+async fn downloads_are_checked_against_their_checksum() {
+    let mut data_folder = std::path::PathBuf::new();
+    let server = serve_state(10, |state| data_folder = state.data_folder.clone()).await;
+    let client = Client::new(&server);
+    let cabinet = client.apply().await.unwrap();
+    let report = "quarterly report\n".repeat(64);
+    let request = SaveRequest::new("1234").file("report.txt", report.as_bytes());
+    client.save(&cabinet, request).await.unwrap();
+    let items = client.items(cabinet.code, "1234").await.unwrap();
+
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{server}/api/v1/cabinet/{}/item/{}/content?mode=file",
+            cabinet.code, items[0].id
+        ))
+        .header("accept-encoding", "gzip")
+        .json(&credential(&server).await)
+        .send()
+        .await
+        .unwrap();
+    let digest = "ZSyY84VBgP+gtKekkzbs/cmTEV70POw5TdXQPklNRlE=";
+    assert_eq!(
+        response.headers()["repr-digest"],
+        format!("sha-256=:{digest}:")
+    );
+    assert_eq!(response.headers()["digest"], format!("sha-256={digest}"));
+    // Compressing would break the digest
+    assert!(response.headers().get("content-encoding").is_none());
+    assert_eq!(response.bytes().await.unwrap(), report.as_bytes());
+
+    let object = stored_objects(&data_folder).pop().unwrap();
+    let mut stored = std::fs::read(&object).unwrap();
+    stored[0] ^= 1;
+    std::fs::write(&object, stored).unwrap();
+    let response = content(&server, cabinet.code, items[0].id, "file").await;
+    assert_eq!(response.status(), 500);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "Cabinet::ItemCorrupted");
+    assert!(
+        body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("report.txt")
+    );
+}
+
+/// Ustar header of a regular file, modified at 2025-10-09 08:53:20 UTC
+fn tar_header(path: &str, size: u64) -> [u8; 512] {
+    let mut header = [0; 512];
+    header[..path.len()].copy_from_slice(path.as_bytes());
+    header[100..108].copy_from_slice(b"0000644\0");
+    header[124..136].copy_from_slice(format!("{size:011o}\0").as_bytes());
+    header[136..148].copy_from_slice(format!("{:011o}\0", 1_760_000_000).as_bytes());
+    header[156] = b'0';
+    header[257..265].copy_from_slice(b"ustar\x0000");
+    header[148..156].fill(b' ');
+    let sum = header.iter().map(|&byte| u32::from(byte)).sum::<u32>();
+    header[148..156].copy_from_slice(format!("{sum:06o}\0 ").as_bytes());
+    header
+}
+
+/// Zip archive storing `files` uncompressed, modified on 2026-10-19 at 12:30
+fn zip_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let time = (12u16 << 11) | (30 << 5);
+    let date = ((2026u16 - 1980) << 9) | (10 << 5) | 19;
+    let mut archive = Vec::new();
+    let mut directory = Vec::new();
+    for (name, content) in files {
+        let offset = archive.len() as u32;
+        let crc = crc32fast::hash(content);
+        let mut fields = Vec::new();
+        for value in [10u16, 0, 0, time, date] {
+            fields.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [crc, content.len() as u32, content.len() as u32] {
+            fields.extend_from_slice(&value.to_le_bytes());
+        }
+        fields.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes());
+        archive.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        archive.extend_from_slice(&fields);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(content);
+        directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        directory.extend_from_slice(&10u16.to_le_bytes());
+        directory.extend_from_slice(&fields);
+        directory.extend_from_slice(&[0; 10]);
+        directory.extend_from_slice(&offset.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+    }
+    let directory_offset = archive.len() as u32;
+    archive.extend_from_slice(&directory);
+    archive.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    archive.extend_from_slice(&[0; 4]);
+    archive.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&directory_offset.to_le_bytes());
+    archive.extend_from_slice(&[0; 2]);
+    archive
+}
+
+#[tokio::test]
+async fn archive_entries_are_listed_and_extracted() {
+    use std::io::Write;
+
+    let client = serve(10).await;
+    let config = b"[server]\nport = 8765\n";
+    let mut tar = Vec::new();
+    tar.extend_from_slice(&tar_header("app/config.toml", config.len() as u64));
+    tar.extend_from_slice(config);
+    tar.resize(1024, 0);
+    // Would land outside the folder it is extracted to
+    tar.extend_from_slice(&tar_header("../../.bashrc", 0));
+    // Claims more than is ever extracted, its data is not even there
+    tar.extend_from_slice(&tar_header("huge.bin", 300 * 1024 * 1024));
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&tar).unwrap();
+    let zip = zip_archive(&[
+        ("docs/readme.txt", b"read me first"),
+        ("/etc/cron.d/job", b"* * * * * root true"),
+    ]);
+    let cabinet = client.apply().await.unwrap();
+    let request = SaveRequest::new("1234")
+        .message("not an archive")
+        .file("app.tar.gz", encoder.finish().unwrap())
+        .file("docs.zip", zip);
+    client.save(&cabinet, request).await.unwrap();
+    let items = client.items(cabinet.code, "1234").await.unwrap();
+
+    let entries = client
+        .entries(cabinet.code, items[1].id, "1234")
+        .await
+        .unwrap();
+    let paths = entries.iter().map(|entry| &entry.path).collect::<Vec<_>>();
+    assert_eq!(paths, ["app/config.toml", "huge.bin"]);
+    assert_eq!(entries[0].size, config.len() as u64);
+    assert_eq!(entries[0].modified.unwrap().timestamp(), 1_760_000_000);
+    let content = client
+        .extract(cabinet.code, items[1].id, "app/config.toml", "1234")
+        .await
+        .unwrap();
+    assert_eq!(content, config);
+    let error = client
+        .extract(cabinet.code, items[1].id, "huge.bin", "1234")
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Some("Cabinet::ArchiveTooLarge"));
+    let error = client
+        .extract(cabinet.code, items[1].id, "../../.bashrc", "1234")
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Some("Cabinet::ArchiveEntryNotFound"));
+    // The code names the variant, the path is left to the message
+    assert!(error.is(&CabinetError::ArchiveEntryNotFound(String::new()).into()));
+    assert!(error.to_string().contains("../../.bashrc"));
+
+    let entries = client
+        .entries(cabinet.code, items[2].id, "1234")
+        .await
+        .unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].path, "docs/readme.txt");
+    let modified = entries[0].modified.unwrap().naive_local();
+    assert_eq!(modified.to_string(), "2026-10-19 12:30:00");
+    let content = client
+        .extract(cabinet.code, items[2].id, "docs/readme.txt", "1234")
+        .await
+        .unwrap();
+    assert_eq!(content, b"read me first");
+
+    let error = client
+        .entries(cabinet.code, items[0].id, "1234")
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Some("Cabinet::InvalidArchive"));
+}
+
+#[tokio::test]
+async fn archive_entries_are_streamed_to_concurrent_downloads() {
+    use std::io::Write;
+
+    let client = serve(10).await;
+    // Larger than any upload, it only fits compressed
+    let data = (0..3 * 1024 * 1024)
+        .map(|i| (i % 251) as u8)
+        .collect::<Vec<_>>();
+    let mut tar = tar_header("data.bin", data.len() as u64).to_vec();
+    tar.extend_from_slice(&data);
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&tar).unwrap();
+    let mut zip = zip_archive(&[("notes.txt", b"checked before sending")]);
+    // Breaks its CRC, the entry is refused before anything is sent
+    let at = zip
+        .windows(7)
+        .position(|window| window == b"checked")
+        .unwrap();
+    zip[at] = b'C';
+    let cabinet = client.apply().await.unwrap();
+    let request = SaveRequest::new("1234")
+        .file("data.tar.gz", encoder.finish().unwrap())
+        .file("notes.zip", zip);
+    client.save(&cabinet, request).await.unwrap();
+    let items = client.items(cabinet.code, "1234").await.unwrap();
+
+    // More than are extracted at once, the others wait for a slot
+    let downloads = (0..8)
+        .map(|_| {
+            let client = client.clone();
+            let (code, id) = (cabinet.code, items[0].id);
+            tokio::spawn(async move { client.extract(code, id, "data.bin", "1234").await })
+        })
+        .collect::<Vec<_>>();
+    for download in downloads {
+        assert!(download.await.unwrap().unwrap() == data);
+    }
+
+    let error = client
+        .extract(cabinet.code, items[1].id, "notes.txt", "1234")
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Some("Cabinet::InvalidArchive"));
+}
+
+/// Every route of the v1 api, keep in step with `interface::router`
+const V1_ROUTES: &[(&str, &str)] = &[
+    ("get", "/api/v1/ping"),
+    ("post", "/api/v1/cabinet/apply"),
+    ("get", "/api/v1/cabinet/usage"),
+    ("get", "/api/v1/cabinet/{cabinet_code}"),
+    ("post", "/api/v1/cabinet/{cabinet_code}"),
+    ("delete", "/api/v1/cabinet/{cabinet_code}"),
+    ("post", "/api/v1/cabinet/{cabinet_code}/items"),
+    (
+        "post",
+        "/api/v1/cabinet/{cabinet_code}/item/{item_id}/content",
+    ),
+    (
+        "get",
+        "/api/v1/cabinet/{cabinet_code}/item/{item_id}/thumbnail",
+    ),
+    (
+        "post",
+        "/api/v1/cabinet/{cabinet_code}/item/{item_id}/entries",
+    ),
+    (
+        "post",
+        "/api/v1/cabinet/{cabinet_code}/item/{item_id}/entry",
+    ),
+    ("get", "/api/v1/crypto/pk"),
+];
+
+#[tokio::test]
+async fn openapi_documents_every_v1_route() {
+    let server = serve_url(10).await;
+    let http = reqwest::Client::new();
+
+    let response = http
+        .get(format!("{server}/api/openapi.json"))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let document: serde_json::Value = response.json().await.unwrap();
+    let mut documented = document["paths"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .filter(|(path, _)| path.starts_with("/api/v1/"))
+        .flat_map(|(path, item)| {
+            item.as_object()
+                .unwrap()
+                .keys()
+                .map(move |method| (method.clone(), path.clone()))
+        })
+        .collect::<Vec<_>>();
+    documented.sort();
+    let mut expected = V1_ROUTES
+        .iter()
+        .map(|(method, path)| (method.to_string(), path.to_string()))
+        .collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(documented, expected);
+
+    // A route the router does not know answers with an empty 404 or a 405,
+    // handlers answer with an envelope
+    for (method, path) in V1_ROUTES {
+        let url = format!(
+            "{server}{}",
+            path.replace("{cabinet_code}", "100000")
+                .replace("{item_id}", "1")
+        );
+        let method = reqwest::Method::from_bytes(method.to_uppercase().as_bytes()).unwrap();
+        let response = http.request(method, &url).send().await.unwrap();
+        assert_ne!(
+            response.status(),
+            reqwest::StatusCode::METHOD_NOT_ALLOWED,
+            "{url}"
+        );
+        let body = response.text().await.unwrap();
+        assert!(
+            body.starts_with(r#"{"data""#) || body.starts_with(r#"{"error""#),
+            "{url}: {body}"
+        );
+    }
+}
+
+/// Value of an unlabelled sample in a Prometheus exposition
+fn sample(exposition: &str, name: &str) -> Option<f64> {
+    exposition.lines().find_map(|line| {
+        let value = line.strip_prefix(name)?.strip_prefix(' ')?;
+        value.parse().ok()
+    })
+}
+
+#[tokio::test]
+async fn metrics_are_exposed() {
+    // The recorder is process wide, another test may have installed it already
+    let _ = interface::metrics::install();
+    let server = serve_url(10).await;
+    let client = Client::new(server.clone());
+
+    let cabinet = client.apply().await.unwrap();
+    let request = SaveRequest::new("1234").file("a.bin", vec![7u8; 3000]);
+    client.save(&cabinet, request).await.unwrap();
+    let items = client.items(cabinet.code, "1234").await.unwrap();
+    let response = content(&server, cabinet.code, items[0].id, "file").await;
+    assert_eq!(response.headers()["content-length"], "3000");
+    assert_eq!(response.bytes().await.unwrap().len(), 3000);
+    let archive = client.apply().await.unwrap();
+    let zip = zip_archive(&[("a.txt", b"a")]);
+    let stored = 3000 + zip.len();
+    let request = SaveRequest::new("1234").file("docs.zip", zip);
+    client.save(&archive, request).await.unwrap();
+    let entries = client.items(archive.code, "1234").await.unwrap();
+    for path in ["missing-one.txt", "missing-two.txt"] {
+        let error = client
+            .extract(archive.code, entries[0].id, path, "1234")
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), Some("Cabinet::ArchiveEntryNotFound"));
+    }
+
+    let response = reqwest::get(format!("{server}/metrics")).await.unwrap();
+    assert!(response.status().is_success());
+    assert!(
+        response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain")
+    );
+    let exposition = response.text().await.unwrap();
+    assert_eq!(sample(&exposition, "cabinet_capacity"), Some(10.0));
+    assert_eq!(sample(&exposition, "cabinet_occupied"), Some(2.0));
+    assert_eq!(
+        sample(&exposition, "cabinet_stored_bytes"),
+        Some(stored as f64)
+    );
+    // Other tests download too, the counter is shared by the process
+    let downloaded = sample(&exposition, "cabinet_download_bytes_total").unwrap();
+    assert!(downloaded >= 3000.0, "{downloaded}");
+    assert!(
+        exposition.contains(r#"route="/api/v1/cabinet/{cabinet_code}/item/{item_id}/content""#)
+    );
+    assert!(exposition.contains(r#"cabinet_downloads_total{outcome="success"}"#));
+    // Errors are counted by variant, what the request held never becomes a label
+    let series = exposition
+        .lines()
+        .filter(|line| line.starts_with("cabinet_errors_total{"))
+        .filter(|line| line.contains("ArchiveEntryNotFound"))
+        .collect::<Vec<_>>();
+    assert_eq!(series.len(), 1, "{series:?}");
+    let label = r#"cabinet_errors_total{code="Cabinet::ArchiveEntryNotFound"}"#;
+    assert!(sample(&exposition, label).unwrap() >= 2.0);
+    assert!(!exposition.contains("missing-"));
+}
+
+#[tokio::test]
+async fn readiness_probes_run_concurrently_until_draining() {
+    let mut state = None;
+    let server = serve_state(10, |s| state = Some(s.clone())).await;
+    let state = state.unwrap();
+
+    let probes = (0..20).map(|_| {
+        let url = format!("{server}/readyz");
+        tokio::spawn(async move { reqwest::get(url).await.unwrap() })
+    });
+    for probe in probes {
+        let response = probe.await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let report: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(report["checks"]["data_folder"]["ok"], true);
+    }
+    let leftovers = std::fs::read_dir(&state.data_folder)
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(".readyz"))
+        .count();
+    assert_eq!(leftovers, 0);
+
+    state.start_draining();
+    let response = reqwest::get(format!("{server}/readyz")).await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["status"], "not_ready");
+    assert_eq!(report["checks"]["draining"]["ok"], false);
+}
+
+#[tokio::test]
+async fn thumbnails_are_awaited_on_shutdown() {
+    let mut state = None;
+    let server = serve_state(10, |s| state = Some(s.clone())).await;
+    let state = state.unwrap();
+    let client = Client::new(&server);
+
+    let mut request = SaveRequest::new("1234");
+    for index in 0..6u8 {
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbImage::from_pixel(640, 480, image::Rgb([index * 40, 30, 30]))
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        request = request.file(format!("{index}.png"), png.into_inner());
+    }
+    let cabinet = client.apply().await.unwrap();
+    client.save(&cabinet, request).await.unwrap();
+    state.wait_background_tasks().await;
+
+    for item in client.items(cabinet.code, "1234").await.unwrap() {
+        // Keys are single use
+        let credential = credential(&server).await;
+        let response = reqwest::Client::new()
+            .get(format!(
+                "{server}/api/v1/cabinet/{}/item/{}/thumbnail",
+                cabinet.code, item.id
+            ))
+            .query(&credential)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK, "{}", item.name);
+    }
+}
+
+#[tokio::test]
+async fn credentials_are_checked_before_scanning() {
+    use infrastructure::clamd::ClamdScanner;
+
+    // A scan would fail, so any scan before the checks shows up as ScannerUnavailable
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address: infrastructure::clamd::ClamdAddress =
+        listener.local_addr().unwrap().to_string().parse().unwrap();
+    drop(listener);
+    let server = serve_state(10, move |state| {
+        let scanner = ClamdScanner::new(address, std::time::Duration::from_secs(5));
+        state.scanner = Some(std::sync::Arc::new(scanner));
+    })
+    .await;
+    let client = Client::new(server);
+
+    let mut cabinet = client.apply().await.unwrap();
+    cabinet.hold_token = Some("not-the-token".to_string());
+    let error = client
+        .save(&cabinet, SaveRequest::new("1234").message("hello"))
+        .await
+        .unwrap_err();
+    assert!(error.is(&CabinetError::NotYourHoldCabinet(cabinet.code).into()));
+
+    cabinet.code = 100000;
+    let error = client
+        .save(&cabinet, SaveRequest::new("1234").message("hello"))
+        .await
+        .unwrap_err();
+    assert!(error.is(&CabinetError::NotFound.into()));
+}
+
+#[tokio::test]
+async fn links_are_capped_and_fetched_within_a_deadline() {
+    use domain::service::link::{MAX_LINKS, TitleFetcher};
+
+    /// A fetcher whose pages never answer
+    struct Hanging;
+
+    #[async_trait::async_trait]
+    impl TitleFetcher for Hanging {
+        async fn fetch_title(&self, _: &url::Url) -> std::io::Result<Option<String>> {
+            tokio::time::sleep(std::time::Duration::from_secs(600)).await;
+            Ok(Some(String::from("Too late")))
+        }
+    }
+
+    let server = serve_state(10, |state| {
+        state.title_fetcher = Some(std::sync::Arc::new(Hanging));
+    })
+    .await;
+    let client = Client::new(&server);
+
+    let cabinet = client.apply().await.unwrap();
+    let mut request = SaveRequest::new("1234");
+    for index in 0..=MAX_LINKS {
+        request = request.link(format!("https://example.com/{index}"));
+    }
+    let error = client.save(&cabinet, request).await.unwrap_err();
+    assert!(error.is(&CabinetError::TooManyLinks(MAX_LINKS).into()));
+
+    let start = std::time::Instant::now();
+    let request = SaveRequest::new("1234")
+        .link("https://example.com/a")
+        .link("https://example.org/b");
+    client.save(&cabinet, request).await.unwrap();
+    assert!(start.elapsed() < std::time::Duration::from_secs(30));
+    let items = client.items(cabinet.code, "1234").await.unwrap();
+    let names = items.iter().map(|i| i.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["example.com", "example.org"]);
+}
+
+#[tokio::test]
+async fn link_titles_are_not_fetched_through_ipv4_tunnels() {
+    use domain::service::link::TitleFetcher;
+    use infrastructure::link_title::HttpTitleFetcher;
+
+    let fetcher = HttpTitleFetcher::new(std::time::Duration::from_secs(5));
+    for url in [
+        // 6to4 of 127.0.0.1
+        "http://[2002:7f00:1::1]/",
+        // Teredo
+        "http://[2001:0:4136:e378:8000:63bf:3fff:fdd2]/",
+    ] {
+        let error = fetcher
+            .fetch_title(&url::Url::parse(url).unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::PermissionDenied, "{url}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn shared_contents_survive_concurrent_saves_and_deletes() {
+    use domain::entity::cabinet::{CabinetItem, CabinetItemCategory};
+    use domain::repository::cabinet::CabinetItemRepository as _;
+    use infrastructure::repository::cabinet::CabinetItemRepository;
+
+    let mut state = None;
+    serve_state(10, |s| state = Some(s.clone())).await;
+    let state = state.unwrap();
+    let repository = std::sync::Arc::new(CabinetItemRepository::new(
+        state.connection.clone(),
+        state.data_folder.join("files"),
+    ));
+    let shared = b"shared release notes".repeat(500);
+    let item = |id: i64| {
+        CabinetItem::new(
+            id,
+            1,
+            CabinetItemCategory::File,
+            String::from("notes.txt"),
+            shared.clone(),
+            id as i32,
+        )
+    };
+
+    for round in 0..100 {
+        // The last holder of the content goes away while another item saves it again
+        let (old, new) = (round * 2 + 1, round * 2 + 2);
+        repository.save(item(old)).await.unwrap();
+        let deleting = repository.clone();
+        let delete = tokio::spawn(async move { deleting.delete_by_id(old).await.unwrap() });
+        let saving = repository.clone();
+        let new_item = item(new);
+        let save = tokio::spawn(async move { saving.save(new_item).await.unwrap() });
+        delete.await.unwrap();
+        save.await.unwrap();
+
+        let saved = repository.find_by_id(new, false).await.unwrap().unwrap();
+        let content = repository.find_stored_content(&saved).await.unwrap();
+        assert_eq!(content, shared, "round {round}");
+        repository.delete_by_id(new).await.unwrap();
+        assert!(stored_objects(&state.data_folder).is_empty());
+    }
+}
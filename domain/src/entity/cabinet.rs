@@ -2,7 +2,7 @@
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, utoipa::ToSchema)]
 pub enum CabinetStatus {
     Vacant,
     Hold,
@@ -42,7 +42,7 @@ pub fn code(&self) -> i32 {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Cabinet {
     pub code: i64,
     pub name: Option<String>,
@@ -56,6 +56,25 @@ pub struct Cabinet {
     pub version: Option<i32>,
 }
 
+/// Password and hold token are redacted
+impl std::fmt::Debug for Cabinet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use super::redact;
+        f.debug_struct("Cabinet")
+            .field("code", &self.code)
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .field("password", &redact(&self.password))
+            .field("status", &self.status)
+            .field("hold_token", &redact(&self.hold_token))
+            .field("expire_at", &self.expire_at)
+            .field("create_at", &self.create_at)
+            .field("update_at", &self.update_at)
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
 impl Cabinet {
     pub fn new(
         code: i64,
@@ -80,10 +99,11 @@ pub fn new(
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub enum CabinetItemCategory {
     Text,
     File,
+    Link,
 }
 
 impl FromStr for CabinetItemCategory {
@@ -93,6 +113,7 @@ fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "text" => Ok(CabinetItemCategory::Text),
             "file" => Ok(CabinetItemCategory::File),
+            "link" => Ok(CabinetItemCategory::Link),
             _ => Err(crate::error::cabinet::CabinetError::InvalidItemCategory(
                 s.to_string(),
             ))?,
@@ -105,6 +126,7 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             CabinetItemCategory::File => write!(f, "file"),
             CabinetItemCategory::Text => write!(f, "text"),
+            CabinetItemCategory::Link => write!(f, "link"),
         }
     }
 }
@@ -118,9 +140,29 @@ pub struct CabinetItem {
     pub content: Option<Vec<u8>>,
     pub size: i64,
     pub sort_order: i32,
+    /// Detected content type, e.g. `image/png`
+    pub mime_type: String,
+    /// Syntax language of a text snippet, e.g. `rust`
+    pub language: Option<String>,
+    /// Target of a link, e.g. `https://example.com/`
+    pub url: Option<String>,
+    /// Encoding the content is stored with, e.g. `zstd`, none when stored as is
+    pub encoding: Option<String>,
+    /// SHA-256 of the content, lowercase hex
+    pub hash: Option<String>,
 }
 
 impl CabinetItem {
+    /// Items a cabinet holds at most, their IDs are numbered within the cabinet's code
+    pub const MAX_ITEMS: usize = 999;
+
+    /// ID of the item at `order` of a cabinet, counted from 1
+    ///
+    /// IDs of different cabinets never meet as long as `order` stays within [`Self::MAX_ITEMS`].
+    pub fn item_id(cabinet_code: i64, order: i64) -> i64 {
+        cabinet_code * 1000 + order
+    }
+
     pub fn new(
         id: i64,
         cabinet_code: i64,
@@ -129,6 +171,7 @@ pub fn new(
         content: Vec<u8>,
         sort_order: i32,
     ) -> Self {
+        let mime_type = Self::detect_mime_type(&category, &name, &content);
         CabinetItem {
             id,
             cabinet_code,
@@ -137,23 +180,173 @@ pub fn new(
             size: content.len() as i64,
             content: Some(content),
             sort_order,
+            mime_type,
+            language: None,
+            url: None,
+            encoding: None,
+            hash: None,
+        }
+    }
+
+    /// A link item named after its host, its content is the address
+    ///
+    /// `url` is expected to be validated already, see [`crate::service::link::parse`].
+    pub fn link(id: i64, cabinet_code: i64, url: &url::Url, sort_order: i32) -> Self {
+        let name = url.host_str().unwrap_or_default().to_string();
+        let mut item = Self::new(
+            id,
+            cabinet_code,
+            CabinetItemCategory::Link,
+            name,
+            url.as_str().as_bytes().to_vec(),
+            sort_order,
+        );
+        item.url = Some(url.to_string());
+        item
+    }
+
+    /// Content type by magic bytes, then by extension
+    ///
+    /// Messages are always UTF-8 text, links a URI list. Files with an unknown type are plain text when they are
+    /// valid UTF-8, otherwise `application/octet-stream`.
+    pub fn detect_mime_type(category: &CabinetItemCategory, name: &str, content: &[u8]) -> String {
+        match category {
+            CabinetItemCategory::Text => return String::from(TEXT_MIME_TYPE),
+            CabinetItemCategory::Link => return String::from(LINK_MIME_TYPE),
+            CabinetItemCategory::File => {}
+        }
+        if let Some(kind) = infer::get(content) {
+            return kind.mime_type().to_string();
+        }
+        if let Some(mime) = mime_guess::from_path(name).first() {
+            return match mime.type_() {
+                mime_guess::mime::TEXT => format!("{}; charset=utf-8", mime.essence_str()),
+                _ => mime.essence_str().to_string(),
+            };
+        }
+        if std::str::from_utf8(content).is_ok() {
+            String::from(TEXT_MIME_TYPE)
+        } else {
+            String::from(BINARY_MIME_TYPE)
         }
     }
+
+    /// SHA-256 of a content, lowercase hex
+    pub fn content_hash(content: &[u8]) -> String {
+        use sha2::Digest;
+        hex::encode(sha2::Sha256::digest(content))
+    }
+
+    /// Check a decoded content against the hash taken when the item was saved
+    ///
+    /// Items saved by older versions have no hash and always pass.
+    pub fn verify(&self, content: &[u8]) -> Result<(), crate::error::DomainError> {
+        match &self.hash {
+            Some(hash) if *hash != Self::content_hash(content) => {
+                log::error!("Content of item {} does not match its hash {hash}", self.id);
+                Err(crate::error::cabinet::CabinetError::ItemCorrupted(self.name.clone()).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether browsers can show the content themselves, without running anything
+    ///
+    /// SVG is left out, it may carry scripts.
+    pub fn is_previewable(&self) -> bool {
+        let essence = self.mime_type.split(';').next().unwrap_or_default().trim();
+        match essence.split_once('/') {
+            Some(("image", "svg+xml")) => false,
+            Some(("image" | "audio" | "video", _)) => true,
+            _ => essence == "application/pdf" || essence == "text/plain",
+        }
+    }
+
+    /// Make an uploaded file name safe to store and to save on any system
+    ///
+    /// - Directories are dropped, whether separated by `/` or `\`
+    /// - Control characters are removed, characters Windows forbids become `_`
+    /// - Trailing dots and spaces are trimmed, reserved Windows names get a `_` prefix
+    /// - The name is cut to 255 bytes, keeping the extension
+    pub fn sanitize_name(name: &str) -> String {
+        let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+        let name = name
+            .chars()
+            .filter(|c| !c.is_control())
+            .map(|c| match c {
+                '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+                c => c,
+            })
+            .collect::<String>();
+        let name = name.trim().trim_end_matches(['.', ' ']);
+        if name.is_empty() || name == "." || name == ".." {
+            return String::from("unknown");
+        }
+        let stem = name.split('.').next().unwrap_or_default();
+        let reserved = matches!(
+            stem.to_ascii_uppercase().as_bytes(),
+            b"CON"
+                | b"PRN"
+                | b"AUX"
+                | b"NUL"
+                | [b'C', b'O', b'M', b'0'..=b'9']
+                | [b'L', b'P', b'T', b'0'..=b'9']
+        );
+        let name = if reserved {
+            format!("_{name}")
+        } else {
+            name.to_string()
+        };
+        truncate_name(name, MAX_NAME_BYTES)
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+/// Content type of messages and of text files without a better match
+pub const TEXT_MIME_TYPE: &str = "text/plain; charset=utf-8";
+
+/// Content type of links
+pub const LINK_MIME_TYPE: &str = "text/uri-list";
+
+/// Content type of unknown binary content
+pub const BINARY_MIME_TYPE: &str = "application/octet-stream";
+
+/// Longest file name most file systems accept, in bytes
+const MAX_NAME_BYTES: usize = 255;
+
+/// Cut the stem of `name` to fit `max` bytes, on a character boundary
+fn truncate_name(name: String, max: usize) -> String {
+    if name.len() <= max {
+        return name;
+    }
+    let (stem, extension) = match name.rfind('.') {
+        // A long "extension" is part of the name
+        Some(dot) if dot > 0 && name.len() - dot <= 16 => name.split_at(dot),
+        _ => (name.as_str(), ""),
+    };
+    let mut end = max - extension.len();
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{extension}", &stem[..end])
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct CabinetUsage {
     pub total: u64,
     pub used: u64,
     pub free: u64,
+    /// Cabinets applied for but not saved yet
+    #[serde(default)]
+    pub held: u64,
 }
 
 impl CabinetUsage {
-    pub fn new(total: u64, used: u64) -> Self {
+    pub fn new(total: u64, used: u64, held: u64) -> Self {
         CabinetUsage {
             total,
             used,
             free: total - used,
+            held,
         }
     }
 }
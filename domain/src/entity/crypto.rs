@@ -1,6 +1,6 @@
 use chrono::{DateTime, Local};
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(PartialEq, Eq, Clone)]
 pub struct CryptoKeypair {
     pub id: Option<uuid::Uuid>,
     pub secret_key: String,
@@ -11,6 +11,21 @@ pub struct CryptoKeypair {
     pub version: Option<i32>,
 }
 
+/// Secret key is redacted
+impl std::fmt::Debug for CryptoKeypair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CryptoKeypair")
+            .field("id", &self.id)
+            .field("secret_key", &super::REDACTED)
+            .field("public_key", &self.public_key)
+            .field("expire_at", &self.expire_at)
+            .field("create_at", &self.create_at)
+            .field("update_at", &self.update_at)
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
 impl CryptoKeypair {
     pub fn new(secret_key: String, public_key: String, expire_at: DateTime<Local>) -> Self {
         CryptoKeypair {
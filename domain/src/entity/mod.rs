@@ -1,2 +1,10 @@
 pub mod cabinet;
 pub mod crypto;
+
+/// Placeholder printed instead of secrets in `Debug` output
+pub const REDACTED: &str = "***";
+
+/// `Debug` helper that hides the value of an optional secret
+pub fn redact<T>(value: &Option<T>) -> Option<&'static str> {
+    value.as_ref().map(|_| REDACTED)
+}
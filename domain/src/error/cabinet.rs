@@ -22,6 +22,17 @@ pub enum CabinetError {
     InvalidPassword,
     InvalidItemContent,
     ItemNotSupportMode(String),
+    ThumbnailNotFound,
+    InfectedItem(String, String),
+    ScannerUnavailable,
+    InvalidSnippet(usize),
+    InvalidLink(usize),
+    TooManyLinks(usize),
+    TooManyItems(usize),
+    ItemCorrupted(String),
+    InvalidArchive(String),
+    ArchiveTooLarge(String),
+    ArchiveEntryNotFound(String),
 }
 
 impl crate::error::I18nError for CabinetError {
@@ -128,6 +139,115 @@ fn i18n_message(&self, locale: super::Locale) -> Cow<'static, str> {
                     mode = mode
                 )
             }
+            CabinetError::ThumbnailNotFound => {
+                rust_i18n::t!("error.cabinet.thumbnail_not_found", locale = locale_text)
+            }
+            CabinetError::InfectedItem(filename, signature) => {
+                rust_i18n::t!(
+                    "error.cabinet.infected_item",
+                    locale = locale_text,
+                    filename = filename,
+                    signature = signature
+                )
+            }
+            CabinetError::ScannerUnavailable => {
+                rust_i18n::t!("error.cabinet.scanner_unavailable", locale = locale_text)
+            }
+            CabinetError::InvalidSnippet(index) => {
+                rust_i18n::t!(
+                    "error.cabinet.invalid_snippet",
+                    locale = locale_text,
+                    index = index
+                )
+            }
+            CabinetError::InvalidLink(index) => {
+                rust_i18n::t!(
+                    "error.cabinet.invalid_link",
+                    locale = locale_text,
+                    index = index
+                )
+            }
+            CabinetError::TooManyLinks(max) => {
+                rust_i18n::t!(
+                    "error.cabinet.too_many_links",
+                    locale = locale_text,
+                    max = max
+                )
+            }
+            CabinetError::TooManyItems(max) => {
+                rust_i18n::t!(
+                    "error.cabinet.too_many_items",
+                    locale = locale_text,
+                    max = max
+                )
+            }
+            CabinetError::ItemCorrupted(filename) => {
+                rust_i18n::t!(
+                    "error.cabinet.item_corrupted",
+                    locale = locale_text,
+                    filename = filename
+                )
+            }
+            CabinetError::InvalidArchive(filename) => {
+                rust_i18n::t!(
+                    "error.cabinet.invalid_archive",
+                    locale = locale_text,
+                    filename = filename
+                )
+            }
+            CabinetError::ArchiveTooLarge(filename) => {
+                rust_i18n::t!(
+                    "error.cabinet.archive_too_large",
+                    locale = locale_text,
+                    filename = filename
+                )
+            }
+            CabinetError::ArchiveEntryNotFound(path) => {
+                rust_i18n::t!(
+                    "error.cabinet.archive_entry_not_found",
+                    locale = locale_text,
+                    path = path
+                )
+            }
+        }
+    }
+}
+
+impl CabinetError {
+    /// Stable code of the error, its variant without the values it holds
+    pub fn code(&self) -> &'static str {
+        match self {
+            CabinetError::StatusNotSupport(..) => "Cabinet::StatusNotSupport",
+            CabinetError::NoAvailableCabinet => "Cabinet::NoAvailableCabinet",
+            CabinetError::InvalidItemCategory(..) => "Cabinet::InvalidItemCategory",
+            CabinetError::NotFound => "Cabinet::NotFound",
+            CabinetError::NoEmptyCabinet => "Cabinet::NoEmptyCabinet",
+            CabinetError::ItemContentMustNotEmpty => "Cabinet::ItemContentMustNotEmpty",
+            CabinetError::CabinetItemNotFound => "Cabinet::CabinetItemNotFound",
+            CabinetError::InvalidTextSize(..) => "Cabinet::InvalidTextSize",
+            CabinetError::InvalidFileSize(..) => "Cabinet::InvalidFileSize",
+            CabinetError::InvalidTotalSize(..) => "Cabinet::InvalidTotalSize",
+            CabinetError::InvalidNumberString(..) => "Cabinet::InvalidNumberString",
+            CabinetError::InvalidHours(..) => "Cabinet::InvalidHours",
+            CabinetError::PasswordRequired => "Cabinet::PasswordRequired",
+            CabinetError::ExpireTimeRequired => "Cabinet::ExpireTimeRequired",
+            CabinetError::HoldTokenRequired => "Cabinet::HoldTokenRequired",
+            CabinetError::PublicKeyRequired => "Cabinet::PublicKeyRequired",
+            CabinetError::NotYourHoldCabinet(..) => "Cabinet::NotYourHoldCabinet",
+            CabinetError::InvalidPassword => "Cabinet::InvalidPassword",
+            CabinetError::InvalidItemContent => "Cabinet::InvalidItemContent",
+            CabinetError::ItemNotSupportMode(..) => "Cabinet::ItemNotSupportMode",
+            CabinetError::ThumbnailNotFound => "Cabinet::ThumbnailNotFound",
+            CabinetError::InfectedItem(..) => "Cabinet::InfectedItem",
+            CabinetError::ScannerUnavailable => "Cabinet::ScannerUnavailable",
+            CabinetError::InvalidSnippet(..) => "Cabinet::InvalidSnippet",
+            CabinetError::InvalidLink(..) => "Cabinet::InvalidLink",
+            CabinetError::TooManyLinks(..) => "Cabinet::TooManyLinks",
+            CabinetError::TooManyItems(..) => "Cabinet::TooManyItems",
+            CabinetError::ItemCorrupted(..) => "Cabinet::ItemCorrupted",
+            CabinetError::InvalidArchive(..) => "Cabinet::InvalidArchive",
+            CabinetError::ArchiveTooLarge(..) => "Cabinet::ArchiveTooLarge",
+            CabinetError::ArchiveEntryNotFound(..) => "Cabinet::ArchiveEntryNotFound",
         }
     }
 }
@@ -6,6 +6,7 @@ pub enum CryptoError {
     InvalidSecretKey,
     InvalidPublicKey,
     DecryptionFailed,
+    EncryptionFailed,
     InvalidHexString,
     KeypairExpired,
     NotFound,
@@ -31,6 +32,9 @@ fn i18n_message(&self, locale: super::Locale) -> Cow<'static, str> {
             CryptoError::DecryptionFailed => {
                 rust_i18n::t!("error.crypto.decryption_failed", locale = locale_text)
             }
+            CryptoError::EncryptionFailed => {
+                rust_i18n::t!("error.crypto.encryption_failed", locale = locale_text)
+            }
             CryptoError::InvalidHexString => {
                 rust_i18n::t!("error.crypto.invalid_hex_string", locale = locale_text)
             }
@@ -50,6 +54,23 @@ fn i18n_message(&self, locale: super::Locale) -> Cow<'static, str> {
     }
 }
 
+impl CryptoError {
+    /// Stable code of the error, its variant without the values it holds
+    pub fn code(&self) -> &'static str {
+        match self {
+            CryptoError::KeypairGenerationFailed => "Crypto::KeypairGenerationFailed",
+            CryptoError::InvalidSecretKey => "Crypto::InvalidSecretKey",
+            CryptoError::InvalidPublicKey => "Crypto::InvalidPublicKey",
+            CryptoError::DecryptionFailed => "Crypto::DecryptionFailed",
+            CryptoError::EncryptionFailed => "Crypto::EncryptionFailed",
+            CryptoError::InvalidHexString => "Crypto::InvalidHexString",
+            CryptoError::KeypairExpired => "Crypto::KeypairExpired",
+            CryptoError::NotFound => "Crypto::NotFound",
+            CryptoError::MaxKeypairCountReached => "Crypto::MaxKeypairCountReached",
+        }
+    }
+}
+
 impl std::error::Error for CryptoError {}
 
 impl std::fmt::Display for CryptoError {
@@ -69,6 +69,19 @@ fn i18n_message(&self, locale: Locale) -> Cow<'static, str> {
     }
 }
 
+impl DomainError {
+    /// Stable code of the error, e.g. `Cabinet::InvalidFileSize`, the values it holds are left to
+    /// the message
+    pub fn code(&self) -> &'static str {
+        match self {
+            DomainError::InternalError => "InternalError",
+            DomainError::LocaleNotSupported => "LocaleNotSupported",
+            DomainError::CabinetError(e) => e.code(),
+            DomainError::CryptoError(e) => e.code(),
+        }
+    }
+}
+
 impl std::error::Error for DomainError {}
 
 impl std::fmt::Display for DomainError {
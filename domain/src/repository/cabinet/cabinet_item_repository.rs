@@ -16,9 +16,31 @@ async fn find_by_id(
         with_content: bool,
     ) -> Result<Option<CabinetItem>, DomainError>;
 
+    /// Find the content of an item as stored, encoded with its `encoding`
+    async fn find_stored_content(&self, item: &CabinetItem) -> Result<Vec<u8>, DomainError>;
+
     /// Get cabinet item by cabinet code
     async fn list_by_cabinet_code(
         &self,
         cabinet_code: i64,
     ) -> Result<Vec<CabinetItem>, DomainError>;
+
+    /// Get every stored item, without content
+    async fn list_all(&self) -> Result<Vec<CabinetItem>, DomainError>;
+
+    /// Store the thumbnail of an item, skipped when the item is gone
+    async fn save_thumbnail(&self, item: &CabinetItem, thumbnail: &[u8])
+    -> Result<(), DomainError>;
+
+    /// Find the thumbnail of an item, `None` until it is generated
+    async fn find_thumbnail(&self, item: &CabinetItem) -> Result<Option<Vec<u8>>, DomainError>;
+
+    /// Get the total size of all cabinet items in bytes
+    async fn total_size(&self) -> Result<u64, DomainError>;
+
+    /// Delete content left behind by interrupted writes, returns the number of files
+    ///
+    /// Only files old enough that no write can still be running are deleted, the data folder may
+    /// be shared with other replicas.
+    async fn delete_temporary_files(&self) -> Result<u64, DomainError>;
 }
@@ -0,0 +1,596 @@
+use crate::entity::cabinet::CabinetItem;
+use crate::error::DomainError;
+use crate::error::cabinet::CabinetError;
+use crate::service::metadata::{u16_le, u32_le};
+use chrono::{DateTime, Local, TimeZone};
+use std::io::{Read, Write};
+
+/// Entries an archive may hold, listing more is refused
+const MAX_ENTRIES: usize = 10_000;
+
+/// Bytes decompressed out of a compressed tar at most, tars have no index to skip through
+const MAX_EXPANDED_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Largest entry extracted, a larger declared size is refused before inflating anything
+const MAX_ENTRY_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Largest GNU long name or pax header, in bytes
+const MAX_HEADER_SIZE: u64 = 64 * 1024;
+
+/// Entry of an archive, only regular files are listed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Relative, `/` separated path inside the archive
+    pub path: String,
+    /// Size once extracted, in bytes
+    pub size: u64,
+    /// Last modification time, when the archive records one
+    pub modified: Option<DateTime<Local>>,
+}
+
+/// Archive formats whose entries can be listed and extracted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarZst,
+}
+
+impl ArchiveFormat {
+    /// Format of an item by its detected content type, gzip and zstd files must hold a tar
+    pub fn detect(item: &CabinetItem, content: &[u8]) -> Option<Self> {
+        let essence = item.mime_type.split(';').next().unwrap_or_default().trim();
+        let format = match essence {
+            "application/zip" => Self::Zip,
+            "application/x-tar" => Self::Tar,
+            "application/gzip" => Self::TarGz,
+            "application/zstd" => Self::TarZst,
+            _ => return None,
+        };
+        if format == Self::Zip {
+            return Some(format);
+        }
+        let mut header = [0; 512];
+        format
+            .reader(content)
+            .ok()?
+            .read_exact(&mut header)
+            .ok()
+            .filter(|_| is_tar_header(&header))
+            .map(|_| format)
+    }
+
+    /// Content of a tar, decompressed on the fly
+    fn reader<'a>(&self, content: &'a [u8]) -> std::io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Self::Zip | Self::Tar => Box::new(content),
+            Self::TarGz => Box::new(flate2::read::MultiGzDecoder::new(content)),
+            Self::TarZst => Box::new(zstd::stream::read::Decoder::new(content)?),
+        })
+    }
+}
+
+/// Why an archive could not be read
+enum Failure {
+    Invalid,
+    TooLarge,
+    NotFound,
+}
+
+impl Failure {
+    fn into_error(self, item: &CabinetItem, path: &str) -> DomainError {
+        match self {
+            Failure::Invalid => CabinetError::InvalidArchive(item.name.clone()).into(),
+            Failure::TooLarge => CabinetError::ArchiveTooLarge(item.name.clone()).into(),
+            Failure::NotFound => CabinetError::ArchiveEntryNotFound(path.to_string()).into(),
+        }
+    }
+}
+
+impl From<std::io::Error> for Failure {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::FileTooLarge => Failure::TooLarge,
+            _ => Failure::Invalid,
+        }
+    }
+}
+
+/// List the files of an archive item without extracting them
+///
+/// Entries whose path could escape the folder they are extracted to are left out.
+pub fn list(item: &CabinetItem, content: &[u8]) -> Result<Vec<ArchiveEntry>, DomainError> {
+    let format = ArchiveFormat::detect(item, content)
+        .ok_or(CabinetError::InvalidArchive(item.name.clone()))?;
+    let result = match format {
+        ArchiveFormat::Zip => zip_entries(content)
+            .map(|entries| entries.into_iter().map(|entry| entry.entry).collect()),
+        _ => {
+            let mut entries = Vec::new();
+            walk_tar(format, content, |entry, _| {
+                entries.push(entry);
+                Ok(true)
+            })
+            .map(|_| entries)
+        }
+    };
+    result.map_err(|failure| failure.into_error(item, ""))
+}
+
+/// Extract the file at `path` out of an archive item into `output`, returning its size
+///
+/// Data is written as it is decompressed, a size or CRC mismatch is only reported once the whole
+/// entry went through.
+pub fn extract(
+    item: &CabinetItem,
+    content: &[u8],
+    path: &str,
+    output: &mut dyn Write,
+) -> Result<u64, DomainError> {
+    let format = ArchiveFormat::detect(item, content)
+        .ok_or(CabinetError::InvalidArchive(item.name.clone()))?;
+    let mut output = Checked::new(output);
+    let result = match format {
+        ArchiveFormat::Zip => extract_zip(content, path, &mut output),
+        _ => {
+            let mut found = false;
+            walk_tar(format, content, |entry, data| {
+                if entry.path != path {
+                    return Ok(true);
+                }
+                if entry.size > MAX_ENTRY_SIZE {
+                    return Err(Failure::TooLarge);
+                }
+                std::io::copy(data, &mut output)?;
+                if output.written != entry.size {
+                    return Err(Failure::Invalid);
+                }
+                found = true;
+                Ok(false)
+            })
+            .and_then(|_| found.then_some(()).ok_or(Failure::NotFound))
+        }
+    };
+    result
+        .and_then(|_| Ok(output.flush()?))
+        .map(|_| output.written)
+        .map_err(|failure| failure.into_error(item, path))
+}
+
+/// Writer counting and checksumming what goes through it
+struct Checked<W> {
+    inner: W,
+    written: u64,
+    crc: crc32fast::Hasher,
+}
+
+impl<W: Write> Checked<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            written: 0,
+            crc: crc32fast::Hasher::new(),
+        }
+    }
+}
+
+impl<W: Write> Write for Checked<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.crc.update(&buf[..written]);
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Relative, `/` separated form of an entry path, none when it could escape the folder it is
+/// extracted to
+///
+/// Absolute paths, drive letters and `..` are refused rather than fixed, an archive holding them
+/// was likely made to write somewhere else.
+fn safe_path(name: &str) -> Option<String> {
+    let name = name.replace('\\', "/");
+    if name.starts_with('/') {
+        return None;
+    }
+    let mut parts = Vec::new();
+    for part in name.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => return None,
+            part if part.chars().any(char::is_control) => return None,
+            part => parts.push(part),
+        }
+    }
+    let first = parts.first()?.as_bytes();
+    if first.len() == 2 && first[0].is_ascii_alphabetic() && first[1] == b':' {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+/// File of a zip archive, with where to find its data
+struct ZipEntry {
+    entry: ArchiveEntry,
+    flags: usize,
+    method: usize,
+    crc: u32,
+    compressed_size: usize,
+    local_offset: usize,
+}
+
+/// Files of a zip archive, read from its central directory
+///
+/// Split and ZIP64 archives are not read.
+fn zip_entries(data: &[u8]) -> Result<Vec<ZipEntry>, Failure> {
+    const END_OF_DIRECTORY: usize = 0x0605_4b50;
+
+    // The end record is last, followed by a comment of at most 64 KiB
+    let search_start = data.len().saturating_sub(22 + 0xFFFF);
+    let eocd = (search_start..=data.len().checked_sub(22).ok_or(Failure::Invalid)?)
+        .rev()
+        .find(|&at| u32_le(data, at) == Some(END_OF_DIRECTORY))
+        .ok_or(Failure::Invalid)?;
+    let disk = u16_le(data, eocd + 4).ok_or(Failure::Invalid)?;
+    let count = u16_le(data, eocd + 10).ok_or(Failure::Invalid)?;
+    let directory_offset = u32_le(data, eocd + 16).ok_or(Failure::Invalid)?;
+    if disk != 0 || count == 0xFFFF || directory_offset == 0xFFFF_FFFF {
+        return Err(Failure::Invalid);
+    }
+    if count > MAX_ENTRIES {
+        return Err(Failure::TooLarge);
+    }
+
+    let mut entries = Vec::new();
+    let mut pos = directory_offset;
+    for _ in 0..count {
+        let (entry, next) = zip_record(data, pos).ok_or(Failure::Invalid)?;
+        entries.extend(entry);
+        pos = next;
+    }
+    Ok(entries)
+}
+
+/// Central directory record at `pos` and where the next one starts
+///
+/// Directories and files whose path is not safe give no entry.
+fn zip_record(data: &[u8], pos: usize) -> Option<(Option<ZipEntry>, usize)> {
+    const CENTRAL_HEADER: usize = 0x0201_4b50;
+
+    if u32_le(data, pos)? != CENTRAL_HEADER {
+        return None;
+    }
+    let compressed_size = u32_le(data, pos + 20)?;
+    let size = u32_le(data, pos + 24)?;
+    let name_length = u16_le(data, pos + 28)?;
+    let extra_length = u16_le(data, pos + 30)?;
+    let comment_length = u16_le(data, pos + 32)?;
+    let local_offset = u32_le(data, pos + 42)?;
+    if [compressed_size, size, local_offset].contains(&0xFFFF_FFFF) {
+        return None;
+    }
+    let name = data.get(pos + 46..pos + 46 + name_length)?;
+    let next = pos + 46 + name_length + extra_length + comment_length;
+    // Directories end with a slash
+    if name.ends_with(b"/") {
+        return Some((None, next));
+    }
+    let name = String::from_utf8_lossy(name);
+    let Some(path) = safe_path(&name) else {
+        log::warn!("Left out zip entry '{name}'");
+        return Some((None, next));
+    };
+    let entry = ZipEntry {
+        entry: ArchiveEntry {
+            path,
+            size: size as u64,
+            modified: dos_time(u16_le(data, pos + 14)?, u16_le(data, pos + 12)?),
+        },
+        flags: u16_le(data, pos + 8)?,
+        method: u16_le(data, pos + 10)?,
+        crc: u32_le(data, pos + 16)? as u32,
+        compressed_size,
+        local_offset,
+    };
+    Some((Some(entry), next))
+}
+
+/// Extract a file of a zip archive, stored or deflated, checking its size and CRC
+///
+/// The size recorded in the directory is trusted only as a limit, inflating stops past it.
+fn extract_zip(
+    data: &[u8],
+    path: &str,
+    output: &mut Checked<&mut dyn Write>,
+) -> Result<(), Failure> {
+    const LOCAL_HEADER: usize = 0x0403_4b50;
+    const ENCRYPTED: usize = 0x0001;
+    const STORED: usize = 0;
+    const DEFLATED: usize = 8;
+
+    let entry = zip_entries(data)?
+        .into_iter()
+        .find(|entry| entry.entry.path == path)
+        .ok_or(Failure::NotFound)?;
+    if entry.flags & ENCRYPTED != 0 {
+        return Err(Failure::Invalid);
+    }
+    if entry.entry.size > MAX_ENTRY_SIZE {
+        return Err(Failure::TooLarge);
+    }
+    if u32_le(data, entry.local_offset) != Some(LOCAL_HEADER) {
+        return Err(Failure::Invalid);
+    }
+    let (Some(name_length), Some(extra_length)) = (
+        u16_le(data, entry.local_offset + 26),
+        u16_le(data, entry.local_offset + 28),
+    ) else {
+        return Err(Failure::Invalid);
+    };
+    let start = entry.local_offset + 30 + name_length + extra_length;
+    let body = data
+        .get(start..start + entry.compressed_size)
+        .ok_or(Failure::Invalid)?;
+    match entry.method {
+        STORED => output.write_all(body)?,
+        DEFLATED => {
+            std::io::copy(
+                &mut flate2::read::DeflateDecoder::new(body).take(entry.entry.size + 1),
+                output,
+            )?;
+        }
+        _ => return Err(Failure::Invalid),
+    }
+    if output.written != entry.entry.size || output.crc.clone().finalize() != entry.crc {
+        return Err(Failure::Invalid);
+    }
+    Ok(())
+}
+
+/// Local time of an MS-DOS date and time, as zip archives record them
+fn dos_time(date: usize, time: usize) -> Option<DateTime<Local>> {
+    let date = chrono::NaiveDate::from_ymd_opt(
+        1980 + (date >> 9) as i32,
+        ((date >> 5) & 0x0F) as u32,
+        (date & 0x1F) as u32,
+    )?;
+    let time = chrono::NaiveTime::from_hms_opt(
+        (time >> 11) as u32,
+        ((time >> 5) & 0x3F) as u32,
+        (time & 0x1F) as u32 * 2,
+    )?;
+    Local.from_local_datetime(&date.and_time(time)).earliest()
+}
+
+/// Reader failing once more than `remaining` bytes were read, a bomb can not run for ever
+struct Limited<R> {
+    inner: R,
+    remaining: u64,
+}
+
+impl<R: Read> Read for Limited<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.remaining = self
+            .remaining
+            .checked_sub(read as u64)
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::FileTooLarge))?;
+        Ok(read)
+    }
+}
+
+/// Call `visit` with every file of a tar and a reader of its data, until it returns false
+///
+/// GNU long names and pax paths, sizes and times are applied to the file they precede, global pax
+/// headers are skipped.
+fn walk_tar(
+    format: ArchiveFormat,
+    content: &[u8],
+    mut visit: impl FnMut(ArchiveEntry, &mut dyn Read) -> Result<bool, Failure>,
+) -> Result<(), Failure> {
+    let mut reader = Limited {
+        inner: format.reader(content)?,
+        remaining: MAX_EXPANDED_SIZE,
+    };
+    let mut header = [0; 512];
+    let mut long_name = None;
+    let mut pax = PaxHeader::default();
+    let mut count = 0;
+    loop {
+        if !read_block(&mut reader, &mut header)? {
+            // Some writers leave out the closing blocks
+            return Ok(());
+        }
+        if header.iter().all(|&byte| byte == 0) {
+            return Ok(());
+        }
+        if !is_tar_header(&header) {
+            return Err(Failure::Invalid);
+        }
+        let header_size = tar_number(&header[124..136]).ok_or(Failure::Invalid)?;
+        // Headers of the next file carry their own size, a pax size is not theirs
+        if let kind @ (b'L' | b'x' | b'g') = header[156] {
+            if header_size > MAX_HEADER_SIZE {
+                return Err(Failure::Invalid);
+            }
+            let mut value = Vec::new();
+            (&mut reader).take(header_size).read_to_end(&mut value)?;
+            if value.len() as u64 != header_size {
+                return Err(Failure::Invalid);
+            }
+            match kind {
+                b'L' => long_name = Some(tar_string(&value)),
+                b'x' => pax = PaxHeader::parse(&value),
+                // Global headers are not applied, nor do they end the headers of the next file
+                _ => {}
+            }
+            skip_padding(&mut reader, header_size)?;
+            continue;
+        }
+        let size = pax.size.unwrap_or(header_size);
+        let mut data = (&mut reader).take(size);
+        match header[156] {
+            // Regular files, old archives mark them with a NUL
+            b'0' | b'\0' | b'7' => {
+                count += 1;
+                if count > MAX_ENTRIES {
+                    return Err(Failure::TooLarge);
+                }
+                let name = pax
+                    .path
+                    .take()
+                    .or(long_name.take())
+                    .unwrap_or_else(|| ustar_name(&header));
+                let modified = pax
+                    .mtime
+                    .or_else(|| tar_number(&header[136..148]).map(|mtime| mtime as i64))
+                    .and_then(|mtime| Local.timestamp_opt(mtime, 0).single());
+                match safe_path(&name) {
+                    Some(path) => {
+                        let entry = ArchiveEntry {
+                            path,
+                            size,
+                            modified,
+                        };
+                        if !visit(entry, &mut data)? {
+                            return Ok(());
+                        }
+                    }
+                    None => log::warn!("Left out tar entry '{name}'"),
+                }
+            }
+            // Directories, links and devices
+            _ => {}
+        }
+        // Whatever `visit` left unread
+        std::io::copy(&mut data, &mut std::io::sink())?;
+        skip_padding(&mut reader, size)?;
+        long_name = None;
+        pax = PaxHeader::default();
+    }
+}
+
+/// Read a whole block, false at the end of the data, a block cut short is invalid
+fn read_block(reader: &mut impl Read, block: &mut [u8; 512]) -> Result<bool, Failure> {
+    let mut filled = 0;
+    while filled < block.len() {
+        match reader.read(&mut block[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    match filled {
+        0 => Ok(false),
+        512 => Ok(true),
+        _ => Err(Failure::Invalid),
+    }
+}
+
+/// Skip the zeros filling the data of a `size` bytes entry up to a whole block
+fn skip_padding(reader: &mut impl Read, size: u64) -> std::io::Result<()> {
+    let padding = (512 - size % 512) % 512;
+    std::io::copy(&mut reader.take(padding), &mut std::io::sink())?;
+    Ok(())
+}
+
+/// Whether a block is a tar header, by its checksum
+fn is_tar_header(header: &[u8; 512]) -> bool {
+    // The checksum is taken with its own field as spaces
+    let sum = header
+        .iter()
+        .enumerate()
+        .map(|(i, &byte)| match i {
+            148..156 => u64::from(b' '),
+            _ => u64::from(byte),
+        })
+        .sum::<u64>();
+    tar_number(&header[148..156]) == Some(sum)
+}
+
+/// Name of a tar header, with its ustar prefix
+fn ustar_name(header: &[u8; 512]) -> String {
+    let name = tar_string(&header[..100]);
+    if &header[257..262] != b"ustar" {
+        return name;
+    }
+    match tar_string(&header[345..500]) {
+        prefix if prefix.is_empty() => name,
+        prefix => format!("{prefix}/{name}"),
+    }
+}
+
+/// Text of a NUL padded field
+fn tar_string(field: &[u8]) -> String {
+    let end = field
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// Number of a header field, octal text or big-endian binary when the high bit is set
+fn tar_number(field: &[u8]) -> Option<u64> {
+    if field.first()? & 0x80 != 0 {
+        // GNU tar writes sizes over 8 GiB this way, more than 64 bits is refused
+        let mut field = field.to_vec();
+        field[0] &= 0x7F;
+        let (high, low) = field.split_at(field.len().saturating_sub(8));
+        if high.iter().any(|&byte| byte != 0) {
+            return None;
+        }
+        return Some(
+            low.iter()
+                .fold(0, |number, &byte| number << 8 | u64::from(byte)),
+        );
+    }
+    let text = std::str::from_utf8(field).ok()?;
+    let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+    if text.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(text, 8).ok()
+}
+
+/// Fields of a pax extended header that apply to the next file
+#[derive(Default)]
+struct PaxHeader {
+    path: Option<String>,
+    size: Option<u64>,
+    mtime: Option<i64>,
+}
+
+impl PaxHeader {
+    /// Read `<length> <key>=<value>\n` records, unknown keys are ignored
+    fn parse(data: &[u8]) -> Self {
+        let mut header = Self::default();
+        let mut rest = data;
+        while let Some(space) = rest.iter().position(|&byte| byte == b' ') {
+            let Some(length) = std::str::from_utf8(&rest[..space])
+                .ok()
+                .and_then(|length| length.parse::<usize>().ok())
+                .filter(|&length| length > space && length <= rest.len())
+            else {
+                break;
+            };
+            let record = String::from_utf8_lossy(&rest[space + 1..length]);
+            if let Some((key, value)) = record.trim_end_matches('\n').split_once('=') {
+                match key {
+                    "path" => header.path = Some(value.to_string()),
+                    "size" => header.size = value.parse().ok(),
+                    // Fractions of a second are dropped
+                    "mtime" => header.mtime = value.split('.').next().and_then(|s| s.parse().ok()),
+                    _ => {}
+                }
+            }
+            rest = &rest[length..];
+        }
+        header
+    }
+}
@@ -4,6 +4,17 @@
 use crate::repository::cabinet::{CabinetItemRepository, CabinetRepository};
 use chrono::Local;
 
+/// Outcome of checking every stored item against its hash
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    /// Items whose content matches their hash
+    pub verified: u64,
+    /// Items saved by older versions, without a hash to check
+    pub unhashed: u64,
+    /// Items that could not be read or do not match, with the reason
+    pub failed: Vec<(CabinetItem, DomainError)>,
+}
+
 pub struct CabinetService<CR, CIR>
 where
     CR: CabinetRepository,
@@ -34,6 +45,7 @@ impl<CR, CIR> CabinetService<CR, CIR>
     CIR: CabinetItemRepository,
 {
     /// Apply for a cabinet
+    #[tracing::instrument(skip_all)]
     pub async fn apply(&self) -> Result<Cabinet, DomainError> {
         let used = self
             .cabinet_repository
@@ -67,13 +79,9 @@ pub async fn apply(&self) -> Result<Cabinet, DomainError> {
         }
     }
 
-    /// Save items
-    pub async fn save(
-        &self,
-        cabinet: Cabinet,
-        items: Vec<CabinetItem>,
-    ) -> Result<Cabinet, DomainError> {
-        // Check params
+    /// Find the cabinet the requester holds, checking the parameters of a save
+    #[tracing::instrument(skip_all, fields(code = cabinet.code))]
+    pub async fn find_held(&self, cabinet: &Cabinet) -> Result<Cabinet, DomainError> {
         if cabinet.password.is_none() {
             return Err(CabinetError::PasswordRequired)?;
         }
@@ -84,10 +92,9 @@ pub async fn save(
             return Err(CabinetError::HoldTokenRequired)?;
         }
         let exists_cabinet = self.cabinet_repository.find_by_code(cabinet.code).await?;
-        if exists_cabinet.is_none() {
+        let Some(exists_cabinet) = exists_cabinet else {
             return Err(CabinetError::NotFound)?;
-        }
-        let mut exists_cabinet = exists_cabinet.unwrap();
+        };
 
         // Check status
         let is_hold = exists_cabinet.status == CabinetStatus::Hold;
@@ -95,14 +102,24 @@ pub async fn save(
             exists_cabinet.hold_token.is_some() && exists_cabinet.hold_token == cabinet.hold_token;
         if !is_hold || !is_your_hold {
             log::error!(
-                "Cabinet '{}' is not hold by {:?}: status: {}, token: {:?}",
+                "Cabinet '{}' is not hold by the requester: status: {}, token matches: {}",
                 exists_cabinet.code,
-                cabinet.hold_token,
                 exists_cabinet.status,
-                exists_cabinet.hold_token,
+                is_your_hold,
             );
             return Err(CabinetError::NotYourHoldCabinet(cabinet.code))?;
         }
+        Ok(exists_cabinet)
+    }
+
+    /// Save items
+    #[tracing::instrument(skip_all, fields(code = cabinet.code))]
+    pub async fn save(
+        &self,
+        cabinet: Cabinet,
+        items: Vec<CabinetItem>,
+    ) -> Result<Cabinet, DomainError> {
+        let mut exists_cabinet = self.find_held(&cabinet).await?;
 
         // Update cabinet
         exists_cabinet.status = CabinetStatus::Occupied;
@@ -125,6 +142,7 @@ pub async fn save(
     }
 
     /// Delete cabinet and items by code
+    #[tracing::instrument(skip_all, fields(cabinet_code = cabinet_code))]
     pub async fn delete_by_code(&self, cabinet_code: i64) -> Result<(), DomainError> {
         let exists = self.cabinet_repository.exists_by_code(cabinet_code).await?;
         if !exists {
@@ -150,6 +168,7 @@ pub async fn delete_by_code(&self, cabinet_code: i64) -> Result<(), DomainError>
     }
 
     /// Delete expired cabinets
+    #[tracing::instrument(skip_all)]
     pub async fn delete_expired(&self) -> Result<u64, DomainError> {
         let cabinets = self.cabinet_repository.list_expired(Local::now()).await?;
         let count = cabinets.len();
@@ -160,11 +179,13 @@ pub async fn delete_expired(&self) -> Result<u64, DomainError> {
     }
 
     /// Get cabinet by code
+    #[tracing::instrument(skip_all, fields(code = code))]
     pub async fn get_by_code(&self, code: i64) -> Result<Option<Cabinet>, DomainError> {
         self.cabinet_repository.find_by_code(code).await
     }
 
     /// Get non-none cabinet by code
+    #[tracing::instrument(skip_all, fields(code = code))]
     pub async fn get_nonnone_by_code(&self, code: i64) -> Result<Cabinet, DomainError> {
         let cabinet = self.cabinet_repository.find_by_code(code).await?;
         if cabinet.is_none() {
@@ -174,15 +195,33 @@ pub async fn get_nonnone_by_code(&self, code: i64) -> Result<Cabinet, DomainErro
     }
 
     /// Get the status of the cabinets
+    #[tracing::instrument(skip_all)]
     pub async fn usage(&self) -> Result<CabinetUsage, DomainError> {
         let used = self
             .cabinet_repository
             .count_by_status(CabinetStatus::Occupied)
             .await?;
-        Ok(CabinetUsage::new(self.cabinets_number, used))
+        let held = self
+            .cabinet_repository
+            .count_by_status(CabinetStatus::Hold)
+            .await?;
+        Ok(CabinetUsage::new(self.cabinets_number, used, held))
+    }
+
+    /// Delete content left behind by interrupted writes
+    #[tracing::instrument(skip_all)]
+    pub async fn delete_temporary_files(&self) -> Result<u64, DomainError> {
+        self.cabinet_item_repository.delete_temporary_files().await
+    }
+
+    /// Get the total size of the stored items in bytes
+    #[tracing::instrument(skip_all)]
+    pub async fn stored_bytes(&self) -> Result<u64, DomainError> {
+        self.cabinet_item_repository.total_size().await
     }
 
     /// Get all the items in a cabinet
+    #[tracing::instrument(skip_all, fields(code = code))]
     pub async fn list_items_by_cabinet_code(
         &self,
         code: i64,
@@ -196,7 +235,63 @@ pub async fn list_items_by_cabinet_code(
             .await
     }
 
+    /// Store the thumbnail generated for an item
+    #[tracing::instrument(skip_all, fields(item_id = item.id))]
+    pub async fn save_thumbnail(
+        &self,
+        item: &CabinetItem,
+        thumbnail: &[u8],
+    ) -> Result<(), DomainError> {
+        self.cabinet_item_repository
+            .save_thumbnail(item, thumbnail)
+            .await
+    }
+
+    /// Get the thumbnail of an item
+    #[tracing::instrument(skip_all, fields(item_id = item.id))]
+    pub async fn get_thumbnail(&self, item: &CabinetItem) -> Result<Vec<u8>, DomainError> {
+        self.cabinet_item_repository
+            .find_thumbnail(item)
+            .await?
+            .ok_or(CabinetError::ThumbnailNotFound.into())
+    }
+
+    /// Get the content of an item as stored, encoded with its `encoding`
+    #[tracing::instrument(skip_all, fields(item_id = item.id))]
+    pub async fn get_item_stored_content(
+        &self,
+        item: &CabinetItem,
+    ) -> Result<Vec<u8>, DomainError> {
+        self.cabinet_item_repository.find_stored_content(item).await
+    }
+
+    /// Read every stored item back and check it against its hash
+    ///
+    /// A failing item does not stop the scan, it is reported with the others.
+    #[tracing::instrument(skip_all)]
+    pub async fn verify_items(&self) -> Result<VerifyReport, DomainError> {
+        let mut report = VerifyReport::default();
+        for item in self.cabinet_item_repository.list_all().await? {
+            if item.hash.is_none() {
+                report.unhashed += 1;
+                continue;
+            }
+            let result = match self.cabinet_item_repository.find_by_id(item.id, true).await {
+                Ok(Some(found)) => found.verify(found.content.as_deref().unwrap_or_default()),
+                // Deleted since it was listed
+                Ok(None) => continue,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => report.verified += 1,
+                Err(e) => report.failed.push((item, e)),
+            }
+        }
+        Ok(report)
+    }
+
     /// Get a cabinet item by id
+    #[tracing::instrument(skip_all, fields(item_id = item_id))]
     pub async fn get_item_by_id(
         &self,
         item_id: i64,
@@ -0,0 +1,64 @@
+use crate::error::DomainError;
+
+/// Encoding of content stored compressed with zstd, also its `Content-Encoding` token
+pub const ZSTD: &str = "zstd";
+
+/// Content smaller than this is stored as is, the frame overhead eats the gain
+const MIN_SIZE: usize = 512;
+
+/// Bytes of each slice sampled to tell whether content compresses
+const SAMPLE_SIZE: usize = 16 * 1024;
+
+/// Slices sampled, spread over the content
+const SAMPLE_COUNT: usize = 4;
+
+/// Compress content with zstd at `level`, none when it is not worth it
+///
+/// Slices spread over the content are compressed first at the fastest level, content whose sample
+/// does not shrink by a tenth, e.g. images or archives, is left alone. Content that does not end
+/// up smaller is left alone too.
+pub fn compress(content: &[u8], level: i32) -> Option<Vec<u8>> {
+    if content.len() < MIN_SIZE {
+        return None;
+    }
+    let sample = if content.len() <= SAMPLE_SIZE * SAMPLE_COUNT {
+        content.to_vec()
+    } else {
+        let stride = (content.len() - SAMPLE_SIZE) / (SAMPLE_COUNT - 1);
+        (0..SAMPLE_COUNT)
+            .flat_map(|i| &content[i * stride..i * stride + SAMPLE_SIZE])
+            .copied()
+            .collect()
+    };
+    let compressed_sample = zstd::bulk::compress(&sample, 1).ok()?;
+    if compressed_sample.len() * 10 > sample.len() * 9 {
+        return None;
+    }
+    match zstd::bulk::compress(content, level) {
+        Ok(compressed) if compressed.len() < content.len() => Some(compressed),
+        Ok(_) => None,
+        Err(e) => {
+            log::warn!("Failed to compress content: {e}");
+            None
+        }
+    }
+}
+
+/// Decode stored content back to its `size` bytes, content without encoding is returned as is
+pub fn decompress(
+    encoding: Option<&str>,
+    content: Vec<u8>,
+    size: i64,
+) -> Result<Vec<u8>, DomainError> {
+    match encoding {
+        None => Ok(content),
+        Some(ZSTD) => zstd::bulk::decompress(&content, size.max(0) as usize).map_err(|e| {
+            log::error!("Failed to decompress content: {e}");
+            DomainError::InternalError
+        }),
+        Some(encoding) => {
+            log::error!("Unknown content encoding '{encoding}'");
+            Err(DomainError::InternalError)
+        }
+    }
+}
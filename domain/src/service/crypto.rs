@@ -21,6 +21,7 @@ pub fn new(crypto_keypair_repository: R, max_keypair_number: u64) -> Self {
 
 impl<R: CryptoKeypairRepository> Sm2CryptoService<R> {
     /// Generate a new ECIES keypair.
+    #[tracing::instrument(skip_all)]
     pub async fn generate_keypair(&self) -> Result<CryptoKeypair, DomainError> {
         use chrono::{Duration, Local};
 
@@ -46,24 +47,32 @@ pub async fn generate_keypair(&self) -> Result<CryptoKeypair, DomainError> {
             "Generated new SM2 keypair with public key '{}'",
             &keypair.public_key
         );
-        self.crypto_keypair_repository.save(keypair).await
+        let keypair = self.crypto_keypair_repository.save(keypair).await?;
+        metrics::counter!("cabinet_keypairs_generated_total").increment(1);
+        Ok(keypair)
     }
 
     /// Delete keypair by id
+    #[tracing::instrument(skip_all, fields(id = %id))]
     pub async fn delete_by_id(&self, id: uuid::Uuid) -> Result<(), DomainError> {
         log::debug!("Deleting keypair with id '{id}'");
         self.crypto_keypair_repository.delete_by_id(id).await
     }
 
     /// Delete expired keypairs
+    #[tracing::instrument(skip_all)]
     pub async fn delete_expired(&self) -> Result<u64, DomainError> {
         log::debug!("Deleting expired keypairs");
-        self.crypto_keypair_repository
+        let count = self
+            .crypto_keypair_repository
             .delete_expired(Local::now())
-            .await
+            .await?;
+        metrics::counter!("cabinet_keypairs_expired_total").increment(count);
+        Ok(count)
     }
 
     /// Get a keypair by its public key.
+    #[tracing::instrument(skip_all)]
     pub async fn get_by_public_key(
         &self,
         public_key: &str,
@@ -74,6 +83,7 @@ pub async fn get_by_public_key(
     }
 
     /// Get a effective keypair by its public key.
+    #[tracing::instrument(skip_all)]
     pub async fn get_effective_by_public_key(
         &self,
         public_key: &str,
@@ -108,7 +118,8 @@ pub fn pk2hex(pk: &gm_sm2::key::Sm2PublicKey) -> String {
 
 pub fn hex2sk(hex_str: &str) -> Result<gm_sm2::key::Sm2PrivateKey, CryptoError> {
     gm_sm2::key::Sm2PrivateKey::from_hex_string(hex_str).map_err(|e| {
-        log::error!("Invalid SM2 secret key '{hex_str}': {e:?}");
+        // Never log the key itself
+        log::error!("Invalid SM2 secret key ({} chars): {e:?}", hex_str.len());
         CryptoError::InvalidSecretKey
     })
 }
@@ -127,6 +138,22 @@ pub fn hex2bytes(hex_str: &str) -> Result<Vec<u8>, CryptoError> {
     })
 }
 
+pub fn encrypt(pk: &gm_sm2::key::Sm2PublicKey, data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    pk.encrypt(data, false, gm_sm2::key::Sm2Model::C1C3C2)
+        .map_err(|e| {
+            log::error!("Failed to encrypt data: {e:?}");
+            CryptoError::EncryptionFailed
+        })
+}
+
+pub fn encrypt_plaintext_to_hex(
+    pk: &gm_sm2::key::Sm2PublicKey,
+    plaintext: &str,
+) -> Result<String, CryptoError> {
+    let encrypted_data = encrypt(pk, plaintext.as_bytes())?;
+    Ok(hex::encode(encrypted_data))
+}
+
 pub fn decrypt(
     sk: &gm_sm2::key::Sm2PrivateKey,
     encrypted_data: &[u8],
@@ -0,0 +1,387 @@
+//! Syntax highlighting of text snippets as standalone HTML
+
+/// Longest language tag
+const MAX_LANGUAGE_LEN: usize = 32;
+
+/// Lexical rules of a language, enough to tell comments, strings, numbers and keywords apart
+struct Syntax {
+    /// Name and aliases, e.g. `javascript` and `js`
+    names: &'static [&'static str],
+    /// Separated by spaces
+    keywords: &'static str,
+    literals: &'static str,
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    /// Keywords match in any case
+    ignore_case: bool,
+    /// `'a` is a lifetime or label, not the start of a string
+    lifetimes: bool,
+}
+
+const C_KEYWORDS: &str = "auto break case char const continue default do double else enum extern \
+    float for goto if inline int long register return short signed sizeof static struct switch \
+    typedef union unsigned void volatile while";
+
+const JS_KEYWORDS: &str = "abstract as async await break case catch class const continue default \
+    delete do else enum export extends finally for from function if implements import in \
+    instanceof interface let new of private public return static switch this throw try type \
+    typeof var void while yield";
+
+const JS_LITERALS: &str = "true false null undefined NaN";
+
+const SYNTAXES: &[Syntax] = &[
+    Syntax {
+        names: &["rust", "rs"],
+        keywords: "as async await break const continue crate dyn else enum extern fn for if impl \
+            in let loop match mod move mut pub ref return self Self static struct super trait \
+            type unsafe use where while",
+        literals: "true false None Some Ok Err",
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        ignore_case: false,
+        lifetimes: true,
+    },
+    Syntax {
+        names: &["python", "py"],
+        keywords: "and as assert async await break class continue def del elif else except \
+            finally for from global if import in is lambda nonlocal not or pass raise return try \
+            while with yield",
+        literals: "True False None",
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        ignore_case: false,
+        lifetimes: false,
+    },
+    Syntax {
+        names: &["javascript", "js", "jsx", "mjs", "cjs"],
+        keywords: JS_KEYWORDS,
+        literals: JS_LITERALS,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        ignore_case: false,
+        lifetimes: false,
+    },
+    Syntax {
+        names: &["typescript", "ts", "tsx"],
+        keywords: JS_KEYWORDS,
+        literals: JS_LITERALS,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        ignore_case: false,
+        lifetimes: false,
+    },
+    Syntax {
+        names: &["json", "jsonc"],
+        keywords: "",
+        literals: "true false null",
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+        ignore_case: false,
+        lifetimes: false,
+    },
+    Syntax {
+        names: &["shell", "sh", "bash", "zsh", "console"],
+        keywords: "case do done elif else esac export fi for function if in local readonly return \
+            select then until while",
+        literals: "true false",
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        ignore_case: false,
+        lifetimes: false,
+    },
+    Syntax {
+        names: &["sql", "mysql", "postgresql", "sqlite"],
+        keywords: "add all alter and as asc begin between by case column commit create delete \
+            desc distinct drop else end exists from group having in index inner insert into is \
+            join key left like limit not on or order outer primary references right rollback \
+            select set table then union update values when where with",
+        literals: "true false null",
+        line_comments: &["--"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['\''],
+        ignore_case: true,
+        lifetimes: false,
+    },
+    Syntax {
+        names: &["yaml", "yml"],
+        keywords: "",
+        literals: "true false null yes no on off",
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        ignore_case: false,
+        lifetimes: false,
+    },
+    Syntax {
+        names: &["toml", "ini", "conf", "properties", "env", "dotenv"],
+        keywords: "",
+        literals: "true false",
+        line_comments: &["#", ";"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        ignore_case: false,
+        lifetimes: false,
+    },
+    Syntax {
+        names: &["c", "h"],
+        keywords: C_KEYWORDS,
+        literals: "NULL true false",
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        ignore_case: false,
+        lifetimes: false,
+    },
+    Syntax {
+        names: &["cpp", "c++", "cc", "hpp", "cxx"],
+        keywords: "auto bool break case catch char class const constexpr continue default delete \
+            do double else enum explicit extern float for friend if inline int long namespace new \
+            operator private protected public return short sizeof static struct switch template \
+            this throw try typedef typename union unsigned using virtual void while",
+        literals: "true false nullptr NULL",
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        ignore_case: false,
+        lifetimes: false,
+    },
+    Syntax {
+        names: &["java", "kotlin", "kt", "csharp", "cs"],
+        keywords: "abstract break case catch class const continue default do else enum extends \
+            final finally for fun if implements import interface namespace new override package \
+            private protected public return static super switch this throw throws try using val \
+            var void when while",
+        literals: "true false null",
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        ignore_case: false,
+        lifetimes: false,
+    },
+    Syntax {
+        names: &["go", "golang"],
+        keywords: "break case chan const continue default defer else fallthrough for func go goto \
+            if import interface map package range return select struct switch type var",
+        literals: "true false nil iota",
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        ignore_case: false,
+        lifetimes: false,
+    },
+    Syntax {
+        names: &["css", "scss", "less"],
+        keywords: "important media import keyframes supports",
+        literals: "",
+        line_comments: &[],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        ignore_case: true,
+        lifetimes: false,
+    },
+    Syntax {
+        names: &["html", "xml", "svg", "vue"],
+        keywords: "",
+        literals: "",
+        line_comments: &[],
+        block_comment: Some(("<!--", "-->")),
+        quotes: &['"', '\''],
+        ignore_case: false,
+        lifetimes: false,
+    },
+    Syntax {
+        names: &["dockerfile", "docker"],
+        keywords: "add arg cmd copy entrypoint env expose from healthcheck label onbuild run \
+            shell stopsignal user volume workdir",
+        literals: "",
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        ignore_case: true,
+        lifetimes: false,
+    },
+];
+
+/// Stylesheet of the rendered page, follows the reader's color scheme
+const STYLE: &str = "body{margin:0;background:#fafafa;color:#24292f} \
+    pre{margin:0;padding:16px;font:13px/1.5 ui-monospace,SFMono-Regular,Menlo,Consolas,monospace; \
+    white-space:pre-wrap;word-break:break-word} \
+    .hl-comment{color:#6e7781;font-style:italic}.hl-string{color:#0a3069} \
+    .hl-number,.hl-literal{color:#0550ae}.hl-keyword{color:#cf222e} \
+    @media(prefers-color-scheme:dark){body{background:#0d1117;color:#c9d1d9} \
+    .hl-comment{color:#8b949e}.hl-string{color:#a5d6ff}.hl-number,.hl-literal{color:#79c0ff} \
+    .hl-keyword{color:#ff7b72}}";
+
+/// Lowercase a language tag, `None` when it is not a plausible tag
+///
+/// Unknown but well formed tags are kept and rendered without highlighting.
+pub fn normalize_language(tag: &str) -> Option<String> {
+    let tag = tag.trim().to_ascii_lowercase();
+    let plausible = !tag.is_empty()
+        && tag.len() <= MAX_LANGUAGE_LEN
+        && tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '#' | '-' | '_' | '.'));
+    plausible.then_some(tag)
+}
+
+/// Render a snippet as a standalone HTML page, highlighted when the language is known
+///
+/// The page has no scripts and only inline styles, so it can be served under a sandboxing
+/// Content-Security-Policy that allows `style-src 'unsafe-inline'`.
+pub fn render_html(name: &str, language: Option<&str>, code: &str) -> String {
+    let syntax = language.and_then(find_syntax);
+    let mut html = String::with_capacity(code.len() * 2 + STYLE.len() + 256);
+    html.push_str("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>");
+    escape_into(&mut html, name);
+    html.push_str("</title><style>");
+    html.push_str(STYLE);
+    html.push_str("</style></head><body><pre><code");
+    if let Some(language) = language {
+        html.push_str(" class=\"language-");
+        escape_into(&mut html, language);
+        html.push('"');
+    }
+    html.push('>');
+    match syntax {
+        Some(syntax) => highlight_into(&mut html, syntax, code),
+        None => escape_into(&mut html, code),
+    }
+    html.push_str("</code></pre></body></html>\n");
+    html
+}
+
+fn find_syntax(language: &str) -> Option<&'static Syntax> {
+    SYNTAXES
+        .iter()
+        .find(|syntax| syntax.names.contains(&language))
+}
+
+fn escape_into(html: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            _ => html.push(c),
+        }
+    }
+}
+
+fn span_into(html: &mut String, class: &str, text: &str) {
+    html.push_str("<span class=\"hl-");
+    html.push_str(class);
+    html.push_str("\">");
+    escape_into(html, text);
+    html.push_str("</span>");
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Split the code into tokens, one pass from left to right
+fn highlight_into(html: &mut String, syntax: &Syntax, code: &str) {
+    let mut rest = code;
+    // Whether the previous character continues a word, `x1` is not a number
+    let mut after_word = false;
+    while let Some(c) = rest.chars().next() {
+        if let Some(prefix) = syntax.line_comments.iter().find(|p| rest.starts_with(**p)) {
+            let end = rest[prefix.len()..]
+                .find('\n')
+                .map_or(rest.len(), |i| prefix.len() + i);
+            span_into(html, "comment", &rest[..end]);
+            rest = &rest[end..];
+            after_word = false;
+            continue;
+        }
+        if let Some((start, stop)) = syntax.block_comment
+            && rest.starts_with(start)
+        {
+            let end = rest[start.len()..]
+                .find(stop)
+                .map_or(rest.len(), |i| start.len() + i + stop.len());
+            span_into(html, "comment", &rest[..end]);
+            rest = &rest[end..];
+            after_word = false;
+            continue;
+        }
+        if syntax.quotes.contains(&c) && !(syntax.lifetimes && c == '\'' && is_lifetime(rest)) {
+            let end = string_end(rest, c);
+            span_into(html, "string", &rest[..end]);
+            rest = &rest[end..];
+            after_word = false;
+            continue;
+        }
+        if c.is_ascii_digit() && !after_word {
+            let end = rest
+                .find(|c: char| !(is_word(c) || c == '.'))
+                .unwrap_or(rest.len());
+            span_into(html, "number", &rest[..end]);
+            rest = &rest[end..];
+            after_word = true;
+            continue;
+        }
+        if is_word(c) {
+            let end = rest.find(|c: char| !is_word(c)).unwrap_or(rest.len());
+            let word = &rest[..end];
+            let matches = |words: &str| {
+                words.split_ascii_whitespace().any(|w| {
+                    if syntax.ignore_case {
+                        w.eq_ignore_ascii_case(word)
+                    } else {
+                        w == word
+                    }
+                })
+            };
+            if matches(syntax.keywords) {
+                span_into(html, "keyword", word);
+            } else if matches(syntax.literals) {
+                span_into(html, "literal", word);
+            } else {
+                escape_into(html, word);
+            }
+            rest = &rest[end..];
+            after_word = true;
+            continue;
+        }
+        escape_into(html, &rest[..c.len_utf8()]);
+        rest = &rest[c.len_utf8()..];
+        after_word = false;
+    }
+}
+
+/// `'a` in `&'a str`, but not `'a'`
+fn is_lifetime(rest: &str) -> bool {
+    let mut chars = rest.chars().skip(1);
+    match (chars.next(), chars.next()) {
+        (Some(c), Some(next)) => (c.is_alphabetic() || c == '_') && next != '\'',
+        _ => false,
+    }
+}
+
+/// End of the string starting at `rest`, backslash escapes the next character
+///
+/// Backtick strings span lines, others end at the line end when unterminated.
+fn string_end(rest: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in rest.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '\n' if quote != '`' => return i,
+            _ if c == quote => return i + c.len_utf8(),
+            _ => {}
+        }
+    }
+    rest.len()
+}
@@ -0,0 +1,137 @@
+use crate::entity::cabinet::{CabinetItem, CabinetItemCategory};
+
+/// Longest link accepted, in bytes
+pub const MAX_URL_LENGTH: usize = 2048;
+
+/// Most links saved at once, each may cost a title fetch
+pub const MAX_LINKS: usize = 10;
+
+/// Longest title kept, in characters, the name column holds 200
+const MAX_TITLE_CHARS: usize = 200;
+
+/// Parse a shared link, only absolute `http` and `https` addresses with a host are accepted
+///
+/// Credentials are refused, `https://bank.example@evil.example` would show the wrong host.
+pub fn parse(text: &str) -> Option<url::Url> {
+    let text = text.trim();
+    if text.is_empty() || text.len() > MAX_URL_LENGTH {
+        return None;
+    }
+    let url = url::Url::parse(text).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return None;
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        return None;
+    }
+    Some(url)
+}
+
+/// Host of a link as shown to the one opening it, with the port when it is not the default
+///
+/// Internationalized hosts stay in punycode, lookalike characters can not pass for another host.
+pub fn host(url: &str) -> Option<String> {
+    let url = url::Url::parse(url).ok()?;
+    let host = url.host_str()?;
+    match url.port() {
+        Some(port) => Some(format!("{host}:{port}")),
+        None => Some(host.to_string()),
+    }
+}
+
+/// Fetcher of the title of a linked page
+#[async_trait::async_trait]
+pub trait TitleFetcher: Send + Sync {
+    /// Title of the page, none when it has none, an error when it could not be fetched
+    async fn fetch_title(&self, url: &url::Url) -> std::io::Result<Option<String>>;
+}
+
+/// Name every link after the title of its page, links keep their host when it can not be fetched
+pub async fn fetch_titles(fetcher: &dyn TitleFetcher, items: &mut [CabinetItem]) {
+    for item in items {
+        if item.category != CabinetItemCategory::Link {
+            continue;
+        }
+        let Some(url) = item.url.as_deref().and_then(parse) else {
+            continue;
+        };
+        match fetcher.fetch_title(&url).await {
+            Ok(Some(title)) => {
+                log::debug!("Link '{url}' is titled '{title}'");
+                metrics::counter!("cabinet_link_titles_total", "outcome" => "fetched").increment(1);
+                item.name = title;
+            }
+            Ok(None) => {
+                metrics::counter!("cabinet_link_titles_total", "outcome" => "missing").increment(1);
+            }
+            Err(e) => {
+                log::warn!("Failed to fetch the title of '{url}': {e}");
+                metrics::counter!("cabinet_link_titles_total", "outcome" => "failed").increment(1);
+            }
+        }
+    }
+}
+
+/// Text of the first `<title>` of an HTML page
+///
+/// Entities are decoded, whitespace is collapsed and the title is cut to 200 characters.
+pub fn extract_title(html: &str) -> Option<String> {
+    // ASCII lowercasing keeps byte offsets, so they index `html` too
+    let lower = html.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = decode_entities(&html[start..end])
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_TITLE_CHARS)
+        .collect::<String>();
+    (!title.is_empty()).then_some(title)
+}
+
+/// Decode the named entities titles commonly hold and numeric ones, others are kept as is
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let code = match entity.strip_prefix('#')? {
+                    hex if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16),
+                    decimal => decimal.parse::<u32>(),
+                };
+                code.ok().and_then(char::from_u32)
+            }
+        });
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
@@ -0,0 +1,654 @@
+use crate::entity::cabinet::CabinetItem;
+
+/// Office documents whose properties are replaced, with the parts holding them
+const DOCUMENT_PARTS: &[(&str, &[(&str, &str)])] = &[
+    (
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        OOXML_PARTS,
+    ),
+    (
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        OOXML_PARTS,
+    ),
+    (
+        "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        OOXML_PARTS,
+    ),
+    ("application/vnd.oasis.opendocument.text", ODF_PARTS),
+    ("application/vnd.oasis.opendocument.spreadsheet", ODF_PARTS),
+    ("application/vnd.oasis.opendocument.presentation", ODF_PARTS),
+];
+
+/// Author, company, dates and custom properties of Office Open XML
+const OOXML_PARTS: &[(&str, &str)] = &[
+    (
+        "docProps/core.xml",
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties"/>"#,
+    ),
+    (
+        "docProps/app.xml",
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/extended-properties"/>"#,
+    ),
+    (
+        "docProps/custom.xml",
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/custom-properties"/>"#,
+    ),
+];
+
+/// Author, dates and statistics of OpenDocument
+const ODF_PARTS: &[(&str, &str)] = &[(
+    "meta.xml",
+    r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-meta xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" office:version="1.2"/>"#,
+)];
+
+/// Strip metadata from the item content, returns the number of bytes removed
+///
+/// Images lose EXIF, XMP, IPTC and text chunks, a JPEG keeps its orientation so it still shows
+/// upright. Office documents get empty properties, PDFs an empty information dictionary and no
+/// XMP. Other types hold no metadata to strip. `None` when the content could not be stripped and
+/// is stored unchanged.
+pub fn strip(item: &mut CabinetItem) -> Option<usize> {
+    let Some(content) = item.content.as_ref() else {
+        return Some(0);
+    };
+    let stripped = match item.mime_type.as_str() {
+        "image/jpeg" => strip_jpeg(content),
+        "image/png" => strip_png(content),
+        "image/webp" => strip_webp(content),
+        "application/pdf" => strip_pdf(content),
+        mime_type => match document_parts(mime_type, &item.name) {
+            Some(parts) => strip_zip(content, parts),
+            None => return Some(0),
+        },
+    };
+    let Some(stripped) = stripped else {
+        log::warn!(
+            "Failed to strip '{}' as {}, metadata kept",
+            item.name,
+            item.mime_type
+        );
+        return None;
+    };
+    // PDFs are blanked in place, their cross-reference table holds byte offsets
+    let removed = if stripped.len() == content.len() {
+        content.iter().zip(&stripped).filter(|(a, b)| a != b).count()
+    } else {
+        content.len().saturating_sub(stripped.len())
+    };
+    log::debug!("Stripped {removed} bytes of metadata from '{}'", item.name);
+    item.size = stripped.len() as i64;
+    item.content = Some(stripped);
+    Some(removed)
+}
+
+/// Parts to replace in a document, a zip whose layout was not recognized goes by its extension
+fn document_parts(mime_type: &str, name: &str) -> Option<&'static [(&'static str, &'static str)]> {
+    let mime_type = match mime_type {
+        "application/zip" => mime_guess::from_path(name).first_raw()?,
+        mime_type => mime_type,
+    };
+    DOCUMENT_PARTS
+        .iter()
+        .find(|(m, _)| *m == mime_type)
+        .map(|(_, parts)| *parts)
+}
+
+fn u16_be(data: &[u8], at: usize) -> Option<usize> {
+    let bytes = data.get(at..at + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+}
+
+fn u32_be(data: &[u8], at: usize) -> Option<usize> {
+    let bytes = data.get(at..at + 4)?;
+    Some(u32::from_be_bytes(bytes.try_into().ok()?) as usize)
+}
+
+pub(crate) fn u16_le(data: &[u8], at: usize) -> Option<usize> {
+    let bytes = data.get(at..at + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+}
+
+pub(crate) fn u32_le(data: &[u8], at: usize) -> Option<usize> {
+    let bytes = data.get(at..at + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?) as usize)
+}
+
+/// Drop APP1 (EXIF, XMP), APP13 (IPTC), comments, unknown APPn and anything after the image
+fn strip_jpeg(data: &[u8]) -> Option<Vec<u8>> {
+    const EXIF_HEADER: &[u8] = b"Exif\0\0";
+
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut output = Vec::with_capacity(data.len());
+    output.extend_from_slice(&data[..2]);
+    let mut orientation = None;
+    let mut pos = 2;
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        // Any number of 0xFF may pad a marker
+        while *data.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        let marker = data[pos + 1];
+        match marker {
+            // End of image, trailing data such as appended pictures is dropped
+            0xD9 => {
+                output.extend_from_slice(&[0xFF, 0xD9]);
+                break;
+            }
+            0x01 | 0xD0..=0xD7 => {
+                output.extend_from_slice(&data[pos..pos + 2]);
+                pos += 2;
+                continue;
+            }
+            _ => {}
+        }
+        let length = u16_be(data, pos + 2)?;
+        if length < 2 {
+            return None;
+        }
+        let end = pos + 2 + length;
+        let segment = data.get(pos..end)?;
+        let payload = &segment[4..];
+        let keep = match marker {
+            // JFIF, ICC profile but not the multi-picture index, Adobe color transform
+            0xE0 | 0xEE => true,
+            0xE2 => !payload.starts_with(b"MPF\0"),
+            0xE1 => {
+                if let Some(exif) = payload.strip_prefix(EXIF_HEADER) {
+                    orientation = image::metadata::Orientation::from_exif_chunk(exif);
+                }
+                false
+            }
+            0xE3..=0xED | 0xEF | 0xFE => false,
+            _ => true,
+        };
+        if keep {
+            // The application segments come first, the orientation closes them
+            if !(0xE0..=0xEF).contains(&marker) {
+                write_orientation(&mut output, orientation.take());
+            }
+            output.extend_from_slice(segment);
+        }
+        pos = end;
+        if marker == 0xDA {
+            // Entropy coded data runs until a marker other than stuffing or a restart
+            let start = pos;
+            while *data.get(pos)? != 0xFF || matches!(data.get(pos + 1)?, 0x00 | 0xD0..=0xD7 | 0xFF)
+            {
+                pos += 1;
+            }
+            output.extend_from_slice(&data[start..pos]);
+        }
+    }
+    Some(output)
+}
+
+/// Write an EXIF segment holding only the orientation, unless the image is upright
+fn write_orientation(output: &mut Vec<u8>, orientation: Option<image::metadata::Orientation>) {
+    let Some(orientation) = orientation else {
+        return;
+    };
+    let value = orientation.to_exif();
+    if value == 1 {
+        return;
+    }
+    #[rustfmt::skip]
+    let exif: [u8; 32] = [
+        b'E', b'x', b'i', b'f', 0, 0,
+        // Big endian TIFF header, first IFD at 8
+        b'M', b'M', 0, 42, 0, 0, 0, 8,
+        // One entry: orientation, SHORT, count 1
+        0, 1, 0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, value, 0, 0,
+        // No next IFD
+        0, 0, 0, 0,
+    ];
+    output.extend_from_slice(&[0xFF, 0xE1, 0, exif.len() as u8 + 2]);
+    output.extend_from_slice(&exif);
+}
+
+/// Drop text, EXIF and time chunks, other chunks are copied as they are
+fn strip_png(data: &[u8]) -> Option<Vec<u8>> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    const DROPPED: &[&[u8]] = &[b"tEXt", b"zTXt", b"iTXt", b"eXIf", b"tIME"];
+
+    if !data.starts_with(SIGNATURE) {
+        return None;
+    }
+    let mut output = Vec::with_capacity(data.len());
+    output.extend_from_slice(SIGNATURE);
+    let mut pos = SIGNATURE.len();
+    loop {
+        let length = u32_be(data, pos)?;
+        let kind = data.get(pos + 4..pos + 8)?;
+        // Length, type, data and CRC
+        let end = pos.checked_add(length)?.checked_add(12)?;
+        let chunk = data.get(pos..end)?;
+        if !DROPPED.contains(&kind) {
+            output.extend_from_slice(chunk);
+        }
+        if kind == b"IEND" {
+            break;
+        }
+        pos = end;
+    }
+    Some(output)
+}
+
+/// Drop the EXIF and XMP chunks and clear their flags in the extended header
+fn strip_webp(data: &[u8]) -> Option<Vec<u8>> {
+    const EXIF_FLAG: u8 = 0x08;
+    const XMP_FLAG: u8 = 0x04;
+
+    if data.get(..4)? != b"RIFF" || data.get(8..12)? != b"WEBP" {
+        return None;
+    }
+    let riff_end = 8usize.checked_add(u32_le(data, 4)?)?.min(data.len());
+    let mut output = Vec::with_capacity(data.len());
+    output.extend_from_slice(&data[..12]);
+    let mut pos = 12;
+    while pos + 8 <= riff_end {
+        let kind = &data[pos..pos + 4];
+        let length = u32_le(data, pos + 4)?;
+        // Chunks are padded to an even size
+        let end = pos.checked_add(8 + length + (length & 1))?.min(riff_end);
+        let chunk = data.get(pos..end)?;
+        match kind {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" => {
+                let start = output.len();
+                output.extend_from_slice(chunk);
+                *output.get_mut(start + 8)? &= !(EXIF_FLAG | XMP_FLAG);
+            }
+            _ => output.extend_from_slice(chunk),
+        }
+        pos = end;
+    }
+    let riff_size = u32::try_from(output.len() - 8).ok()?;
+    output[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Some(output)
+}
+
+/// Rewrite a zip archive with the given parts replaced, other entries are copied as they are
+///
+/// Encrypted, split and ZIP64 archives are not rewritten.
+fn strip_zip(data: &[u8], parts: &[(&str, &str)]) -> Option<Vec<u8>> {
+    const LOCAL_HEADER: u32 = 0x0403_4b50;
+    const CENTRAL_HEADER: u32 = 0x0201_4b50;
+    const END_OF_DIRECTORY: u32 = 0x0605_4b50;
+    const ENCRYPTED: usize = 0x0001;
+    const DATA_DESCRIPTOR: usize = 0x0008;
+
+    // The end record is last, followed by a comment of at most 64 KiB
+    let search_start = data.len().saturating_sub(22 + 0xFFFF);
+    let eocd = (search_start..=data.len().checked_sub(22)?)
+        .rev()
+        .find(|&at| u32_le(data, at) == Some(END_OF_DIRECTORY as usize))?;
+    let disk = u16_le(data, eocd + 4)?;
+    let entries = u16_le(data, eocd + 10)?;
+    let directory_offset = u32_le(data, eocd + 16)?;
+    if disk != 0 || entries == 0xFFFF || directory_offset == 0xFFFF_FFFF {
+        return None;
+    }
+
+    let mut output = Vec::with_capacity(data.len());
+    let mut directory = Vec::new();
+    let mut pos = directory_offset;
+    for _ in 0..entries {
+        if u32_le(data, pos)? != CENTRAL_HEADER as usize {
+            return None;
+        }
+        let flags = u16_le(data, pos + 8)?;
+        let compressed_size = u32_le(data, pos + 20)?;
+        let name_length = u16_le(data, pos + 28)?;
+        let extra_length = u16_le(data, pos + 30)?;
+        let comment_length = u16_le(data, pos + 32)?;
+        let local_offset = u32_le(data, pos + 42)?;
+        if flags & ENCRYPTED != 0 || compressed_size == 0xFFFF_FFFF || local_offset == 0xFFFF_FFFF {
+            return None;
+        }
+        let end = pos + 46 + name_length + extra_length + comment_length;
+        let mut header = data.get(pos..end)?.to_vec();
+        let name = data.get(pos + 46..pos + 46 + name_length)?;
+
+        if u32_le(data, local_offset)? != LOCAL_HEADER as usize {
+            return None;
+        }
+        let local_name_length = u16_le(data, local_offset + 26)?;
+        let local_extra_length = u16_le(data, local_offset + 28)?;
+        let data_start = local_offset + 30 + local_name_length + local_extra_length;
+        let replacement = parts
+            .iter()
+            .find(|(part, _)| part.as_bytes() == name)
+            .map(|(_, content)| content.as_bytes());
+        let (method, crc, body) = match replacement {
+            // Stored, the replacements are tiny
+            Some(content) => (0, crc32fast::hash(content), content),
+            None => (
+                u16_le(data, pos + 10)? as u16,
+                u32_le(data, pos + 16)? as u32,
+                data.get(data_start..data_start + compressed_size)?,
+            ),
+        };
+        let uncompressed_size = match replacement {
+            Some(content) => content.len(),
+            None => u32_le(data, pos + 24)?,
+        };
+        let new_offset = u32::try_from(output.len()).ok()?;
+        // Sizes are known now, a data descriptor is not needed
+        let flags = (flags & !DATA_DESCRIPTOR) as u16;
+
+        output.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+        output.extend_from_slice(&data[pos + 6..pos + 8]);
+        output.extend_from_slice(&flags.to_le_bytes());
+        output.extend_from_slice(&method.to_le_bytes());
+        output.extend_from_slice(&data[pos + 12..pos + 16]);
+        output.extend_from_slice(&crc.to_le_bytes());
+        output.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        output.extend_from_slice(&(uncompressed_size as u32).to_le_bytes());
+        output.extend_from_slice(&(name_length as u16).to_le_bytes());
+        output.extend_from_slice(&0u16.to_le_bytes());
+        output.extend_from_slice(name);
+        output.extend_from_slice(body);
+
+        header[8..10].copy_from_slice(&flags.to_le_bytes());
+        header[10..12].copy_from_slice(&method.to_le_bytes());
+        header[16..20].copy_from_slice(&crc.to_le_bytes());
+        header[20..24].copy_from_slice(&(body.len() as u32).to_le_bytes());
+        header[24..28].copy_from_slice(&(uncompressed_size as u32).to_le_bytes());
+        header[42..46].copy_from_slice(&new_offset.to_le_bytes());
+        directory.extend_from_slice(&header);
+        pos = end;
+    }
+
+    let new_directory_offset = u32::try_from(output.len()).ok()?;
+    output.extend_from_slice(&directory);
+    let mut end = data.get(eocd..)?.to_vec();
+    end[12..16].copy_from_slice(&(directory.len() as u32).to_le_bytes());
+    end[16..20].copy_from_slice(&new_directory_offset.to_le_bytes());
+    output.extend_from_slice(&end);
+    Some(output)
+}
+
+/// Empty the information dictionaries and blank the XMP streams of a PDF
+///
+/// Bytes are overwritten with spaces rather than removed, the cross-reference table holds the
+/// offset of every object. An information dictionary or XMP stream that is referenced but not
+/// written out as is, e.g. inside a compressed object stream, fails the whole document.
+fn strip_pdf(data: &[u8]) -> Option<Vec<u8>> {
+    if !data.starts_with(b"%PDF-") {
+        return None;
+    }
+    let objects = pdf_objects(data);
+    let find = |reference| {
+        objects
+            .iter()
+            .filter(move |(id, _)| *id == reference)
+            .map(|(_, start)| *start)
+    };
+    let mut output = data.to_vec();
+    // In the trailer of every revision, or in its cross-reference stream
+    for at in pdf_names(data, b"/Info") {
+        let value = skip_pdf_whitespace(data, at + b"/Info".len());
+        let starts = match pdf_reference(data, value) {
+            Some((reference, _)) => find(reference).collect(),
+            // Some writers inline it
+            None if data[value..].starts_with(b"<<") => vec![value],
+            None => continue,
+        };
+        if starts.is_empty() {
+            return None;
+        }
+        for start in starts {
+            let (end, _) = pdf_dictionary(data, start)?;
+            output[start + 2..end - 2].fill(b' ');
+        }
+    }
+    // Of the document and of any page or image
+    for at in pdf_names(data, b"/Metadata") {
+        let value = skip_pdf_whitespace(data, at + b"/Metadata".len());
+        let Some((reference, end)) = pdf_reference(data, value) else {
+            continue;
+        };
+        let mut starts = find(reference).peekable();
+        starts.peek()?;
+        for start in starts {
+            blank_pdf_stream(data, &mut output, start)?;
+        }
+        output[at..end].fill(b' ');
+    }
+    // Streams whose reference sits in a compressed object stream
+    for (_, start) in &objects {
+        let Some((_, entries)) = pdf_dictionary(data, *start) else {
+            continue;
+        };
+        let is_metadata = entries
+            .iter()
+            .any(|entry| entry.key == b"/Type" && &data[entry.value..entry.end] == b"/Metadata");
+        if is_metadata {
+            blank_pdf_stream(data, &mut output, *start)?;
+        }
+    }
+    Some(output)
+}
+
+/// Entry of a PDF dictionary, by offsets into the document
+struct PdfEntry<'a> {
+    key: &'a [u8],
+    start: usize,
+    value: usize,
+    end: usize,
+}
+
+/// Blank the data of the stream object whose dictionary starts at `start`
+///
+/// Its filters go too, spaces decode as nothing else.
+fn blank_pdf_stream(data: &[u8], output: &mut [u8], start: usize) -> Option<()> {
+    let (end, entries) = pdf_dictionary(data, start)?;
+    for entry in entries {
+        if entry.key == b"/Filter" || entry.key == b"/DecodeParms" {
+            output[entry.start..entry.end].fill(b' ');
+        }
+    }
+    let mut pos = skip_pdf_whitespace(data, end);
+    if !data[pos..].starts_with(b"stream") {
+        return None;
+    }
+    pos += b"stream".len();
+    if data[pos..].starts_with(b"\r\n") {
+        pos += 2;
+    } else if data[pos..].starts_with(b"\n") {
+        pos += 1;
+    }
+    let length = data[pos..]
+        .windows(b"endstream".len())
+        .position(|window| window == b"endstream")?;
+    output[pos..pos + length].fill(b' ');
+    Some(())
+}
+
+/// Objects written out in a PDF, by number and generation, with where their value starts
+///
+/// Objects of compressed object streams are not listed.
+fn pdf_objects(data: &[u8]) -> Vec<((u64, u64), usize)> {
+    pdf_names(data, b"obj")
+        .filter_map(|at| {
+            let id = pdf_object_id(data, at)?;
+            Some((id, skip_pdf_whitespace(data, at + b"obj".len())))
+        })
+        .collect()
+}
+
+/// Number and generation before the `obj` keyword at `at`
+fn pdf_object_id(data: &[u8], at: usize) -> Option<(u64, u64)> {
+    let mut pos = at;
+    let mut numbers = [0u64; 2];
+    for number in numbers.iter_mut().rev() {
+        // `endobj` has no whitespace before `obj`
+        let end = pos;
+        while pos > 0 && is_pdf_whitespace(data[pos - 1]) {
+            pos -= 1;
+        }
+        if pos == end {
+            return None;
+        }
+        let digits_end = pos;
+        while pos > 0 && data[pos - 1].is_ascii_digit() {
+            pos -= 1;
+        }
+        *number = std::str::from_utf8(&data[pos..digits_end])
+            .ok()?
+            .parse()
+            .ok()?;
+    }
+    (pos == 0 || is_pdf_whitespace(data[pos - 1])).then_some((numbers[0], numbers[1]))
+}
+
+/// Offsets of a name or keyword in a PDF, where it is a whole token
+fn pdf_names<'a>(data: &'a [u8], name: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+    data.windows(name.len())
+        .enumerate()
+        .filter(move |(at, window)| {
+            *window == name
+                && data
+                    .get(at + name.len())
+                    .is_none_or(|&byte| is_pdf_whitespace(byte) || is_pdf_delimiter(byte))
+        })
+        .map(|(at, _)| at)
+}
+
+/// Object an indirect reference `<number> <generation> R` at `pos` points at, and its end
+fn pdf_reference(data: &[u8], pos: usize) -> Option<((u64, u64), usize)> {
+    let integer = |pos: usize| {
+        let end = skip_pdf_token(data, pos)?;
+        let value = std::str::from_utf8(&data[pos..end]).ok()?.parse().ok()?;
+        Some((value, end))
+    };
+    let (number, end) = integer(pos)?;
+    let (generation, end) = integer(skip_pdf_whitespace(data, end))?;
+    let end = skip_pdf_whitespace(data, end);
+    (skip_pdf_token(data, end)? == end + 1 && data[end] == b'R')
+        .then_some(((number, generation), end + 1))
+}
+
+/// Entries of the dictionary at `pos` and where it ends
+fn pdf_dictionary(data: &[u8], pos: usize) -> Option<(usize, Vec<PdfEntry<'_>>)> {
+    if !data.get(pos..)?.starts_with(b"<<") {
+        return None;
+    }
+    let mut entries = Vec::new();
+    let mut pos = pos + 2;
+    loop {
+        pos = skip_pdf_whitespace(data, pos);
+        if data.get(pos..)?.starts_with(b">>") {
+            return Some((pos + 2, entries));
+        }
+        if data[pos] != b'/' {
+            return None;
+        }
+        let start = pos;
+        let key_end = skip_pdf_value(data, pos, 0)?;
+        let value = skip_pdf_whitespace(data, key_end);
+        let end = match pdf_reference(data, value) {
+            Some((_, end)) => end,
+            None => skip_pdf_value(data, value, 0)?,
+        };
+        entries.push(PdfEntry {
+            key: &data[start..key_end],
+            start,
+            value,
+            end,
+        });
+        pos = end;
+    }
+}
+
+/// End of the value at `pos`, containers deeper than `depth` allows are refused
+fn skip_pdf_value(data: &[u8], pos: usize, depth: usize) -> Option<usize> {
+    const MAX_DEPTH: usize = 32;
+
+    if depth > MAX_DEPTH {
+        return None;
+    }
+    let (close, mut pos) = match *data.get(pos)? {
+        b'<' if data.get(pos + 1) == Some(&b'<') => (&b">>"[..], pos + 2),
+        b'[' => (&b"]"[..], pos + 1),
+        b'<' => {
+            let length = data[pos..].iter().position(|&byte| byte == b'>')?;
+            return Some(pos + length + 1);
+        }
+        b'(' => return skip_pdf_string(data, pos),
+        b'/' => return Some(skip_pdf_token(data, pos + 1).unwrap_or(pos + 1)),
+        _ => return skip_pdf_token(data, pos),
+    };
+    loop {
+        pos = skip_pdf_whitespace(data, pos);
+        if data.get(pos..)?.starts_with(close) {
+            return Some(pos + close.len());
+        }
+        pos = skip_pdf_value(data, pos, depth + 1)?;
+    }
+}
+
+/// End of the literal string at `pos`, balanced parentheses and escapes included
+fn skip_pdf_string(data: &[u8], mut pos: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    loop {
+        match *data.get(pos)? {
+            b'\\' => pos += 1,
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(pos + 1);
+                }
+            }
+            _ => {}
+        }
+        pos += 1;
+    }
+}
+
+/// End of the number, keyword or name body at `pos`, none when nothing is there
+fn skip_pdf_token(data: &[u8], pos: usize) -> Option<usize> {
+    let length = data
+        .get(pos..)?
+        .iter()
+        .position(|&byte| is_pdf_whitespace(byte) || is_pdf_delimiter(byte))
+        .unwrap_or(data.len() - pos);
+    (length > 0).then_some(pos + length)
+}
+
+/// Offset of the next token, whitespace and comments skipped
+fn skip_pdf_whitespace(data: &[u8], mut pos: usize) -> usize {
+    while let Some(&byte) = data.get(pos) {
+        if byte == b'%' {
+            while data.get(pos).is_some_and(|&byte| byte != b'\r' && byte != b'\n') {
+                pos += 1;
+            }
+        } else if is_pdf_whitespace(byte) {
+            pos += 1;
+        } else {
+            break;
+        }
+    }
+    pos
+}
+
+fn is_pdf_whitespace(byte: u8) -> bool {
+    matches!(byte, b'\0' | b'\t' | b'\n' | b'\x0C' | b'\r' | b' ')
+}
+
+fn is_pdf_delimiter(byte: u8) -> bool {
+    matches!(
+        byte,
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
+}
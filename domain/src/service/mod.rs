@@ -1,2 +1,9 @@
+pub mod archive;
 pub mod cabinet;
+pub mod compression;
 pub mod crypto;
+pub mod highlight;
+pub mod link;
+pub mod metadata;
+pub mod scanner;
+pub mod thumbnail;
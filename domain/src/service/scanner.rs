@@ -0,0 +1,53 @@
+use crate::entity::cabinet::CabinetItem;
+use crate::error::DomainError;
+use crate::error::cabinet::CabinetError;
+
+/// Verdict of a content scan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanVerdict {
+    Clean,
+    /// Name of the signature that matched
+    Infected(String),
+}
+
+/// Scanner the items of a cabinet go through before it is occupied
+#[async_trait::async_trait]
+pub trait ContentScanner: Send + Sync {
+    /// Scan the content, an error means the scanner could not give a verdict
+    async fn scan(&self, content: &[u8]) -> std::io::Result<ScanVerdict>;
+}
+
+/// Scan every item, the first infected one rejects them all
+///
+/// When the scanner fails, the items pass with `fail_open`, otherwise they are rejected.
+pub async fn scan_items(
+    scanner: &dyn ContentScanner,
+    items: &[CabinetItem],
+    fail_open: bool,
+) -> Result<(), DomainError> {
+    for item in items {
+        let Some(content) = item.content.as_deref() else {
+            continue;
+        };
+        match scanner.scan(content).await {
+            Ok(ScanVerdict::Clean) => {}
+            Ok(ScanVerdict::Infected(signature)) => {
+                log::warn!("Item '{}' is infected with {signature}", item.name);
+                metrics::counter!("cabinet_scans_total", "outcome" => "infected").increment(1);
+                return Err(CabinetError::InfectedItem(item.name.clone(), signature))?;
+            }
+            Err(e) if fail_open => {
+                log::warn!("Failed to scan item '{}', letting it pass: {e}", item.name);
+                metrics::counter!("cabinet_scans_total", "outcome" => "skipped").increment(1);
+                continue;
+            }
+            Err(e) => {
+                log::error!("Failed to scan item '{}': {e}", item.name);
+                metrics::counter!("cabinet_scans_total", "outcome" => "failed").increment(1);
+                return Err(CabinetError::ScannerUnavailable)?;
+            }
+        }
+        metrics::counter!("cabinet_scans_total", "outcome" => "clean").increment(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use crate::entity::cabinet::CabinetItem;
+use crate::error::DomainError;
+use image::{ImageDecoder, ImageReader, Limits};
+
+/// Longest side of a thumbnail in pixels
+pub const THUMBNAIL_SIZE: u32 = 320;
+
+/// Content type of generated thumbnails
+pub const THUMBNAIL_MIME_TYPE: &str = "image/webp";
+
+/// Largest image decoded for a thumbnail, a small file may claim huge dimensions
+const MAX_PIXELS: u64 = 40_000_000;
+
+/// Memory a single decode may allocate
+const MAX_ALLOC: u64 = 256 * 1024 * 1024;
+
+/// Image types thumbnails are generated for
+const SUPPORTED_MIME_TYPES: &[&str] = &["image/jpeg", "image/png", "image/gif", "image/webp"];
+
+/// Whether a thumbnail is generated for the item
+pub fn is_supported(item: &CabinetItem) -> bool {
+    SUPPORTED_MIME_TYPES.contains(&item.mime_type.as_str())
+}
+
+/// Generate a WebP thumbnail of an image
+///
+/// EXIF orientation is applied before resizing. Only pixels are re-encoded, so no metadata of
+/// the original survives. Images over [`MAX_PIXELS`] are refused before decoding. This is CPU
+/// bound, call it off the async runtime.
+pub fn generate(content: &[u8]) -> Result<Vec<u8>, DomainError> {
+    let mut reader = ImageReader::new(std::io::Cursor::new(content))
+        .with_guessed_format()
+        .map_err(|e| {
+            log::error!("Failed to read image: {e}");
+            DomainError::InternalError
+        })?;
+    let mut limits = Limits::default();
+    limits.max_alloc = Some(MAX_ALLOC);
+    reader.limits(limits);
+    let mut decoder = reader.into_decoder().map_err(|e| {
+        log::warn!("Unsupported image: {e}");
+        DomainError::InternalError
+    })?;
+    let (width, height) = decoder.dimensions();
+    if u64::from(width) * u64::from(height) > MAX_PIXELS {
+        log::warn!("Image of {width}x{height} pixels is too large for a thumbnail");
+        return Err(DomainError::InternalError);
+    }
+    let orientation = decoder
+        .orientation()
+        .unwrap_or(image::metadata::Orientation::NoTransforms);
+    let mut image = image::DynamicImage::from_decoder(decoder).map_err(|e| {
+        log::warn!("Failed to decode image: {e}");
+        DomainError::InternalError
+    })?;
+    image.apply_orientation(orientation);
+    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).into_rgba8();
+    let mut output = std::io::Cursor::new(Vec::new());
+    thumbnail
+        .write_to(&mut output, image::ImageFormat::WebP)
+        .map_err(|e| {
+            log::error!("Failed to encode thumbnail: {e}");
+            DomainError::InternalError
+        })?;
+    Ok(output.into_inner())
+}
@@ -0,0 +1,373 @@
+//! Listing and extraction of archive items, with hostile and broken archives
+
+use domain::entity::cabinet::{CabinetItem, CabinetItemCategory};
+use domain::error::DomainError;
+use domain::service::archive;
+use std::io::Write;
+
+/// Entries, expanded bytes and entry size the service stops at
+const MAX_ENTRIES: usize = 10_000;
+const MAX_EXPANDED_SIZE: u64 = 1024 * 1024 * 1024;
+const MAX_ENTRY_SIZE: u64 = 256 * 1024 * 1024;
+
+fn item(name: &str, mime_type: &str, content: &[u8]) -> CabinetItem {
+    let mut item = CabinetItem::new(
+        0,
+        1,
+        CabinetItemCategory::File,
+        name.to_string(),
+        content.to_vec(),
+        0,
+    );
+    item.mime_type = mime_type.to_string();
+    item
+}
+
+fn tar_item(content: &[u8]) -> CabinetItem {
+    item("files.tar", "application/x-tar", content)
+}
+
+fn paths(item: &CabinetItem, content: &[u8]) -> Result<Vec<String>, DomainError> {
+    archive::list(item, content).map(|entries| entries.into_iter().map(|e| e.path).collect())
+}
+
+fn extract(item: &CabinetItem, content: &[u8], path: &str) -> Result<Vec<u8>, DomainError> {
+    let mut output = Vec::new();
+    archive::extract(item, content, path, &mut output).map(|_| output)
+}
+
+/// Ustar header of an entry of `kind`, modified at 2025-10-09 08:53:20 UTC
+fn tar_header(name: &str, kind: u8, size: u64) -> [u8; 512] {
+    let mut header = [0; 512];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[100..108].copy_from_slice(b"0000644\0");
+    header[124..136].copy_from_slice(format!("{size:011o}\0").as_bytes());
+    header[136..148].copy_from_slice(format!("{:011o}\0", 1_760_000_000).as_bytes());
+    header[156] = kind;
+    header[257..265].copy_from_slice(b"ustar\x0000");
+    checksum(&mut header);
+    header
+}
+
+fn checksum(header: &mut [u8; 512]) {
+    header[148..156].fill(b' ');
+    let sum = header.iter().map(|&byte| u32::from(byte)).sum::<u32>();
+    header[148..156].copy_from_slice(format!("{sum:06o}\0 ").as_bytes());
+}
+
+/// Append an entry of `kind` holding `data` to a tar
+fn tar_entry(tar: &mut Vec<u8>, name: &str, kind: u8, data: &[u8]) {
+    tar.extend_from_slice(&tar_header(name, kind, data.len() as u64));
+    tar.extend_from_slice(data);
+    tar.resize(tar.len().next_multiple_of(512), 0);
+}
+
+/// Pax extended header records, each `<length> <key>=<value>\n` counting its own length
+fn pax(records: &[(&str, &str)]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for (key, value) in records {
+        let rest = format!(" {key}={value}\n");
+        let mut length = rest.len() + 1;
+        while length.to_string().len() + rest.len() != length {
+            length += 1;
+        }
+        data.extend_from_slice(format!("{length}{rest}").as_bytes());
+    }
+    data
+}
+
+/// Zip archive storing `files` uncompressed
+fn zip_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    let mut directory = Vec::new();
+    for (name, content) in files {
+        let offset = archive.len() as u32;
+        let mut fields = Vec::new();
+        for value in [10u16, 0, 0, 0, 0x5A21] {
+            fields.extend_from_slice(&value.to_le_bytes());
+        }
+        let size = content.len() as u32;
+        for value in [crc32fast::hash(content), size, size] {
+            fields.extend_from_slice(&value.to_le_bytes());
+        }
+        fields.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes());
+        archive.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        archive.extend_from_slice(&fields);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(content);
+        directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        directory.extend_from_slice(&10u16.to_le_bytes());
+        directory.extend_from_slice(&fields);
+        directory.extend_from_slice(&[0; 10]);
+        directory.extend_from_slice(&offset.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+    }
+    let directory_offset = archive.len() as u32;
+    archive.extend_from_slice(&directory);
+    archive.extend_from_slice(&end_of_directory(
+        files.len() as u16,
+        directory.len() as u32,
+        directory_offset,
+    ));
+    archive
+}
+
+fn end_of_directory(count: u16, directory_size: u32, directory_offset: u32) -> Vec<u8> {
+    let mut record = Vec::new();
+    record.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    record.extend_from_slice(&[0; 4]);
+    record.extend_from_slice(&count.to_le_bytes());
+    record.extend_from_slice(&count.to_le_bytes());
+    record.extend_from_slice(&directory_size.to_le_bytes());
+    record.extend_from_slice(&directory_offset.to_le_bytes());
+    record.extend_from_slice(&[0; 2]);
+    record
+}
+
+#[test]
+fn paths_escaping_the_folder_are_left_out() {
+    let mut tar = Vec::new();
+    for name in [
+        "ok/./file.txt",
+        "a//b.txt",
+        "win\\path.txt",
+        "../escape.txt",
+        "a/../../b.txt",
+        "dir\\..\\..\\x.txt",
+        "/etc/passwd",
+        "C:/Windows/win.ini",
+        "c:\\boot.ini",
+        "bad\u{1}name.txt",
+        "./",
+    ] {
+        tar_entry(&mut tar, name, b'0', b"data");
+    }
+    let item = tar_item(&tar);
+    assert_eq!(
+        paths(&item, &tar).unwrap(),
+        ["ok/file.txt", "a/b.txt", "win/path.txt"]
+    );
+    assert_eq!(extract(&item, &tar, "ok/file.txt").unwrap(), b"data");
+    for path in [
+        "../escape.txt",
+        "/etc/passwd",
+        "etc/passwd",
+        "C:/Windows/win.ini",
+    ] {
+        let error = extract(&item, &tar, path).unwrap_err();
+        assert_eq!(error.code(), "Cabinet::ArchiveEntryNotFound", "{path}");
+    }
+
+    let zip = zip_archive(&[
+        ("docs/readme.txt", b"read me"),
+        ("../../.bashrc", b"evil"),
+        ("/etc/cron.d/job", b"evil"),
+        ("D:\\autorun.inf", b"evil"),
+        ("docs/", b""),
+    ]);
+    let item = self::item("docs.zip", "application/zip", &zip);
+    assert_eq!(paths(&item, &zip).unwrap(), ["docs/readme.txt"]);
+}
+
+#[test]
+fn entry_count_is_capped() {
+    let mut tar = Vec::new();
+    for index in 0..MAX_ENTRIES {
+        tar_entry(&mut tar, &format!("{index}.txt"), b'0', b"");
+    }
+    assert_eq!(paths(&tar_item(&tar), &tar).unwrap().len(), MAX_ENTRIES);
+    tar_entry(&mut tar, "one-more.txt", b'0', b"");
+    let error = paths(&tar_item(&tar), &tar).unwrap_err();
+    assert_eq!(error.code(), "Cabinet::ArchiveTooLarge");
+
+    // Refused from the count of the end record alone
+    let zip = end_of_directory(MAX_ENTRIES as u16 + 1, 0, 0);
+    let error = paths(&item("many.zip", "application/zip", &zip), &zip).unwrap_err();
+    assert_eq!(error.code(), "Cabinet::ArchiveTooLarge");
+}
+
+#[test]
+fn expansion_is_capped() {
+    // A small zstd file inflating to more than the service ever decompresses
+    let mut encoder = zstd::stream::Encoder::new(Vec::new(), 1).unwrap();
+    encoder
+        .write_all(&tar_header("bomb.bin", b'0', 2 * MAX_EXPANDED_SIZE))
+        .unwrap();
+    let zeros = vec![0; 1024 * 1024];
+    for _ in 0..=MAX_EXPANDED_SIZE / zeros.len() as u64 {
+        encoder.write_all(&zeros).unwrap();
+    }
+    let bomb = encoder.finish().unwrap();
+    let item = item("bomb.tar.zst", "application/zstd", &bomb);
+    let error = paths(&item, &bomb).unwrap_err();
+    assert_eq!(error.code(), "Cabinet::ArchiveTooLarge");
+}
+
+#[test]
+fn large_entries_are_not_extracted() {
+    // Refused from the declared size, the data is not even there
+    let mut tar = Vec::new();
+    tar_entry(&mut tar, "small.txt", b'0', b"small");
+    tar.extend_from_slice(&tar_header("huge.bin", b'0', MAX_ENTRY_SIZE + 1));
+    let item = tar_item(&tar);
+    assert_eq!(paths(&item, &tar).unwrap(), ["small.txt", "huge.bin"]);
+    let error = extract(&item, &tar, "huge.bin").unwrap_err();
+    assert_eq!(error.code(), "Cabinet::ArchiveTooLarge");
+
+    let mut zip = zip_archive(&[("huge.bin", b"tiny")]);
+    let directory = zip
+        .windows(4)
+        .position(|window| window == 0x0201_4b50u32.to_le_bytes())
+        .unwrap();
+    zip[directory + 24..directory + 28].copy_from_slice(&(MAX_ENTRY_SIZE as u32 + 1).to_le_bytes());
+    let item = self::item("huge.zip", "application/zip", &zip);
+    let error = extract(&item, &zip, "huge.bin").unwrap_err();
+    assert_eq!(error.code(), "Cabinet::ArchiveTooLarge");
+}
+
+#[test]
+fn truncated_archives_are_invalid() {
+    let mut tar = Vec::new();
+    tar_entry(&mut tar, "first.txt", b'0', b"first");
+    // Writers may leave out the closing blocks
+    assert_eq!(paths(&tar_item(&tar), &tar).unwrap(), ["first.txt"]);
+
+    let mut cut = tar.clone();
+    cut.extend_from_slice(&tar_header("second.txt", b'0', 0)[..300]);
+    let error = paths(&tar_item(&cut), &cut).unwrap_err();
+    assert_eq!(error.code(), "Cabinet::InvalidArchive");
+
+    let mut corrupt = tar.clone();
+    let mut header = tar_header("second.txt", b'0', 0);
+    header[0] = b'S';
+    corrupt.extend_from_slice(&header);
+    let error = paths(&tar_item(&corrupt), &corrupt).unwrap_err();
+    assert_eq!(error.code(), "Cabinet::InvalidArchive");
+
+    let mut cut = tar.clone();
+    cut.extend_from_slice(&tar_header("././@LongLink", b'L', 200));
+    cut.extend_from_slice(&[b'a'; 100]);
+    let error = paths(&tar_item(&cut), &cut).unwrap_err();
+    assert_eq!(error.code(), "Cabinet::InvalidArchive");
+
+    let mut cut = tar.clone();
+    cut.extend_from_slice(&tar_header("second.txt", b'0', 100));
+    cut.extend_from_slice(b"only part of it");
+    let error = extract(&tar_item(&cut), &cut, "second.txt").unwrap_err();
+    assert_eq!(error.code(), "Cabinet::InvalidArchive");
+
+    let zip = zip_archive(&[("docs/readme.txt", b"read me")]);
+    let cut = &zip[..zip.len() - 10];
+    let error = paths(&item("docs.zip", "application/zip", cut), cut).unwrap_err();
+    assert_eq!(error.code(), "Cabinet::InvalidArchive");
+}
+
+#[test]
+fn pax_headers_apply_to_the_next_file() {
+    let long_path = format!("{}/report.txt", "nested".repeat(30));
+    let mut tar = Vec::new();
+    let records = pax(&[
+        ("path", &long_path),
+        ("size", "7"),
+        ("mtime", "1760000123.75"),
+        ("comment", "ignored"),
+    ]);
+    tar_entry(&mut tar, "PaxHeaders/report.txt", b'x', &records);
+    // The header size is overridden, and stale
+    tar.extend_from_slice(&tar_header("report.txt", b'0', 3));
+    tar.extend_from_slice(b"content");
+    tar.resize(tar.len().next_multiple_of(512), 0);
+    tar_entry(&mut tar, "after.txt", b'0', b"after");
+
+    let item = tar_item(&tar);
+    let entries = archive::list(&item, &tar).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].path, long_path);
+    assert_eq!(entries[0].size, 7);
+    assert_eq!(entries[0].modified.unwrap().timestamp(), 1_760_000_123);
+    assert_eq!(entries[1].path, "after.txt");
+    assert_eq!(entries[1].modified.unwrap().timestamp(), 1_760_000_000);
+    assert_eq!(extract(&item, &tar, &long_path).unwrap(), b"content");
+    assert_eq!(extract(&item, &tar, "after.txt").unwrap(), b"after");
+}
+
+#[test]
+fn gnu_long_names_apply_to_the_next_file() {
+    let long_path = format!("{}notes.txt", "deep/".repeat(40));
+    let mut tar = Vec::new();
+    let mut name = long_path.clone().into_bytes();
+    name.push(0);
+    tar_entry(&mut tar, "././@LongLink", b'L', &name);
+    tar_entry(&mut tar, "truncated-name", b'0', b"notes");
+    tar_entry(&mut tar, "after.txt", b'0', b"after");
+
+    let item = tar_item(&tar);
+    assert_eq!(
+        paths(&item, &tar).unwrap(),
+        [long_path.as_str(), "after.txt"]
+    );
+    assert_eq!(extract(&item, &tar, &long_path).unwrap(), b"notes");
+}
+
+#[test]
+fn pax_size_is_not_applied_to_the_headers_in_between() {
+    let long_path = format!("{}/data.bin", "long".repeat(40));
+    let mut tar = Vec::new();
+    tar_entry(
+        &mut tar,
+        "PaxHeaders/data.bin",
+        b'x',
+        &pax(&[("size", "5")]),
+    );
+    // Its own size is 165, reading it as 5 bytes would run into its name
+    tar_entry(&mut tar, "././@LongLink", b'L', long_path.as_bytes());
+    tar.extend_from_slice(&tar_header("data.bin", b'0', 0));
+    tar.extend_from_slice(b"12345");
+    tar.resize(tar.len().next_multiple_of(512), 0);
+    tar_entry(&mut tar, "after.txt", b'0', b"after");
+
+    let item = tar_item(&tar);
+    let entries = archive::list(&item, &tar).unwrap();
+    let paths = entries.iter().map(|e| e.path.as_str()).collect::<Vec<_>>();
+    assert_eq!(paths, [long_path.as_str(), "after.txt"]);
+    assert_eq!(entries[0].size, 5);
+    assert_eq!(extract(&item, &tar, &long_path).unwrap(), b"12345");
+    assert_eq!(extract(&item, &tar, "after.txt").unwrap(), b"after");
+}
+
+#[test]
+fn global_headers_keep_the_headers_of_the_next_file() {
+    let mut tar = Vec::new();
+    tar_entry(
+        &mut tar,
+        "PaxHeaders/report.txt",
+        b'x',
+        &pax(&[("path", "from-pax.txt"), ("size", "4")]),
+    );
+    tar_entry(
+        &mut tar,
+        "GlobalHead.0",
+        b'g',
+        &pax(&[("comment", "made by a global header of some length")]),
+    );
+    tar.extend_from_slice(&tar_header("from-header.txt", b'0', 0));
+    tar.extend_from_slice(b"body");
+    tar.resize(tar.len().next_multiple_of(512), 0);
+    tar_entry(&mut tar, "././@LongLink", b'L', b"from-long-name.txt");
+    tar_entry(
+        &mut tar,
+        "GlobalHead.1",
+        b'g',
+        &pax(&[("comment", "again")]),
+    );
+    tar_entry(&mut tar, "short.txt", b'0', b"long");
+
+    let item = tar_item(&tar);
+    assert_eq!(
+        paths(&item, &tar).unwrap(),
+        ["from-pax.txt", "from-long-name.txt"]
+    );
+    assert_eq!(extract(&item, &tar, "from-pax.txt").unwrap(), b"body");
+    assert_eq!(extract(&item, &tar, "from-long-name.txt").unwrap(), b"long");
+}
@@ -0,0 +1,146 @@
+//! Highlighted snippets are served as `text/html`, nothing of the snippet may become markup
+
+use domain::service::highlight::{normalize_language, render_html};
+
+/// Snippets written to break out of a string, a comment or the page
+const HOSTILE: &[&str] = &[
+    "let s = \"<script>alert('x')</script>\";",
+    "// </span><script>alert(1)</script>",
+    "/* \"quoted\" & <b>bold</b> */ x",
+    "'<img src=x onerror=alert(1)>'",
+    "`<iframe srcdoc=\"&lt;script&gt;\">`",
+    "\"unterminated <script>\nnext line",
+    "/* unterminated <!-- comment",
+    "# shell <svg onload=alert(1)> 'single' \"double\"",
+    "-- sql <style>body{}</style>",
+    "fn f<'a>(x: &'a str) -> &'a str { x } // 'lifetime' &amp; \\",
+    "<!DOCTYPE html><html><body onload=\"x()\"></body></html>",
+    "\\\"\\'\\\\ trailing backslash \\",
+    "1.5e3 0x1F <> 'é ✓ 🦀' \"&#39;\"",
+];
+
+/// Languages with a syntax, aliases, unknown tags and none
+const LANGUAGES: &[Option<&str>] = &[
+    Some("rust"),
+    Some("python"),
+    Some("js"),
+    Some("typescript"),
+    Some("json"),
+    Some("bash"),
+    Some("sql"),
+    Some("yaml"),
+    Some("toml"),
+    Some("c"),
+    Some("c++"),
+    Some("java"),
+    Some("go"),
+    Some("css"),
+    Some("html"),
+    Some("dockerfile"),
+    Some("brainfuck"),
+    None,
+];
+
+/// Text of the `<code>` element with the highlighting spans taken out, still escaped
+fn code_text(html: &str) -> &str {
+    let start = html.find("<code").unwrap();
+    let start = start + html[start..].find('>').unwrap() + 1;
+    let end = html.rfind("</code></pre></body></html>").unwrap();
+    &html[start..end]
+}
+
+fn without_spans(code: &str) -> String {
+    let mut text = code.to_string();
+    for class in ["comment", "string", "number", "literal", "keyword"] {
+        text = text.replace(&format!("<span class=\"hl-{class}\">"), "");
+    }
+    text.replace("</span>", "")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[test]
+fn snippets_never_become_markup() {
+    for language in LANGUAGES {
+        for snippet in HOSTILE {
+            let html = render_html("snippet", *language, snippet);
+            let text = without_spans(code_text(&html));
+            assert!(
+                !text.contains(['<', '>', '"', '\'']),
+                "{language:?} left markup in {text:?}"
+            );
+            // Every `&` starts an entity the escaping wrote
+            assert_eq!(unescape(&text), *snippet, "{language:?}");
+            assert_eq!(html.matches("<code").count(), 1);
+            assert_eq!(html.matches("</code>").count(), 1);
+            assert_eq!(
+                html.matches("<span").count(),
+                html.matches("</span>").count()
+            );
+        }
+    }
+}
+
+#[test]
+fn strings_and_comments_are_escaped_inside_their_spans() {
+    let html = render_html("a.rs", Some("rust"), HOSTILE[0]);
+    assert!(html.contains(
+        "<span class=\"hl-string\">&quot;&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt;&quot;</span>"
+    ));
+    let html = render_html("a.rs", Some("rust"), HOSTILE[1]);
+    assert!(html.contains(
+        "<span class=\"hl-comment\">// &lt;/span&gt;&lt;script&gt;alert(1)&lt;/script&gt;</span>"
+    ));
+    let html = render_html("a.c", Some("c"), HOSTILE[2]);
+    assert!(html.contains(
+        "<span class=\"hl-comment\">/* &quot;quoted&quot; &amp; &lt;b&gt;bold&lt;/b&gt; */</span> x"
+    ));
+}
+
+#[test]
+fn unterminated_strings_and_comments_are_closed() {
+    // A string ends with its line, a block comment with the snippet
+    let html = render_html("a.js", Some("js"), HOSTILE[5]);
+    assert!(
+        html.contains("<span class=\"hl-string\">&quot;unterminated &lt;script&gt;</span>\nnext")
+    );
+    let html = render_html("a.c", Some("c"), HOSTILE[6]);
+    assert!(
+        html.contains("<span class=\"hl-comment\">/* unterminated &lt;!-- comment</span></code>")
+    );
+    // Backtick strings span lines
+    let html = render_html("a.js", Some("js"), "`a\n<b>");
+    assert!(html.contains("<span class=\"hl-string\">`a\n&lt;b&gt;</span></code>"));
+}
+
+#[test]
+fn name_and_language_are_escaped() {
+    let html = render_html("</title><script>x()</script>", Some("\"><script>"), "x");
+    assert!(html.contains("<title>&lt;/title&gt;&lt;script&gt;x()&lt;/script&gt;</title>"));
+    assert!(html.contains("<code class=\"language-&quot;&gt;&lt;script&gt;\">x</code>"));
+    assert_eq!(html.matches("<script").count(), 0);
+}
+
+#[test]
+fn unknown_languages_are_rendered_plain() {
+    let html = render_html("a.bf", Some("brainfuck"), "fn <main> // 'x'");
+    assert_eq!(code_text(&html), "fn &lt;main&gt; // &#39;x&#39;");
+    assert!(html.contains("<code class=\"language-brainfuck\">"));
+    let html = render_html("a.txt", None, "<b>");
+    assert!(html.contains("<code>&lt;b&gt;</code>"));
+}
+
+#[test]
+fn language_tags_are_normalized() {
+    assert_eq!(normalize_language(" Rust "), Some(String::from("rust")));
+    assert_eq!(normalize_language("C++"), Some(String::from("c++")));
+    assert_eq!(normalize_language("\"><script>"), None);
+    assert_eq!(normalize_language(""), None);
+    assert_eq!(normalize_language(&"a".repeat(33)), None);
+}
@@ -0,0 +1,351 @@
+//! Metadata stripping of the formats saved with `strip_metadata`
+
+use domain::entity::cabinet::{CabinetItem, CabinetItemCategory};
+use domain::service::{archive, metadata};
+
+fn item(name: &str, content: Vec<u8>) -> CabinetItem {
+    CabinetItem::new(
+        0,
+        1,
+        CabinetItemCategory::File,
+        name.to_string(),
+        content,
+        0,
+    )
+}
+
+fn contains(data: &[u8], needle: &[u8]) -> bool {
+    data.windows(needle.len()).any(|window| window == needle)
+}
+
+/// Big endian EXIF pointing at a GPS directory with a latitude reference
+fn gps_exif() -> Vec<u8> {
+    let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08".to_vec();
+    // IFD0: one entry, the GPS directory at 26
+    exif.extend_from_slice(b"\0\x01\x88\x25\0\x04\0\0\0\x01\0\0\0\x1a\0\0\0\0");
+    // GPS IFD: latitude reference N
+    exif.extend_from_slice(b"\0\x01\0\x01\0\x02\0\0\0\x02N\0\0\0\0\0\0\0");
+    exif.extend_from_slice(b"GPS 52.5200 N 13.4050 E");
+    exif
+}
+
+const XMP: &[u8] = b"<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">Jane Doe</x:xmpmeta>";
+
+fn encode(format: image::ImageFormat) -> Vec<u8> {
+    let mut output = std::io::Cursor::new(Vec::new());
+    image::RgbImage::from_pixel(40, 20, image::Rgb([30, 200, 30]))
+        .write_to(&mut output, format)
+        .unwrap();
+    output.into_inner()
+}
+
+fn assert_decodes(data: &[u8], format: image::ImageFormat) {
+    let decoded = image::load_from_memory_with_format(data, format).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (40, 20));
+}
+
+#[test]
+fn jpeg_loses_gps_exif_xmp_iptc_and_comments() {
+    let jpeg = encode(image::ImageFormat::Jpeg);
+    let mut xmp = b"http://ns.adobe.com/xap/1.0/\0".to_vec();
+    xmp.extend_from_slice(XMP);
+    let iptc = b"Photoshop 3.0\08BIM\x04\x04\0\0\0\0\0\x0c\x1c\x02\x50\0\x08Jane Doe".to_vec();
+    let mut photo = jpeg[..2].to_vec();
+    for (marker, payload) in [
+        (0xE1, gps_exif()),
+        (0xE1, xmp),
+        (0xED, iptc),
+        (0xFE, b"shot by Jane Doe".to_vec()),
+    ] {
+        photo.extend_from_slice(&[0xFF, marker]);
+        photo.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+        photo.extend_from_slice(&payload);
+    }
+    photo.extend_from_slice(&jpeg[2..]);
+    assert_decodes(&photo, image::ImageFormat::Jpeg);
+
+    let mut item = item("photo.jpg", photo.clone());
+    let removed = metadata::strip(&mut item).unwrap();
+    let stripped = item.content.unwrap();
+    assert_eq!(removed, photo.len() - stripped.len());
+    assert_eq!(stripped, jpeg);
+    assert_eq!(item.size as usize, stripped.len());
+    assert_decodes(&stripped, image::ImageFormat::Jpeg);
+}
+
+#[test]
+fn png_loses_exif_xmp_text_and_time_chunks() {
+    fn chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        let crc = crc32fast::hash(&chunk[4..]);
+        chunk.extend_from_slice(&crc.to_be_bytes());
+        chunk
+    }
+
+    let png = encode(image::ImageFormat::Png);
+    // Signature and IHDR
+    let header = 8 + 25;
+    let mut xmp = b"XML:com.adobe.xmp\0\0\0\0\0".to_vec();
+    xmp.extend_from_slice(XMP);
+    let mut photo = png[..header].to_vec();
+    photo.extend(chunk(b"eXIf", &gps_exif()[6..]));
+    photo.extend(chunk(b"iTXt", &xmp));
+    photo.extend(chunk(b"tEXt", b"Author\0Jane Doe"));
+    photo.extend(chunk(b"tIME", &[0x07, 0xEA, 10, 19, 12, 30, 0]));
+    photo.extend_from_slice(&png[header..]);
+    assert_decodes(&photo, image::ImageFormat::Png);
+
+    let mut item = item("photo.png", photo);
+    assert!(metadata::strip(&mut item).unwrap() > 0);
+    let stripped = item.content.unwrap();
+    assert_eq!(stripped, png);
+    assert_decodes(&stripped, image::ImageFormat::Png);
+}
+
+#[test]
+fn webp_loses_exif_and_xmp_chunks_and_flags() {
+    fn chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = kind.to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+    fn riff(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body = chunks.concat();
+        let mut riff = b"RIFF".to_vec();
+        riff.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+        riff.extend_from_slice(b"WEBP");
+        riff.extend_from_slice(&body);
+        riff
+    }
+
+    let webp = encode(image::ImageFormat::WebP);
+    // The lossless bitstream, after the RIFF header and its chunk header
+    let length = u32::from_le_bytes(webp[16..20].try_into().unwrap()) as usize;
+    let bitstream = chunk(&webp[12..16], &webp[20..20 + length]);
+    let extended = |flags: u8| {
+        let mut data = vec![flags, 0, 0, 0];
+        data.extend_from_slice(&39u32.to_le_bytes()[..3]);
+        data.extend_from_slice(&19u32.to_le_bytes()[..3]);
+        chunk(b"VP8X", &data)
+    };
+    let photo = riff(&[
+        extended(0x08 | 0x04),
+        bitstream.clone(),
+        chunk(b"EXIF", &gps_exif()[6..]),
+        chunk(b"XMP ", XMP),
+    ]);
+    assert_decodes(&photo, image::ImageFormat::WebP);
+
+    let mut item = item("photo.webp", photo);
+    assert!(metadata::strip(&mut item).unwrap() > 0);
+    let stripped = item.content.unwrap();
+    assert_eq!(stripped, riff(&[extended(0), bitstream]));
+    assert_decodes(&stripped, image::ImageFormat::WebP);
+}
+
+/// Zip archive storing `files` uncompressed
+fn zip_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    let mut directory = Vec::new();
+    for (name, content) in files {
+        let offset = archive.len() as u32;
+        let mut fields = Vec::new();
+        for value in [20u16, 0, 0, 0, 0x5A21] {
+            fields.extend_from_slice(&value.to_le_bytes());
+        }
+        let size = content.len() as u32;
+        for value in [crc32fast::hash(content), size, size] {
+            fields.extend_from_slice(&value.to_le_bytes());
+        }
+        fields.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes());
+        archive.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        archive.extend_from_slice(&fields);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(content);
+        directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        directory.extend_from_slice(&20u16.to_le_bytes());
+        directory.extend_from_slice(&fields);
+        directory.extend_from_slice(&[0; 10]);
+        directory.extend_from_slice(&offset.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+    }
+    let directory_offset = archive.len() as u32;
+    archive.extend_from_slice(&directory);
+    archive.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    archive.extend_from_slice(&[0; 4]);
+    archive.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&directory_offset.to_le_bytes());
+    archive.extend_from_slice(&[0; 2]);
+    archive
+}
+
+#[test]
+fn office_documents_get_empty_properties() {
+    let body = b"<w:document>Quarterly report</w:document>";
+    let document = zip_archive(&[
+        ("[Content_Types].xml", b"<Types/>"),
+        (
+            "docProps/core.xml",
+            b"<cp:coreProperties><dc:creator>Jane Doe</dc:creator></cp:coreProperties>",
+        ),
+        (
+            "docProps/app.xml",
+            b"<Properties><Company>ACME</Company></Properties>",
+        ),
+        ("word/document.xml", body),
+    ]);
+
+    let mut item = item("report.docx", document);
+    assert!(metadata::strip(&mut item).is_some());
+    let stripped = item.content.unwrap();
+    assert!(!contains(&stripped, b"Jane Doe") && !contains(&stripped, b"ACME"));
+    // Still a zip every entry of which reads back with a valid CRC
+    let mut archive = self::item("report.zip", stripped.clone());
+    archive.mime_type = String::from("application/zip");
+    let paths = archive::list(&archive, &stripped)
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.path)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        [
+            "[Content_Types].xml",
+            "docProps/core.xml",
+            "docProps/app.xml",
+            "word/document.xml"
+        ]
+    );
+    let mut output = Vec::new();
+    archive::extract(&archive, &stripped, "word/document.xml", &mut output).unwrap();
+    assert_eq!(output, body);
+    output.clear();
+    archive::extract(&archive, &stripped, "docProps/core.xml", &mut output).unwrap();
+    assert!(output.ends_with(b"/>"));
+}
+
+/// PDF of the given objects with a cross-reference table and `trailer` entries
+fn pdf(objects: &[&[u8]], trailer: &str) -> Vec<u8> {
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} {trailer} >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .as_bytes(),
+    );
+    pdf
+}
+
+/// Whether every entry of the cross-reference table still points at its object
+fn offsets_hold(pdf: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(pdf);
+    let table = &text[text.rfind("xref\n").unwrap()..];
+    table
+        .lines()
+        .skip(3)
+        .take_while(|line| line.ends_with(" n "))
+        .enumerate()
+        .all(|(index, line)| {
+            let offset = line[..10].parse::<usize>().unwrap();
+            text[offset..].starts_with(&format!("{} 0 obj", index + 1))
+        })
+}
+
+const XMP_STREAM: &[u8] = b"<< /Type /Metadata /Subtype /XML /Length 98 >>\nstream\n\
+<?xpacket begin=\"\"?><x:xmpmeta><dc:creator>Jane Doe</dc:creator></x:xmpmeta><?xpacket end=\"w\"?>\n\
+endstream";
+
+#[test]
+fn pdf_information_and_xmp_are_blanked_in_place() {
+    let document = pdf(
+        &[
+            b"<< /Type /Catalog /Pages 2 0 R /Metadata 4 0 R >>",
+            b"<< /Type /Pages /Kids [] /Count 0 >>",
+            b"<< /Author (Jane \\(Doe\\)) /Producer <4A616E65> /Custom [(a) << /Nested (Jane) >>] >>",
+            XMP_STREAM,
+        ],
+        "/Root 1 0 R /Info 3 0 R",
+    );
+    assert!(offsets_hold(&document));
+
+    let mut item = item("report.pdf", document.clone());
+    assert_eq!(item.mime_type, "application/pdf");
+    let removed = metadata::strip(&mut item).unwrap();
+    let stripped = item.content.unwrap();
+    assert_eq!(stripped.len(), document.len());
+    assert!(removed > 0);
+    assert!(offsets_hold(&stripped));
+    assert!(!contains(&stripped, b"Jane") && !contains(&stripped, b"4A616E65"));
+    assert!(!contains(&stripped, b"xpacket") && !contains(&stripped, b"/Metadata 4 0 R"));
+    let text = String::from_utf8_lossy(&stripped);
+    assert!(text.contains("<< /Type /Catalog /Pages 2 0 R "));
+    assert!(text.contains("3 0 obj\n<<    "));
+    assert!(text.contains("/Root 1 0 R /Info 3 0 R"));
+}
+
+#[test]
+fn pdf_xmp_is_blanked_without_a_visible_reference() {
+    // The catalog sits in a compressed object stream, only the XMP stream can be seen
+    let compressed = b"<< /Type /Metadata /Subtype /XML /Filter /FlateDecode /Length 98 >>\nstream\n\
+<?xpacket begin=\"\"?><x:xmpmeta><dc:creator>Jane Doe</dc:creator></x:xmpmeta><?xpacket end=\"w\"?>\n\
+endstream";
+    let document = pdf(&[compressed], "/Root 2 0 R");
+
+    let mut item = item("report.pdf", document.clone());
+    assert!(metadata::strip(&mut item).unwrap() > 0);
+    let stripped = item.content.unwrap();
+    assert_eq!(stripped.len(), document.len());
+    assert!(offsets_hold(&stripped));
+    assert!(!contains(&stripped, b"Jane") && !contains(&stripped, b"/FlateDecode"));
+}
+
+#[test]
+fn pdf_that_can_not_be_stripped_is_reported_and_kept() {
+    // The information dictionary is inside a compressed object stream
+    let hidden = pdf(
+        &[b"<< /Type /Catalog /Pages 2 0 R >>"],
+        "/Root 1 0 R /Info 7 0 R",
+    );
+    let nested = format!("<< /Author {}Jane{} >>", "[".repeat(200), "]".repeat(200));
+    let deep = pdf(&[nested.as_bytes()], "/Info 1 0 R");
+    for document in [hidden, deep, b"%PDF-1.4\n/Metadata 9 0 R".to_vec()] {
+        let mut item = item("report.pdf", document.clone());
+        assert_eq!(metadata::strip(&mut item), None);
+        assert_eq!(item.content.unwrap(), document);
+    }
+}
+
+#[test]
+fn other_types_have_nothing_to_strip() {
+    let mut text = item("notes.txt", b"GPS 52.5200 N".to_vec());
+    assert_eq!(metadata::strip(&mut text), Some(0));
+    assert_eq!(text.content.unwrap(), b"GPS 52.5200 N");
+    // Claims to be a JPEG but does not parse
+    let mut broken = item("photo.jpg", b"\xFF\xD8\xFF\xE1\x00".to_vec());
+    broken.mime_type = String::from("image/jpeg");
+    assert_eq!(metadata::strip(&mut broken), None);
+}
@@ -0,0 +1,128 @@
+//! Content scanner talking to clamd
+
+use domain::service::scanner::{ContentScanner, ScanVerdict};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Bytes sent per INSTREAM chunk
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Longest reply read from clamd
+const MAX_REPLY_SIZE: usize = 4096;
+
+/// Where clamd listens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClamdAddress {
+    /// `host:port`
+    Tcp(String),
+    Unix(std::path::PathBuf),
+}
+
+impl std::str::FromStr for ClamdAddress {
+    type Err = String;
+
+    /// `unix:/path`, or an absolute path, is a Unix socket, anything else is `host:port`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            return Ok(ClamdAddress::Unix(path.into()));
+        }
+        if s.starts_with('/') {
+            return Ok(ClamdAddress::Unix(s.into()));
+        }
+        match s.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+                Ok(ClamdAddress::Tcp(s.to_string()))
+            }
+            _ => Err(format!("'{s}' is neither host:port nor a socket path")),
+        }
+    }
+}
+
+impl std::fmt::Display for ClamdAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClamdAddress::Tcp(address) => write!(f, "{address}"),
+            ClamdAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Scan contents with the INSTREAM command of clamd, one connection per scan
+#[derive(Debug, Clone)]
+pub struct ClamdScanner {
+    address: ClamdAddress,
+    timeout: std::time::Duration,
+}
+
+impl ClamdScanner {
+    /// A scan taking longer than `timeout`, connecting included, fails
+    pub fn new(address: ClamdAddress, timeout: std::time::Duration) -> Self {
+        Self { address, timeout }
+    }
+}
+
+#[async_trait::async_trait]
+impl ContentScanner for ClamdScanner {
+    #[tracing::instrument(level = "debug", skip_all, fields(address = %self.address))]
+    async fn scan(&self, content: &[u8]) -> std::io::Result<ScanVerdict> {
+        let scan = async {
+            match &self.address {
+                ClamdAddress::Tcp(address) => {
+                    instream(tokio::net::TcpStream::connect(address).await?, content).await
+                }
+                #[cfg(unix)]
+                ClamdAddress::Unix(path) => {
+                    instream(tokio::net::UnixStream::connect(path).await?, content).await
+                }
+                #[cfg(not(unix))]
+                ClamdAddress::Unix(_) => Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "Unix sockets are not supported on this platform",
+                )),
+            }
+        };
+        let reply = tokio::time::timeout(self.timeout, scan)
+            .await
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "clamd timed out"))??;
+        parse_reply(&reply)
+    }
+}
+
+/// Stream the content in length prefixed chunks, a zero length ends it
+async fn instream<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    content: &[u8],
+) -> std::io::Result<String> {
+    stream.write_all(b"zINSTREAM\0").await?;
+    for chunk in content.chunks(CHUNK_SIZE) {
+        stream
+            .write_all(&(chunk.len() as u32).to_be_bytes())
+            .await?;
+        stream.write_all(chunk).await?;
+    }
+    stream.write_all(&0u32.to_be_bytes()).await?;
+    stream.flush().await?;
+
+    // The reply ends with a NUL, or the connection when clamd gives up early
+    let mut reply = Vec::new();
+    let mut buffer = [0u8; 512];
+    while !reply.contains(&0) && reply.len() < MAX_REPLY_SIZE {
+        match stream.read(&mut buffer).await? {
+            0 => break,
+            n => reply.extend_from_slice(&buffer[..n]),
+        }
+    }
+    let reply = reply.split(|&b| b == 0).next().unwrap_or_default();
+    Ok(String::from_utf8_lossy(reply).trim_end().to_string())
+}
+
+/// `stream: OK`, `stream: <signature> FOUND` or `<reason> ERROR`
+fn parse_reply(reply: &str) -> std::io::Result<ScanVerdict> {
+    let result = reply.strip_prefix("stream: ").unwrap_or(reply);
+    if result == "OK" {
+        return Ok(ScanVerdict::Clean);
+    }
+    if let Some(signature) = result.strip_suffix(" FOUND") {
+        return Ok(ScanVerdict::Infected(signature.to_string()));
+    }
+    Err(std::io::Error::other(format!("clamd replied '{reply}'")))
+}
@@ -42,3 +42,96 @@ pub async fn commit(self) -> Result<(), DomainError> {
         Ok(())
     }
 }
+
+/// Rows copied by [`copy_data`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CopyReport {
+    pub cabinets: u64,
+    pub items: u64,
+    pub objects: u64,
+}
+
+/// Copy cabinets, cabinet items and their objects into an empty, migrated database
+///
+/// Keypairs are not copied, they expire within minutes. Absolute item paths under the store
+/// folder are rewritten relative to it.
+pub async fn copy_data(
+    source: &DatabaseConnection,
+    target: &DatabaseConnection,
+    data_folder: &std::path::Path,
+) -> Result<CopyReport, DomainError> {
+    use crate::entity::{cabinet, cabinet_item, cabinet_object};
+    use sea_orm::{EntityTrait, PaginatorTrait};
+
+    const CHUNK_SIZE: usize = 100;
+
+    let db_error = |e: sea_orm::DbErr| {
+        log::error!("Failed to copy data: {e}");
+        DomainError::InternalError
+    };
+    let existing = cabinet::Entity::find()
+        .count(target)
+        .await
+        .map_err(db_error)?
+        + cabinet_item::Entity::find()
+            .count(target)
+            .await
+            .map_err(db_error)?
+        + cabinet_object::Entity::find()
+            .count(target)
+            .await
+            .map_err(db_error)?;
+    if existing > 0 {
+        log::error!("Target database is not empty ({existing} rows), refusing to copy");
+        return Err(DomainError::InternalError);
+    }
+
+    let cabinets = cabinet::Entity::find()
+        .all(source)
+        .await
+        .map_err(db_error)?;
+    let mut items = cabinet_item::Entity::find()
+        .all(source)
+        .await
+        .map_err(db_error)?;
+    let objects = cabinet_object::Entity::find()
+        .all(source)
+        .await
+        .map_err(db_error)?;
+    let store_folder = data_folder.join("files");
+    for item in items.iter_mut() {
+        if let Ok(path) = std::path::Path::new(&item.path).strip_prefix(&store_folder) {
+            item.path = path.to_string_lossy().to_string();
+        }
+    }
+    let report = CopyReport {
+        cabinets: cabinets.len() as u64,
+        items: items.len() as u64,
+        objects: objects.len() as u64,
+    };
+
+    let transaction = target.begin().await.map_err(db_error)?;
+    for chunk in cabinets.chunks(CHUNK_SIZE) {
+        let models = chunk.iter().cloned().map(cabinet::ActiveModel::from);
+        cabinet::Entity::insert_many(models)
+            .exec_without_returning(&transaction)
+            .await
+            .map_err(db_error)?;
+    }
+    for chunk in items.chunks(CHUNK_SIZE) {
+        let models = chunk.iter().cloned().map(cabinet_item::ActiveModel::from);
+        cabinet_item::Entity::insert_many(models)
+            .exec_without_returning(&transaction)
+            .await
+            .map_err(db_error)?;
+    }
+    for chunk in objects.chunks(CHUNK_SIZE) {
+        let models = chunk.iter().cloned().map(cabinet_object::ActiveModel::from);
+        cabinet_object::Entity::insert_many(models)
+            .exec_without_returning(&transaction)
+            .await
+            .map_err(db_error)?;
+    }
+    transaction.commit().await.map_err(db_error)?;
+    Ok(report)
+}
@@ -16,6 +16,11 @@ pub struct Model {
     pub create_at: DateTime,
     pub update_at: DateTime,
     pub version: i32,
+    pub mime_type: String,
+    pub language: Option<String>,
+    pub url: Option<String>,
+    pub encoding: Option<String>,
+    pub hash: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -2,4 +2,5 @@
 
 pub mod cabinet;
 pub mod cabinet_item;
+pub mod cabinet_object;
 pub mod keypair;
@@ -1,4 +1,6 @@
+pub mod clamd;
 pub mod database;
 mod entity;
+pub mod link_title;
 pub mod repository;
 pub mod service;
@@ -0,0 +1,163 @@
+//! Title fetcher for links, refusing to reach anything but public addresses
+
+use domain::service::link::{self, TitleFetcher};
+use std::net::{IpAddr, SocketAddr};
+
+/// Redirects followed, every target is checked like the link itself
+const MAX_REDIRECTS: usize = 3;
+
+/// Bytes of a page read to find its title
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// Fetch titles over HTTP, one connection per page
+///
+/// Requests go to the very address that was checked, a second lookup can not point them to a
+/// private one. Proxies are bypassed for the same reason.
+#[derive(Debug, Clone)]
+pub struct HttpTitleFetcher {
+    timeout: std::time::Duration,
+}
+
+impl HttpTitleFetcher {
+    /// A fetch taking longer than `timeout`, redirects included, fails
+    pub fn new(timeout: std::time::Duration) -> Self {
+        Self { timeout }
+    }
+
+    async fn fetch(&self, mut url: url::Url) -> std::io::Result<Option<String>> {
+        use reqwest::header::{ACCEPT, CONTENT_TYPE, LOCATION};
+
+        for _ in 0..=MAX_REDIRECTS {
+            let address = resolve_public(&url).await?;
+            let client = reqwest::Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .no_proxy()
+                .resolve(url.host_str().unwrap_or_default(), address)
+                .user_agent(concat!("temporary-cabinet/", env!("CARGO_PKG_VERSION")))
+                .build()
+                .map_err(std::io::Error::other)?;
+            let mut response = client
+                .get(url.clone())
+                .header(ACCEPT, "text/html")
+                .send()
+                .await
+                .map_err(std::io::Error::other)?;
+            let status = response.status();
+            if status.is_redirection() {
+                let location = response
+                    .headers()
+                    .get(LOCATION)
+                    .and_then(|location| location.to_str().ok())
+                    .ok_or_else(|| std::io::Error::other("redirected without a location"))?;
+                url = url
+                    .join(location)
+                    .ok()
+                    .and_then(|target| link::parse(target.as_str()))
+                    .ok_or_else(|| std::io::Error::other("redirected to an invalid link"))?;
+                continue;
+            }
+            if !status.is_success() {
+                return Err(std::io::Error::other(format!("server replied {status}")));
+            }
+            let is_html = response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|content_type| content_type.to_str().ok())
+                .is_some_and(|content_type| {
+                    let content_type = content_type.to_ascii_lowercase();
+                    content_type.starts_with("text/html")
+                        || content_type.starts_with("application/xhtml+xml")
+                });
+            if !is_html {
+                return Ok(None);
+            }
+            let mut body = Vec::new();
+            while body.len() < MAX_BODY_SIZE
+                && let Some(chunk) = response.chunk().await.map_err(std::io::Error::other)?
+            {
+                body.extend_from_slice(&chunk);
+            }
+            body.truncate(MAX_BODY_SIZE);
+            return Ok(link::extract_title(&String::from_utf8_lossy(&body)));
+        }
+        Err(std::io::Error::other("too many redirects"))
+    }
+}
+
+#[async_trait::async_trait]
+impl TitleFetcher for HttpTitleFetcher {
+    #[tracing::instrument(level = "debug", skip_all, fields(host = url.host_str()))]
+    async fn fetch_title(&self, url: &url::Url) -> std::io::Result<Option<String>> {
+        tokio::time::timeout(self.timeout, self.fetch(url.clone()))
+            .await
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "fetch timed out"))?
+    }
+}
+
+/// Address to connect to for `url`, every address the host resolves to must be public
+async fn resolve_public(url: &url::Url) -> std::io::Result<SocketAddr> {
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addresses = match url.host() {
+        Some(url::Host::Domain(domain)) => tokio::net::lookup_host((domain, port))
+            .await?
+            .collect::<Vec<_>>(),
+        Some(url::Host::Ipv4(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        Some(url::Host::Ipv6(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        None => Vec::new(),
+    };
+    if let Some(address) = addresses.iter().find(|address| !is_public(address.ip())) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("'{}' is not a public address", address.ip()),
+        ));
+    }
+    addresses
+        .first()
+        .copied()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "host has no address"))
+}
+
+/// Whether an address is reachable on the internet, not a private, local or reserved one
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // This network
+                || a == 0
+                // Shared address space of carrier-grade NAT
+                || (a == 100 && (64..128).contains(&b))
+                // IETF protocol assignments
+                || (a == 192 && b == 0 && c == 0)
+                // Benchmarking
+                || (a == 198 && (18..20).contains(&b))
+                // Reserved
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public(ip.into());
+            }
+            let segments = ip.segments();
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || ip.is_unique_local()
+                || ip.is_unicast_link_local()
+                // Documentation
+                || (segments[0] == 0x2001 && segments[1] == 0xdb8)
+                // NAT64 and IPv4-compatible addresses reach IPv4 hosts
+                || (segments[0] == 0x64 && segments[1] == 0xff9b)
+                // So do 6to4 and Teredo, which embed an IPv4 address
+                || segments[0] == 0x2002
+                || (segments[0] == 0x2001 && segments[1] == 0)
+                || segments[..6] == [0; 6])
+        }
+    }
+}
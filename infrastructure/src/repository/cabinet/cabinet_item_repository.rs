@@ -2,15 +2,37 @@
 use std::str::FromStr;
 
 use crate::entity::cabinet_item::{ActiveModel, Column, Entity, Model};
+use crate::entity::cabinet_object;
 use domain::entity::cabinet::{CabinetItem, CabinetItemCategory};
 use domain::error::DomainError;
 use domain::error::cabinet::CabinetError;
 use domain::repository::cabinet::CabinetItemRepository as Repository;
-use sea_orm::{QueryOrder, prelude::*};
+use domain::service::compression;
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::{QueryOrder, QuerySelect, Set, prelude::*};
+
+/// Extension of content files being written
+const TEMPORARY_EXTENSION: &str = "tmp";
+
+/// Age after which a temporary file is left over, younger ones may still be written by a replica
+const TEMPORARY_FILE_AGE: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Folder of the store holding contents by hash
+const OBJECTS_FOLDER: &str = "objects";
+
+/// Locks serializing the reference counting of objects, by the first byte of their hash
+///
+/// Writing, counting and removing an object happen under its lock, so a save can not write an
+/// object again while a delete is about to remove its file. They guard one process, replicas
+/// sharing a data folder still rely on the database.
+static OBJECT_LOCKS: [tokio::sync::Mutex<()>; 64] =
+    [const { tokio::sync::Mutex::const_new(()) }; 64];
 
 pub struct CabinetItemRepository {
     connection: DatabaseConnection,
     store_folder: PathBuf,
+    /// zstd level new contents are compressed with, none stores them as is
+    compression_level: Option<i32>,
 }
 
 impl CabinetItemRepository {
@@ -18,27 +40,72 @@ pub fn new(connection: DatabaseConnection, store_folder: PathBuf) -> Self {
         Self {
             connection,
             store_folder,
+            compression_level: None,
         }
     }
+
+    /// Compress the contents saved from now on with zstd at `level`, when they compress
+    pub fn with_compression(mut self, level: Option<i32>) -> Self {
+        self.compression_level = level;
+        self
+    }
 }
 
 impl CabinetItemRepository {
-    fn resolve_file_path(&self, cabinet_code: i64, cabinet_item_id: i64) -> PathBuf {
-        let folder_path = self.store_folder.join(cabinet_code.to_string());
+    /// Path of a content relative to the store folder, `objects/ab/cdef…` after its hash
+    ///
+    /// Relative paths keep rows valid when the store folder is mounted elsewhere, e.g. on
+    /// replicas sharing a database.
+    fn object_file_path(hash: &str) -> PathBuf {
+        let (prefix, rest) = hash.split_at(2.min(hash.len()));
+        Path::new(OBJECTS_FOLDER).join(prefix).join(rest)
+    }
+
+    /// Lock of the object holding the content with `hash`
+    fn object_lock(hash: &str) -> &'static tokio::sync::Mutex<()> {
+        let byte = hash
+            .get(..2)
+            .and_then(|prefix| u8::from_str_radix(prefix, 16).ok())
+            .unwrap_or_default();
+        &OBJECT_LOCKS[byte as usize % OBJECT_LOCKS.len()]
+    }
+
+    /// Thumbnail in the cabinet's folder, its temporary file must not clash with the item's
+    fn thumbnail_file_path(&self, cabinet_code: i64, cabinet_item_id: i64) -> PathBuf {
+        self.store_folder
+            .join(cabinet_code.to_string())
+            .join(format!("{cabinet_item_id}-thumbnail.webp"))
+    }
+
+    /// Absolute path of a stored item path, rows written by older versions hold absolute paths
+    fn absolute_file_path(&self, path: &str) -> PathBuf {
+        self.store_folder.join(path)
+    }
 
+    fn create_parent_folder(&self, path: &Path) -> Result<(), DomainError> {
+        let Some(folder_path) = path.parent() else {
+            return Ok(());
+        };
         if !folder_path.exists()
-            && let Err(e) = std::fs::create_dir_all(&folder_path)
+            && let Err(e) = std::fs::create_dir_all(folder_path)
         {
             log::error!("Failed to create folder '{folder_path:?}': {e}");
-            return PathBuf::new();
+            return Err(DomainError::InternalError);
         }
-        folder_path.join(cabinet_item_id.to_string())
+        Ok(())
     }
 
     /// Write content to filesystem
+    ///
+    /// Content goes to a temporary file first, so an interrupted write never leaves a partial
+    /// item behind.
     fn write_content(&self, path: &Path, content: &[u8]) -> Result<(), DomainError> {
-        if let Err(e) = std::fs::write(path, content) {
+        let temporary_path = path.with_extension(TEMPORARY_EXTENSION);
+        let result = std::fs::write(&temporary_path, content)
+            .and_then(|_| std::fs::rename(&temporary_path, path));
+        if let Err(e) = result {
             log::error!("Failed to write cabinet content to '{path:?}': {e}");
+            let _ = std::fs::remove_file(&temporary_path);
             return Err(DomainError::InternalError);
         }
         Ok(())
@@ -65,23 +132,17 @@ fn remove_file(&self, path: &Path) -> Result<(), DomainError> {
 
 #[async_trait::async_trait]
 impl Repository for CabinetItemRepository {
-    async fn save(&self, item: CabinetItem) -> Result<(), DomainError> {
+    #[tracing::instrument(skip_all, level = "debug", fields(item_id = item.id))]
+    async fn save(&self, mut item: CabinetItem) -> Result<(), DomainError> {
         let content = if let Some(content) = item.content.as_ref() {
             content
         } else {
             return Err(CabinetError::ItemContentMustNotEmpty)?;
         };
-
-        // Write content to filesystem
-        let path = self.resolve_file_path(item.cabinet_code, item.id);
-
-        log::debug!(
-            "Writing cabinet '{}' item '{}' content to '{:?}'",
-            item.cabinet_code,
-            item.sort_order,
-            path
-        );
-        self.write_content(&path, content)?;
+        let hash = CabinetItem::content_hash(content);
+        let path = Self::object_file_path(&hash);
+        item.encoding = self.acquire_object(&hash, &path, content).await?;
+        item.hash = Some(hash);
 
         // Save cabinet item to database
         let mut model = Model::try_from(item)?;
@@ -94,6 +155,7 @@ async fn save(&self, item: CabinetItem) -> Result<(), DomainError> {
         Ok(())
     }
 
+    #[tracing::instrument(skip_all, level = "debug", fields(id = id))]
     async fn delete_by_id(&self, id: i64) -> Result<(), DomainError> {
         let item = self.find_model_by_id(id).await?;
         if item.is_none() {
@@ -101,8 +163,19 @@ async fn delete_by_id(&self, id: i64) -> Result<(), DomainError> {
         }
         let item = item.unwrap();
         let item_category = CabinetItemCategory::from_str(&item.category)?;
-        if item_category == CabinetItemCategory::File {
-            let path = PathBuf::from(&item.path);
+        // The row goes first, a content is never removed while an item still points at it
+        Entity::delete_by_id(id)
+            .exec(&self.connection)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to delete cabinet item '{id}': {e}");
+                DomainError::InternalError
+            })?;
+        if let Some(hash) = item.hash.as_deref() {
+            self.release_object(hash).await?;
+        } else if item_category == CabinetItemCategory::File {
+            // Saved by an older version under the cabinet's folder
+            let path = self.absolute_file_path(&item.path);
             log::debug!(
                 "Removing cabinet '{}' item '{}' content from '{:?}'",
                 item.cabinet_code,
@@ -111,16 +184,39 @@ async fn delete_by_id(&self, id: i64) -> Result<(), DomainError> {
             );
             self.remove_file(&path)?;
         }
-        Entity::delete_by_id(id)
-            .exec(&self.connection)
-            .await
-            .map_err(|e| {
-                log::error!("Failed to delete cabinet item '{id}': {e}");
-                DomainError::InternalError
-            })?;
+        let thumbnail_path = self.thumbnail_file_path(item.cabinet_code, item.id);
+        if thumbnail_path.exists() {
+            self.remove_file(&thumbnail_path)?;
+        }
         Ok(())
     }
 
+    #[tracing::instrument(skip_all, level = "debug", fields(item_id = item.id))]
+    async fn save_thumbnail(
+        &self,
+        item: &CabinetItem,
+        thumbnail: &[u8],
+    ) -> Result<(), DomainError> {
+        // The cabinet may have been emptied while the thumbnail was generated
+        if self.find_model_by_id(item.id).await?.is_none() {
+            log::debug!("Item '{}' is gone, dropping its thumbnail", item.id);
+            return Ok(());
+        }
+        let path = self.thumbnail_file_path(item.cabinet_code, item.id);
+        self.create_parent_folder(&path)?;
+        self.write_content(&path, thumbnail)
+    }
+
+    #[tracing::instrument(skip_all, level = "debug", fields(item_id = item.id))]
+    async fn find_thumbnail(&self, item: &CabinetItem) -> Result<Option<Vec<u8>>, DomainError> {
+        let path = self.thumbnail_file_path(item.cabinet_code, item.id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        self.read_content(&path).map(Some)
+    }
+
+    #[tracing::instrument(skip_all, level = "debug", fields(id = id))]
     async fn find_by_id(
         &self,
         id: i64,
@@ -132,15 +228,29 @@ async fn find_by_id(
         }
         let model = model.unwrap();
 
-        let path = PathBuf::from(&model.path);
+        let path = self.absolute_file_path(&model.path);
         let mut cabinet_item = CabinetItem::try_from(model)?;
         if with_content {
             let content = self.read_content(&path)?;
-            cabinet_item.content = Some(content);
+            cabinet_item.content = Some(compression::decompress(
+                cabinet_item.encoding.as_deref(),
+                content,
+                cabinet_item.size,
+            )?);
         }
         Ok(Some(cabinet_item))
     }
 
+    #[tracing::instrument(skip_all, level = "debug", fields(item_id = item.id))]
+    async fn find_stored_content(&self, item: &CabinetItem) -> Result<Vec<u8>, DomainError> {
+        let model = self
+            .find_model_by_id(item.id)
+            .await?
+            .ok_or(CabinetError::CabinetItemNotFound)?;
+        self.read_content(&self.absolute_file_path(&model.path))
+    }
+
+    #[tracing::instrument(skip_all, level = "debug", fields(cabinet_code = cabinet_code))]
     async fn list_by_cabinet_code(
         &self,
         cabinet_code: i64,
@@ -161,9 +271,101 @@ async fn list_by_cabinet_code(
         }
         Ok(cabinet_items)
     }
+
+    #[tracing::instrument(skip_all, level = "debug")]
+    async fn list_all(&self) -> Result<Vec<CabinetItem>, DomainError> {
+        let models = Entity::find()
+            .order_by_asc(Column::Id)
+            .all(&self.connection)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to list cabinet items: {e}");
+                DomainError::InternalError
+            })?;
+        models.into_iter().map(CabinetItem::try_from).collect()
+    }
+
+    #[tracing::instrument(skip_all, level = "debug")]
+    async fn delete_temporary_files(&self) -> Result<u64, DomainError> {
+        let folders = match std::fs::read_dir(&self.store_folder) {
+            Ok(folders) => folders,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => {
+                log::error!("Failed to read folder '{:?}': {e}", self.store_folder);
+                return Err(DomainError::InternalError);
+            }
+        };
+        // Cabinet folders, and the prefix folders of the objects
+        let folders = folders
+            .flatten()
+            .flat_map(|folder| {
+                if folder.file_name() != OBJECTS_FOLDER {
+                    return vec![folder.path()];
+                }
+                std::fs::read_dir(folder.path())
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                    .map(|prefix| prefix.path())
+                    .collect()
+            })
+            .collect::<Vec<_>>();
+        let mut count = 0;
+        for folder in folders {
+            let Ok(files) = std::fs::read_dir(folder) else {
+                continue;
+            };
+            for file in files.flatten() {
+                let path = file.path();
+                let is_left_over = file
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .and_then(|modified| modified.elapsed().ok())
+                    .is_some_and(|age| age >= TEMPORARY_FILE_AGE);
+                if is_left_over
+                    && path
+                        .extension()
+                        .is_some_and(|ext| ext == TEMPORARY_EXTENSION)
+                {
+                    log::debug!("Removing temporary file '{path:?}'");
+                    self.remove_file(&path)?;
+                    count += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    #[tracing::instrument(skip_all, level = "debug")]
+    async fn total_size(&self) -> Result<u64, DomainError> {
+        use sea_orm::sea_query::{Alias, Func, SimpleExpr};
+        // SUM of a BIGINT is a decimal on PostgreSQL and MySQL, cast it back to an integer
+        let integer = match self.connection.get_database_backend() {
+            sea_orm::DbBackend::MySql => "SIGNED",
+            sea_orm::DbBackend::Postgres => "BIGINT",
+            sea_orm::DbBackend::Sqlite => "INTEGER",
+        };
+        let total = Func::cast_as(
+            Func::coalesce([Expr::col(Column::Size).sum(), Expr::value(0)]),
+            Alias::new(integer),
+        );
+        let total: Option<i64> = Entity::find()
+            .select_only()
+            .column_as(SimpleExpr::from(total), "total")
+            .into_tuple()
+            .one(&self.connection)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to sum cabinet item sizes: {e}");
+                DomainError::InternalError
+            })?;
+        Ok(total.unwrap_or_default().max(0) as u64)
+    }
 }
 
 impl CabinetItemRepository {
+    #[tracing::instrument(skip_all, level = "debug", fields(id = id))]
     async fn find_model_by_id(&self, id: i64) -> Result<Option<Model>, DomainError> {
         Entity::find_by_id(id)
             .one(&self.connection)
@@ -173,6 +375,141 @@ async fn find_model_by_id(&self, id: i64) -> Result<Option<Model>, DomainError>
                 DomainError::InternalError
             })
     }
+
+    /// Reference the object of a content, writing it when it is not stored yet
+    ///
+    /// Returns the encoding the object is stored with, which is the one of the first item
+    /// holding the content.
+    #[tracing::instrument(skip_all, level = "debug", fields(hash = hash))]
+    async fn acquire_object(
+        &self,
+        hash: &str,
+        path: &Path,
+        content: &[u8],
+    ) -> Result<Option<String>, DomainError> {
+        let _lock = Self::object_lock(hash).lock().await;
+        let absolute_path = self.store_folder.join(path);
+        let object = cabinet_object::Entity::find_by_id(hash)
+            .one(&self.connection)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to find object '{hash}': {e}");
+                DomainError::InternalError
+            })?;
+        if let Some(object) = object
+            && absolute_path.exists()
+        {
+            let result = cabinet_object::Entity::update_many()
+                .col_expr(
+                    cabinet_object::Column::RefCount,
+                    Expr::col(cabinet_object::Column::RefCount).add(1),
+                )
+                .col_expr(
+                    cabinet_object::Column::UpdateAt,
+                    Expr::value(chrono::Local::now().naive_local()),
+                )
+                .filter(cabinet_object::Column::Hash.eq(hash))
+                .exec(&self.connection)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to reference object '{hash}': {e}");
+                    DomainError::InternalError
+                })?;
+            // Unless the last item holding it was deleted meanwhile
+            if result.rows_affected == 1 {
+                log::debug!("Content '{hash}' is stored already");
+                return Ok(object.encoding);
+            }
+        }
+
+        let compressed = self
+            .compression_level
+            .and_then(|level| compression::compress(content, level));
+        self.create_parent_folder(&absolute_path)?;
+        log::debug!("Writing content '{hash}' to '{absolute_path:?}'");
+        match compressed.as_deref() {
+            Some(compressed) => {
+                log::debug!(
+                    "Compressed content '{hash}' from {} to {} bytes",
+                    content.len(),
+                    compressed.len()
+                );
+                self.write_content(&absolute_path, compressed)?;
+            }
+            None => self.write_content(&absolute_path, content)?,
+        }
+        let encoding = compressed.map(|_| String::from(compression::ZSTD));
+
+        // A row left without its file is taken over, the file was just written again
+        let now = chrono::Local::now().naive_local();
+        let object = cabinet_object::ActiveModel {
+            hash: Set(hash.to_string()),
+            encoding: Set(encoding.clone()),
+            size: Set(content.len() as i64),
+            ref_count: Set(1),
+            create_at: Set(now),
+            update_at: Set(now),
+            version: Set(1),
+        };
+        cabinet_object::Entity::insert(object)
+            .on_conflict(
+                OnConflict::column(cabinet_object::Column::Hash)
+                    .value(
+                        cabinet_object::Column::RefCount,
+                        Expr::col(cabinet_object::Column::RefCount).add(1),
+                    )
+                    .update_columns([
+                        cabinet_object::Column::Encoding,
+                        cabinet_object::Column::UpdateAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to save object '{hash}': {e}");
+                DomainError::InternalError
+            })?;
+        Ok(encoding)
+    }
+
+    /// Drop a reference to an object, the object is removed with its last reference
+    #[tracing::instrument(skip_all, level = "debug", fields(hash = hash))]
+    async fn release_object(&self, hash: &str) -> Result<(), DomainError> {
+        let _lock = Self::object_lock(hash).lock().await;
+        cabinet_object::Entity::update_many()
+            .col_expr(
+                cabinet_object::Column::RefCount,
+                Expr::col(cabinet_object::Column::RefCount).sub(1),
+            )
+            .col_expr(
+                cabinet_object::Column::UpdateAt,
+                Expr::value(chrono::Local::now().naive_local()),
+            )
+            .filter(cabinet_object::Column::Hash.eq(hash))
+            .exec(&self.connection)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to release object '{hash}': {e}");
+                DomainError::InternalError
+            })?;
+        // Only the deleting caller removes the file, an item saved on a replica keeps it alive
+        let result = cabinet_object::Entity::delete_many()
+            .filter(cabinet_object::Column::Hash.eq(hash))
+            .filter(cabinet_object::Column::RefCount.lte(0))
+            .exec(&self.connection)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to delete object '{hash}': {e}");
+                DomainError::InternalError
+            })?;
+        if result.rows_affected == 1 {
+            let path = self.store_folder.join(Self::object_file_path(hash));
+            log::debug!("Removing content '{hash}' from '{path:?}'");
+            self.remove_file(&path)?;
+        }
+        Ok(())
+    }
 }
 
 impl TryFrom<Model> for CabinetItem {
@@ -187,6 +524,11 @@ fn try_from(value: Model) -> Result<Self, Self::Error> {
             content: None,
             size: value.size,
             sort_order: value.sort_order,
+            mime_type: value.mime_type,
+            language: value.language,
+            url: value.url,
+            encoding: value.encoding,
+            hash: value.hash,
         })
     }
 }
@@ -206,6 +548,11 @@ fn try_from(value: CabinetItem) -> Result<Self, Self::Error> {
             create_at: now,
             update_at: now,
             version: 1,
+            mime_type: value.mime_type,
+            language: value.language,
+            url: value.url,
+            encoding: value.encoding,
+            hash: value.hash,
         })
     }
 }
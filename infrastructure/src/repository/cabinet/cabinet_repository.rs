@@ -17,6 +17,7 @@ pub fn new(connection: DatabaseConnection) -> Self {
 
 #[async_trait::async_trait]
 impl Repository for CabinetRepository {
+    #[tracing::instrument(skip_all, level = "debug", fields(code = cabinet.code))]
     async fn save(&self, cabinet: Cabinet) -> Result<Cabinet, DomainError> {
         let model = Model::from(cabinet);
         let active_model = ActiveModel::from(model);
@@ -27,6 +28,7 @@ async fn save(&self, cabinet: Cabinet) -> Result<Cabinet, DomainError> {
         Ok(Cabinet::try_from(model)?)
     }
 
+    #[tracing::instrument(skip_all, level = "debug", fields(code = code))]
     async fn delete_by_code(&self, code: i64) -> Result<(), DomainError> {
         Entity::delete_by_id(code)
             .exec(&self.connection)
@@ -38,6 +40,7 @@ async fn delete_by_code(&self, code: i64) -> Result<(), DomainError> {
         Ok(())
     }
 
+    #[tracing::instrument(skip_all, level = "debug", fields(code = cabinet.code))]
     async fn update_by_code(&self, cabinet: Cabinet) -> Result<(), DomainError> {
         use sea_orm::ActiveValue;
         let model = Model::from(cabinet);
@@ -58,6 +61,7 @@ async fn update_by_code(&self, cabinet: Cabinet) -> Result<(), DomainError> {
         Ok(())
     }
 
+    #[tracing::instrument(skip_all, level = "debug")]
     async fn count(&self) -> Result<u64, DomainError> {
         Entity::find().count(&self.connection).await.map_err(|e| {
             log::error!("Failed to count cabinet: {e}");
@@ -65,6 +69,7 @@ async fn count(&self) -> Result<u64, DomainError> {
         })
     }
 
+    #[tracing::instrument(skip_all, level = "debug")]
     async fn count_by_status(&self, status: CabinetStatus) -> Result<u64, DomainError> {
         Entity::find()
             .filter(Column::Status.eq(status.code()))
@@ -76,6 +81,7 @@ async fn count_by_status(&self, status: CabinetStatus) -> Result<u64, DomainErro
             })
     }
 
+    #[tracing::instrument(skip_all, level = "debug", fields(code = code))]
     async fn exists_by_code(&self, code: i64) -> Result<bool, DomainError> {
         let count = Entity::find()
             .filter(Column::Code.eq(code))
@@ -88,6 +94,7 @@ async fn exists_by_code(&self, code: i64) -> Result<bool, DomainError> {
         Ok(count > 0)
     }
 
+    #[tracing::instrument(skip_all, level = "debug", fields(code = code))]
     async fn find_by_code(&self, code: i64) -> Result<Option<Cabinet>, DomainError> {
         Entity::find_by_id(code)
             .one(&self.connection)
@@ -100,6 +107,7 @@ async fn find_by_code(&self, code: i64) -> Result<Option<Cabinet>, DomainError>
             .transpose()
     }
 
+    #[tracing::instrument(skip_all, level = "debug")]
     async fn list_expired(&self, time: DateTime<Local>) -> Result<Vec<Cabinet>, DomainError> {
         Entity::find()
             .filter(Column::ExpireAt.lt(time.naive_local()))
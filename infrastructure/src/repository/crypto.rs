@@ -17,6 +17,7 @@ pub fn new(connection: DatabaseConnection) -> Self {
 
 #[async_trait::async_trait]
 impl Repository for CryptoKeypairRepository {
+    #[tracing::instrument(skip_all, level = "debug")]
     async fn save(&self, keypair: CryptoKeypair) -> Result<CryptoKeypair, DomainError> {
         let model: Model = keypair.into();
         let active_model: ActiveModel = model.into();
@@ -27,6 +28,7 @@ async fn save(&self, keypair: CryptoKeypair) -> Result<CryptoKeypair, DomainErro
         Ok(res.into())
     }
 
+    #[tracing::instrument(skip_all, level = "debug", fields(id = %id))]
     async fn delete_by_id(&self, id: Uuid) -> Result<(), DomainError> {
         Entity::delete_by_id(id)
             .exec(&self.connection)
@@ -38,6 +40,7 @@ async fn delete_by_id(&self, id: Uuid) -> Result<(), DomainError> {
         Ok(())
     }
 
+    #[tracing::instrument(skip_all, level = "debug")]
     async fn delete_expired(&self, time: DateTime<Local>) -> Result<u64, DomainError> {
         let result = Entity::delete_many()
             .filter(Column::ExpireAt.lte(time.naive_local()))
@@ -50,6 +53,7 @@ async fn delete_expired(&self, time: DateTime<Local>) -> Result<u64, DomainError
         Ok(result.rows_affected)
     }
 
+    #[tracing::instrument(skip_all, level = "debug")]
     async fn count(&self) -> Result<u64, DomainError> {
         Entity::find().count(&self.connection).await.map_err(|e| {
             log::error!("Failed to count keypairs: {e}");
@@ -57,6 +61,7 @@ async fn count(&self) -> Result<u64, DomainError> {
         })
     }
 
+    #[tracing::instrument(skip_all, level = "debug")]
     async fn find_by_public_key(
         &self,
         public_key: &str,
@@ -6,10 +6,23 @@ pub fn create_cabinet_service(
     connection: sea_orm::DatabaseConnection,
     data_folder: &std::path::Path,
     cabinets_number: u64,
+) -> CabinetService<CabinetRepository, CabinetItemRepository> {
+    create_compressing_cabinet_service(connection, data_folder, cabinets_number, None)
+}
+
+/// Create cabinet service saving contents compressed with zstd at `compression_level`
+///
+/// Contents are read back whatever they were saved with, only saving needs the level.
+pub fn create_compressing_cabinet_service(
+    connection: sea_orm::DatabaseConnection,
+    data_folder: &std::path::Path,
+    cabinets_number: u64,
+    compression_level: Option<i32>,
 ) -> CabinetService<CabinetRepository, CabinetItemRepository> {
     CabinetService::new(
         CabinetRepository::new(connection.clone()),
-        CabinetItemRepository::new(connection, data_folder.join("files")),
+        CabinetItemRepository::new(connection, data_folder.join("files"))
+            .with_compression(compression_level),
         cabinets_number,
     )
 }
@@ -0,0 +1,180 @@
+use crate::error::ErrorView;
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use domain::error::DomainError;
+use domain::error::cabinet::CabinetError;
+use domain::error::crypto::CryptoError;
+
+/// Success body of the v1 api
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct Envelope<T> {
+    pub data: T,
+}
+
+/// Error body of the v1 api
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct ErrorEnvelope {
+    pub error: ErrorView,
+}
+
+/// Response extension marking a body that must be sent as is, e.g. item content
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RawBody;
+
+/// Largest handler body the envelope middleware buffers
+const MAX_ENVELOPE_BODY: usize = 8 * 1024 * 1024;
+
+/// Wrap v1 responses into `{"data": ...}` or `{"error": {...}}`
+pub(crate) async fn wrap(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    if response.extensions().get::<RawBody>().is_some() {
+        return response;
+    }
+
+    let domain_error = response.extensions().get::<DomainError>().cloned();
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_ENVELOPE_BODY).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            log::error!("Failed to read response body: {e}");
+            return internal_error();
+        }
+    };
+    let is_json = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"application/json"));
+
+    let body = if parts.status.is_success() {
+        let data = if is_json {
+            match serde_json::from_slice(&bytes) {
+                Ok(data) => data,
+                Err(e) => {
+                    log::error!("Failed to parse response body: {e}");
+                    return internal_error();
+                }
+            }
+        } else {
+            serde_json::Value::String(String::from_utf8_lossy(&bytes).to_string())
+        };
+        serde_json::to_vec(&Envelope { data })
+    } else {
+        let error = match serde_json::from_slice::<ErrorView>(&bytes) {
+            Ok(error) if is_json => error,
+            _ => ErrorView {
+                code: String::from("InvalidRequest"),
+                message: String::from_utf8_lossy(&bytes).to_string(),
+            },
+        };
+        if let Some(domain_error) = domain_error.as_ref() {
+            parts.status = status_of(domain_error);
+        }
+        serde_json::to_vec(&ErrorEnvelope { error })
+    };
+    let body = match body {
+        Ok(body) => body,
+        Err(e) => {
+            log::error!("Failed to serialize response envelope: {e}");
+            return internal_error();
+        }
+    };
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Error envelope of a response the middleware could not wrap
+fn internal_error() -> Response {
+    use crate::error::error_code;
+    use domain::error::{I18nError, Locale};
+    let error = DomainError::InternalError;
+    let body = ErrorEnvelope {
+        error: ErrorView {
+            code: error_code(&error).to_string(),
+            message: error.i18n_message(Locale::default()).to_string(),
+        },
+    };
+    (StatusCode::INTERNAL_SERVER_ERROR, axum::Json(body)).into_response()
+}
+
+/// Mark responses of the unversioned api as deprecated in favour of `/api/v1`
+pub(crate) async fn deprecated(request: Request, next: Next) -> Response {
+    use axum::extract::OriginalUri;
+    // The router is nested, so the original path carries the mount prefix
+    let path = request.uri().path().to_string();
+    let original_path = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map(|uri| uri.path().to_string())
+        .unwrap_or_else(|| path.clone());
+    let successor = original_path
+        .strip_suffix(&path)
+        .map(|prefix| format!("<{prefix}/v1{path}>; rel=\"successor-version\""));
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static("true"));
+    if let Some(link) = successor.and_then(|link| HeaderValue::from_str(&link).ok()) {
+        headers.insert(header::LINK, link);
+    }
+    response
+}
+
+/// HTTP status of a domain error in the v1 api
+pub(crate) fn status_of(error: &DomainError) -> StatusCode {
+    match error {
+        DomainError::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+        DomainError::LocaleNotSupported => StatusCode::BAD_REQUEST,
+        DomainError::CabinetError(e) => match e {
+            CabinetError::NotFound
+            | CabinetError::CabinetItemNotFound
+            | CabinetError::ThumbnailNotFound
+            | CabinetError::ArchiveEntryNotFound(_) => StatusCode::NOT_FOUND,
+            CabinetError::InvalidPassword | CabinetError::NotYourHoldCabinet(_) => {
+                StatusCode::FORBIDDEN
+            }
+            CabinetError::NoAvailableCabinet
+            | CabinetError::NoEmptyCabinet
+            | CabinetError::ScannerUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            CabinetError::InfectedItem(_, _) | CabinetError::ArchiveTooLarge(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            CabinetError::InvalidFileSize(_, _)
+            | CabinetError::InvalidTotalSize(_)
+            | CabinetError::InvalidTextSize(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            CabinetError::InvalidItemCategory(_)
+            | CabinetError::ItemContentMustNotEmpty
+            | CabinetError::InvalidNumberString(_)
+            | CabinetError::InvalidHours(_)
+            | CabinetError::PasswordRequired
+            | CabinetError::ExpireTimeRequired
+            | CabinetError::HoldTokenRequired
+            | CabinetError::PublicKeyRequired
+            | CabinetError::ItemNotSupportMode(_)
+            | CabinetError::InvalidSnippet(_)
+            | CabinetError::InvalidLink(_)
+            | CabinetError::TooManyLinks(_)
+            | CabinetError::TooManyItems(_)
+            | CabinetError::InvalidArchive(_) => StatusCode::BAD_REQUEST,
+            CabinetError::StatusNotSupport(_)
+            | CabinetError::InvalidItemContent
+            | CabinetError::ItemCorrupted(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        },
+        DomainError::CryptoError(e) => match e {
+            CryptoError::NotFound => StatusCode::NOT_FOUND,
+            CryptoError::MaxKeypairCountReached => StatusCode::SERVICE_UNAVAILABLE,
+            CryptoError::InvalidPublicKey
+            | CryptoError::DecryptionFailed
+            | CryptoError::InvalidHexString
+            | CryptoError::KeypairExpired => StatusCode::BAD_REQUEST,
+            CryptoError::KeypairGenerationFailed
+            | CryptoError::InvalidSecretKey
+            | CryptoError::EncryptionFailed => StatusCode::INTERNAL_SERVER_ERROR,
+        },
+    }
+}
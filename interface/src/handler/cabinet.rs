@@ -1,6 +1,8 @@
+use crate::envelope::{Envelope, ErrorEnvelope, RawBody};
 use crate::error::InterfaceError;
 use crate::extract::AcceptLanguage;
 use crate::handler::ServerState;
+use crate::openapi::BinaryContent;
 use axum::extract::{Json, Path, Query, State};
 use chrono::{DateTime, Local};
 use domain::entity::cabinet::{
@@ -8,9 +10,25 @@
 };
 use domain::error::DomainError;
 use domain::error::cabinet::CabinetError;
-use infrastructure::service::cabinet::create_cabinet_service;
+use domain::service::{archive, compression, highlight, link, metadata, scanner, thumbnail};
+use infrastructure::service::cabinet::{
+    create_cabinet_service, create_compressing_cabinet_service,
+};
 use infrastructure::service::crypto::create_sm2_crypto_service;
 
+/// Policy of stored contents, nothing in them may run or load, even when opened directly
+const CONTENT_SECURITY_POLICY: &str = "sandbox; default-src 'none'";
+
+/// Policy of highlighted snippets, the page styles itself inline
+const HIGHLIGHTED_CONTENT_SECURITY_POLICY: &str =
+    "sandbox; default-src 'none'; style-src 'unsafe-inline'";
+
+/// Longest time fetching the titles of the links of a save may take altogether
+const LINK_TITLES_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Saves whose thumbnails may wait for a slot at the same time
+const MAX_QUEUED_THUMBNAILS: usize = 32;
+
 /// Cabinet router
 pub(crate) fn router() -> axum::Router<ServerState> {
     use axum::routing::{get, post};
@@ -26,10 +44,30 @@ pub(crate) fn router() -> axum::Router<ServerState> {
             "/{cabinet_code}/item/{item_id}/content",
             post(get_item_content),
         )
+        .route(
+            "/{cabinet_code}/item/{item_id}/thumbnail",
+            get(get_item_thumbnail),
+        )
+        .route(
+            "/{cabinet_code}/item/{item_id}/entries",
+            post(get_item_entries),
+        )
+        .route("/{cabinet_code}/item/{item_id}/entry", post(get_item_entry))
 }
 
 /// Apply for a cabinet
+#[utoipa::path(
+    post,
+    path = "/api/v1/cabinet/apply",
+    tag = "cabinet",
+    responses(
+        (status = 200, description = "Cabinet held for 10 minutes", body = Envelope<CabinetView>),
+        (status = "4XX", description = "Rejected request", body = ErrorEnvelope),
+        (status = "5XX", description = "Server error", body = ErrorEnvelope),
+    )
+)]
 #[axum::debug_handler]
+#[tracing::instrument(skip_all)]
 pub(crate) async fn apply(
     State(state): State<ServerState>,
     AcceptLanguage(language): AcceptLanguage,
@@ -47,7 +85,18 @@ pub(crate) async fn apply(
 }
 
 /// Get cabinet usage status
+#[utoipa::path(
+    get,
+    path = "/api/v1/cabinet/usage",
+    tag = "cabinet",
+    responses(
+        (status = 200, description = "Cabinet usage", body = Envelope<CabinetUsage>),
+        (status = "4XX", description = "Rejected request", body = ErrorEnvelope),
+        (status = "5XX", description = "Server error", body = ErrorEnvelope),
+    )
+)]
 #[axum::debug_handler]
+#[tracing::instrument(skip_all)]
 pub(crate) async fn usage(
     State(state): State<ServerState>,
     AcceptLanguage(language): AcceptLanguage,
@@ -65,7 +114,19 @@ pub(crate) async fn usage(
 }
 
 /// Get cabinet by code
+#[utoipa::path(
+    get,
+    path = "/api/v1/cabinet/{cabinet_code}",
+    tag = "cabinet",
+    params(("cabinet_code" = i64, Path, description = "Cabinet code")),
+    responses(
+        (status = 200, description = "Cabinet", body = Envelope<CabinetView>),
+        (status = "4XX", description = "Rejected request", body = ErrorEnvelope),
+        (status = "5XX", description = "Server error", body = ErrorEnvelope),
+    )
+)]
 #[axum::debug_handler]
+#[tracing::instrument(skip_all, fields(cabinet_code = cabinet_code))]
 pub(crate) async fn get_by_code(
     State(state): State<ServerState>,
     AcceptLanguage(language): AcceptLanguage,
@@ -84,14 +145,44 @@ pub(crate) async fn get_by_code(
 }
 
 /// Save cabinet items and update cabinet status to `Occupied`
+#[utoipa::path(
+    post,
+    path = "/api/v1/cabinet/{cabinet_code}",
+    tag = "cabinet",
+    params(("cabinet_code" = i64, Path, description = "Cabinet code")),
+    request_body(content = CabinetSaveForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Cabinet locked", body = Envelope<CabinetView>),
+        (status = "4XX", description = "Rejected request", body = ErrorEnvelope),
+        (status = "5XX", description = "Server error", body = ErrorEnvelope),
+    )
+)]
 #[axum::debug_handler]
+#[tracing::instrument(skip_all, fields(cabinet_code = cabinet_code))]
 pub(crate) async fn save(
     State(state): State<ServerState>,
     AcceptLanguage(language): AcceptLanguage,
     Path(cabinet_code): Path<i64>,
-    mut multipart: axum::extract::Multipart,
+    multipart: axum::extract::Multipart,
 ) -> Result<Json<CabinetView>, InterfaceError> {
+    let result = save_items(state, language, cabinet_code, multipart).await;
+    metrics::counter!("cabinet_uploads_total", "outcome" => crate::metrics::outcome(&result))
+        .increment(1);
+    result.map(|(cabinet, size)| {
+        metrics::counter!("cabinet_upload_bytes_total").increment(size as u64);
+        cabinet
+    })
+}
+
+/// Read the multipart form and save it, returns the cabinet and the stored size
+async fn save_items(
+    state: ServerState,
+    language: domain::error::Locale,
+    cabinet_code: i64,
+    mut multipart: axum::extract::Multipart,
+) -> Result<(Json<CabinetView>, usize), InterfaceError> {
     const MAX_MSG_SIZE: usize = 2000;
+    const MAX_SNIPPET_SIZE: usize = 64 * 1024;
     const MAX_FILE_SIZE: usize = 2 * 1024 * 1024;
     const MAX_TOTAL_SIZE: usize = 10 * 1024 * 1024;
 
@@ -100,6 +191,9 @@ pub(crate) async fn save(
     let mut items = Vec::new();
     let mut order = 1;
     let mut total_size = 0;
+    let mut strip_metadata = false;
+    let mut snippets = 0;
+    let mut links = 0;
     loop {
         let field = match multipart.next_field().await {
             Ok(Some(field)) => field,
@@ -116,6 +210,16 @@ pub(crate) async fn save(
         }
         let field_name = field_name.unwrap().to_string();
         log::debug!("Cabinet '{cabinet_code}' save with field '{field_name}'.");
+        if matches!(
+            field_name.as_str(),
+            "message" | "snippets" | "links" | "files"
+        ) && order > CabinetItem::MAX_ITEMS as i64
+        {
+            return Err(InterfaceError::new(
+                language,
+                CabinetError::TooManyItems(CabinetItem::MAX_ITEMS).into(),
+            ));
+        }
         match field_name.as_str() {
             "password" => match field.text().await {
                 Ok(password) => {
@@ -165,6 +269,14 @@ pub(crate) async fn save(
                     return Err(InterfaceError::new(language, DomainError::InternalError));
                 }
             },
+            "strip_metadata" => match field.text().await {
+                // `on` is what a checkbox sends
+                Ok(text) => strip_metadata = matches!(text.trim(), "true" | "1" | "on"),
+                Err(e) => {
+                    log::error!("Failed to read strip_metadata: {e:?}");
+                    return Err(InterfaceError::new(language, DomainError::InternalError));
+                }
+            },
             "message" => {
                 let bytes = field.text().await.unwrap().into_bytes();
                 let text_size = bytes.len();
@@ -176,7 +288,7 @@ pub(crate) async fn save(
                 }
 
                 let text_item = CabinetItem::new(
-                    cabinet_code * 10 + order,
+                    CabinetItem::item_id(cabinet_code, order),
                     cabinet_code,
                     CabinetItemCategory::Text,
                     String::from("message.txt"),
@@ -193,10 +305,91 @@ pub(crate) async fn save(
                 order += 1;
                 total_size += text_size;
             }
+            "snippets" => {
+                snippets += 1;
+                let snippet = field
+                    .text()
+                    .await
+                    .ok()
+                    .and_then(|text| serde_json::from_str::<CabinetSnippet>(&text).ok())
+                    .ok_or(InterfaceError::new(
+                        language,
+                        CabinetError::InvalidSnippet(snippets).into(),
+                    ))?;
+                let snippet_language = match snippet.language.as_deref().map(str::trim) {
+                    None | Some("") => None,
+                    Some(tag) => Some(highlight::normalize_language(tag).ok_or(
+                        InterfaceError::new(
+                            language,
+                            CabinetError::InvalidSnippet(snippets).into(),
+                        ),
+                    )?),
+                };
+                let text_size = snippet.content.len();
+                if text_size > MAX_SNIPPET_SIZE {
+                    return Err(InterfaceError::new(
+                        language,
+                        CabinetError::InvalidTextSize(text_size).into(),
+                    ))?;
+                }
+                let name = match snippet.name.as_deref().map(str::trim) {
+                    None | Some("") => format!("snippet-{snippets}.txt"),
+                    Some(name) => CabinetItem::sanitize_name(name),
+                };
+                let mut text_item = CabinetItem::new(
+                    CabinetItem::item_id(cabinet_code, order),
+                    cabinet_code,
+                    CabinetItemCategory::Text,
+                    name,
+                    snippet.content.into_bytes(),
+                    order as i32,
+                );
+                text_item.language = snippet_language;
+                log::debug!(
+                    "Cabinet '{}' add snippet item '{}' ({}).",
+                    cabinet_code,
+                    &text_item.name,
+                    text_size
+                );
+                items.push(text_item);
+                order += 1;
+                total_size += text_size;
+            }
+            "links" => {
+                links += 1;
+                if links > link::MAX_LINKS {
+                    return Err(InterfaceError::new(
+                        language,
+                        CabinetError::TooManyLinks(link::MAX_LINKS).into(),
+                    ));
+                }
+                let url = field
+                    .text()
+                    .await
+                    .ok()
+                    .and_then(|text| link::parse(&text))
+                    .ok_or(InterfaceError::new(
+                        language,
+                        CabinetError::InvalidLink(links).into(),
+                    ))?;
+                let link_item = CabinetItem::link(
+                    CabinetItem::item_id(cabinet_code, order),
+                    cabinet_code,
+                    &url,
+                    order as i32,
+                );
+                log::debug!(
+                    "Cabinet '{}' add link item '{}' ({}).",
+                    cabinet_code,
+                    &link_item.name,
+                    link_item.size
+                );
+                total_size += link_item.size as usize;
+                items.push(link_item);
+                order += 1;
+            }
             "files" => {
-                let filename = field.file_name().unwrap_or("unknown");
-                let filename = filename.rsplit('/').next().unwrap_or("unknown");
-                let filename = filename.to_string();
+                let filename = CabinetItem::sanitize_name(field.file_name().unwrap_or_default());
                 let bytes = field.bytes().await.map_err(|e| {
                     log::error!("Failed to read file '{filename}': {e:?}");
                     InterfaceError::new(language, DomainError::InternalError)
@@ -209,7 +402,7 @@ pub(crate) async fn save(
                     ))?;
                 }
                 let file_item = CabinetItem::new(
-                    cabinet_code * 10 + order,
+                    CabinetItem::item_id(cabinet_code, order),
                     cabinet_code,
                     CabinetItemCategory::File,
                     filename,
@@ -258,9 +451,7 @@ pub(crate) async fn save(
         ));
     }
 
-    let transaction = infrastructure::database::begin_transaction(&state.connection)
-        .await
-        .map_err(|e| InterfaceError::new(language, e))?;
+    // Check the credential before any costly work on the items
     let public_key = public_key.unwrap();
     let crypto_service =
         create_sm2_crypto_service(state.connection.clone(), state.max_keypair_number);
@@ -280,12 +471,57 @@ pub(crate) async fn save(
         .delete_by_id(keypair.id.unwrap())
         .await
         .map_err(|e| InterfaceError::new(language, e))?;
-
-    let cabinet_service = create_cabinet_service(
-        state.connection,
+    let cabinet_service = create_compressing_cabinet_service(
+        state.connection.clone(),
         &state.data_folder,
         state.max_cabinet_number,
+        state.compression_level,
     );
+    cabinet_service
+        .find_held(&cabinet)
+        .await
+        .map_err(|e| InterfaceError::new(language, e))?;
+
+    // The field may come after the files, so items are stripped once all are read
+    let mut metadata_kept = Vec::new();
+    let stripped_bytes = strip_metadata.then(|| {
+        items
+            .iter_mut()
+            .map(|item| match metadata::strip(item) {
+                Some(removed) => removed,
+                None => {
+                    metadata_kept.push(item.name.clone());
+                    0
+                }
+            })
+            .sum()
+    });
+    if let Some(fetcher) = &state.title_fetcher {
+        // Links not reached in time keep their host as name
+        let fetch = link::fetch_titles(fetcher.as_ref(), &mut items);
+        if tokio::time::timeout(LINK_TITLES_TIMEOUT, fetch)
+            .await
+            .is_err()
+        {
+            log::warn!("Fetching link titles took over {LINK_TITLES_TIMEOUT:?}, stopped");
+        }
+    }
+    // Before the transaction, a scan may take a while
+    if let Some(scanner) = &state.scanner {
+        scanner::scan_items(scanner.as_ref(), &items, state.scan_fail_open)
+            .await
+            .map_err(|e| InterfaceError::new(language, e))?;
+    }
+
+    let transaction = infrastructure::database::begin_transaction(&state.connection)
+        .await
+        .map_err(|e| InterfaceError::new(language, e))?;
+    let images = items
+        .iter()
+        .filter(|item| thumbnail::is_supported(item))
+        .cloned()
+        .collect::<Vec<_>>();
+    let thumbnail_state = state.clone();
     let cabinet = cabinet_service
         .save(cabinet, items)
         .await
@@ -294,11 +530,73 @@ pub(crate) async fn save(
         .commit()
         .await
         .map_err(|e| InterfaceError::new(language, e))?;
-    Ok(Json(CabinetView::from(cabinet)))
+    spawn_thumbnails(thumbnail_state, images);
+    let mut view = CabinetView::from(cabinet);
+    view.stripped_bytes = stripped_bytes;
+    view.metadata_kept = strip_metadata.then_some(metadata_kept);
+    Ok((Json(view), total_size))
+}
+
+/// Generate the thumbnails of saved images in the background, the upload does not wait for them
+fn spawn_thumbnails(state: ServerState, items: Vec<CabinetItem>) {
+    use tracing::Instrument;
+
+    if items.is_empty() {
+        return;
+    }
+    // Queued items hold their content, a flood of saves skips thumbnails rather than memory
+    if state.background_tasks.len() >= MAX_QUEUED_THUMBNAILS {
+        log::warn!("Thumbnail queue is full, skipping {} items", items.len());
+        return;
+    }
+    let background_tasks = state.background_tasks.clone();
+    let task = async move {
+        let cabinet_service = create_cabinet_service(
+            state.connection,
+            &state.data_folder,
+            state.max_cabinet_number,
+        );
+        for mut item in items {
+            let Some(content) = item.content.take() else {
+                continue;
+            };
+            let Ok(_slot) = state.thumbnail_slots.clone().acquire_owned().await else {
+                return;
+            };
+            // Decoding is CPU bound, keep it off the runtime threads
+            let result = tokio::task::spawn_blocking(move || thumbnail::generate(&content)).await;
+            let thumbnail = match result {
+                Ok(Ok(thumbnail)) => thumbnail,
+                // Already logged, the item simply has no thumbnail
+                Ok(Err(_)) => continue,
+                Err(e) => {
+                    log::error!("Thumbnail of item '{}' panicked: {e}", item.id);
+                    continue;
+                }
+            };
+            if let Err(e) = cabinet_service.save_thumbnail(&item, &thumbnail).await {
+                log::error!("Failed to save thumbnail of item '{}': {e}", item.id);
+            }
+        }
+    };
+    background_tasks.spawn(task.in_current_span());
 }
 
 /// Delete cabinet and items
+#[utoipa::path(
+    delete,
+    path = "/api/v1/cabinet/{cabinet_code}",
+    tag = "cabinet",
+    params(("cabinet_code" = i64, Path, description = "Cabinet code")),
+    request_body = CabinetCredential,
+    responses(
+        (status = 200, description = "Cabinet reclaimed", body = Envelope<bool>),
+        (status = "4XX", description = "Rejected request", body = ErrorEnvelope),
+        (status = "5XX", description = "Server error", body = ErrorEnvelope),
+    )
+)]
 #[axum::debug_handler]
+#[tracing::instrument(skip_all, fields(cabinet_code = cabinet_code))]
 pub(crate) async fn delete_cabinet(
     State(state): State<ServerState>,
     AcceptLanguage(language): AcceptLanguage,
@@ -329,7 +627,20 @@ pub(crate) async fn delete_cabinet(
 }
 
 /// Get cabinet items
+#[utoipa::path(
+    post,
+    path = "/api/v1/cabinet/{cabinet_code}/items",
+    tag = "cabinet",
+    params(("cabinet_code" = i64, Path, description = "Cabinet code")),
+    request_body = CabinetCredential,
+    responses(
+        (status = 200, description = "Items in the cabinet", body = Envelope<Vec<CabinetItemView>>),
+        (status = "4XX", description = "Rejected request", body = ErrorEnvelope),
+        (status = "5XX", description = "Server error", body = ErrorEnvelope),
+    )
+)]
 #[axum::debug_handler]
+#[tracing::instrument(skip_all, fields(cabinet_code = cabinet_code))]
 pub(crate) async fn items(
     State(state): State<ServerState>,
     AcceptLanguage(language): AcceptLanguage,
@@ -357,16 +668,79 @@ pub(crate) async fn items(
 }
 
 /// Get cabinet item content
+#[utoipa::path(
+    post,
+    path = "/api/v1/cabinet/{cabinet_code}/item/{item_id}/content",
+    tag = "cabinet",
+    params(
+        ("cabinet_code" = i64, Path, description = "Cabinet code"),
+        ("item_id" = i64, Path, description = "Item id"),
+        CabinetItemContentParams,
+    ),
+    request_body = CabinetCredential,
+    responses(
+        (status = 200, description = "Item content", content(
+            (String = "text/plain"),
+            (String = "text/html"),
+            (BinaryContent = "application/octet-stream"),
+        )),
+        (status = "4XX", description = "Rejected request", body = ErrorEnvelope),
+        (status = "5XX", description = "Server error", body = ErrorEnvelope),
+    )
+)]
 #[axum::debug_handler]
+#[tracing::instrument(skip_all, fields(cabinet_code = cabinet_code, item_id = item_id))]
 pub(crate) async fn get_item_content(
     State(state): State<ServerState>,
     AcceptLanguage(language): AcceptLanguage,
     Path((cabinet_code, item_id)): Path<(i64, i64)>,
     Query(params): Query<CabinetItemContentParams>,
+    headers: axum::http::HeaderMap,
     Json(credential): Json<CabinetCredential>,
+) -> Result<axum::response::Response, InterfaceError> {
+    let result = item_content(
+        state,
+        language,
+        cabinet_code,
+        item_id,
+        params,
+        &headers,
+        credential,
+    )
+    .await;
+    metrics::counter!("cabinet_downloads_total", "outcome" => crate::metrics::outcome(&result))
+        .increment(1);
+    result.map(crate::metrics::count_download)
+}
+
+/// Get the thumbnail of an image item
+///
+/// Thumbnails are generated in the background after saving, shortly after that this answers
+/// `404`. Takes the credential as query parameters so the response can feed an `<img>`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/cabinet/{cabinet_code}/item/{item_id}/thumbnail",
+    tag = "cabinet",
+    params(
+        ("cabinet_code" = i64, Path, description = "Cabinet code"),
+        ("item_id" = i64, Path, description = "Item id"),
+        CabinetCredential,
+    ),
+    responses(
+        (status = 200, description = "WebP thumbnail", content((BinaryContent = "image/webp"))),
+        (status = "4XX", description = "Rejected request", body = ErrorEnvelope),
+        (status = "5XX", description = "Server error", body = ErrorEnvelope),
+    )
+)]
+#[tracing::instrument(skip_all, fields(cabinet_code = cabinet_code, item_id = item_id))]
+pub(crate) async fn get_item_thumbnail(
+    State(state): State<ServerState>,
+    AcceptLanguage(language): AcceptLanguage,
+    Path((cabinet_code, item_id)): Path<(i64, i64)>,
+    Query(credential): Query<CabinetCredential>,
 ) -> Result<axum::response::Response, InterfaceError> {
     use axum::body::Body;
-    use axum::http::header::HeaderValue;
+    use axum::http::header::{self, HeaderValue};
     use axum::response::Response;
     let _ = validate_cabinet_permission(&state, cabinet_code, credential)
         .await
@@ -376,23 +750,297 @@ pub(crate) async fn get_item_content(
         &state.data_folder,
         state.max_cabinet_number,
     );
-    // Get item
     let item = cabinet_service
-        .get_item_by_id(item_id, true)
+        .get_item_by_id(item_id, false)
         .await
         .map_err(|e| InterfaceError::new(language, e))?
+        .filter(|item| item.cabinet_code == cabinet_code)
         .ok_or(InterfaceError::new(
             language,
             CabinetError::CabinetItemNotFound.into(),
         ))?;
+    let thumbnail = cabinet_service
+        .get_thumbnail(&item)
+        .await
+        .map_err(|e| InterfaceError::new(language, e))?;
+    Ok(Response::builder()
+        .header(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(thumbnail::THUMBNAIL_MIME_TYPE),
+        )
+        // The url carries a one-time credential, a cached copy could never be revalidated
+        .header(header::CACHE_CONTROL, HeaderValue::from_static("no-store"))
+        .header(
+            header::CONTENT_SECURITY_POLICY,
+            HeaderValue::from_static(CONTENT_SECURITY_POLICY),
+        )
+        .extension(RawBody)
+        .body(Body::from(thumbnail))
+        .unwrap())
+}
 
-    let content = item.content.ok_or(InterfaceError::new(
-        language,
-        CabinetError::InvalidItemContent.into(),
-    ))?;
-    match params.mode.as_str() {
+/// List the files of a ZIP or tar item
+///
+/// Tars may be gzip or zstd compressed. Entries whose path could escape the folder they are
+/// extracted to are left out.
+#[utoipa::path(
+    post,
+    path = "/api/v1/cabinet/{cabinet_code}/item/{item_id}/entries",
+    tag = "cabinet",
+    params(
+        ("cabinet_code" = i64, Path, description = "Cabinet code"),
+        ("item_id" = i64, Path, description = "Item id"),
+    ),
+    request_body = CabinetCredential,
+    responses(
+        (status = 200, description = "Files in the archive", body = Envelope<Vec<CabinetArchiveEntryView>>),
+        (status = "4XX", description = "Rejected request", body = ErrorEnvelope),
+        (status = "5XX", description = "Server error", body = ErrorEnvelope),
+    )
+)]
+#[axum::debug_handler]
+#[tracing::instrument(skip_all, fields(cabinet_code = cabinet_code, item_id = item_id))]
+pub(crate) async fn get_item_entries(
+    State(state): State<ServerState>,
+    AcceptLanguage(language): AcceptLanguage,
+    Path((cabinet_code, item_id)): Path<(i64, i64)>,
+    Json(credential): Json<CabinetCredential>,
+) -> Result<Json<Vec<CabinetArchiveEntryView>>, InterfaceError> {
+    let (item, content, _slot) = archive_content(&state, cabinet_code, item_id, credential)
+        .await
+        .map_err(|e| InterfaceError::new(language, e))?;
+    // Decompressing is CPU bound, keep it off the runtime threads
+    let entries = tokio::task::spawn_blocking(move || archive::list(&item, &content))
+        .await
+        .map_err(|e| {
+            log::error!("Listing item '{item_id}' panicked: {e}");
+            InterfaceError::new(language, DomainError::InternalError)
+        })?
+        .map_err(|e| InterfaceError::new(language, e))?;
+    Ok(Json(
+        entries
+            .into_iter()
+            .map(CabinetArchiveEntryView::from)
+            .collect(),
+    ))
+}
+
+/// Download one file of a ZIP or tar item
+#[utoipa::path(
+    post,
+    path = "/api/v1/cabinet/{cabinet_code}/item/{item_id}/entry",
+    tag = "cabinet",
+    params(
+        ("cabinet_code" = i64, Path, description = "Cabinet code"),
+        ("item_id" = i64, Path, description = "Item id"),
+        CabinetArchiveEntryParams,
+    ),
+    request_body = CabinetCredential,
+    responses(
+        (status = 200, description = "File content", content((BinaryContent = "application/octet-stream"))),
+        (status = "4XX", description = "Rejected request", body = ErrorEnvelope),
+        (status = "5XX", description = "Server error", body = ErrorEnvelope),
+    )
+)]
+#[axum::debug_handler]
+#[tracing::instrument(skip_all, fields(cabinet_code = cabinet_code, item_id = item_id))]
+pub(crate) async fn get_item_entry(
+    State(state): State<ServerState>,
+    AcceptLanguage(language): AcceptLanguage,
+    Path((cabinet_code, item_id)): Path<(i64, i64)>,
+    Query(params): Query<CabinetArchiveEntryParams>,
+    Json(credential): Json<CabinetCredential>,
+) -> Result<axum::response::Response, InterfaceError> {
+    use axum::body::Body;
+    use axum::http::header::{self, HeaderValue};
+    use axum::response::Response;
+    use futures_util::StreamExt;
+    let (item, content, slot) = archive_content(&state, cabinet_code, item_id, credential)
+        .await
+        .map_err(|e| InterfaceError::new(language, e))?;
+    let path = params.path.clone();
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(ENTRY_CHUNKS);
+    // Decompressing is CPU bound, keep it off the runtime threads
+    let extraction = tokio::task::spawn_blocking(move || {
+        let _slot = slot;
+        let mut output = std::io::BufWriter::with_capacity(ENTRY_CHUNK_SIZE, BodyWriter(sender));
+        let result = archive::extract(&item, &content, &path, &mut output);
+        if result.is_err() {
+            // Drop what is still buffered, a small entry failing then gets an error response
+            let _ = output.into_parts();
+        }
+        result
+    });
+    // Wait for the first chunk, the entry could be missing or the archive invalid
+    let (first, extracting) = match receiver.recv().await {
+        Some(chunk) => (chunk, Some((receiver, extraction))),
+        None => {
+            extraction
+                .await
+                .map_err(|e| {
+                    log::error!("Extracting from item '{item_id}' panicked: {e}");
+                    InterfaceError::new(language, DomainError::InternalError)
+                })?
+                .map_err(|e| InterfaceError::new(language, e))?;
+            (axum::body::Bytes::new(), None)
+        }
+    };
+    let rest = futures_util::stream::unfold(extracting, move |extracting| async move {
+        let (mut receiver, extraction) = extracting?;
+        if let Some(chunk) = receiver.recv().await {
+            return Some((Ok(chunk), Some((receiver, extraction))));
+        }
+        // Headers are gone, an entry failing its checks now aborts the body
+        let error = match extraction.await {
+            Ok(Ok(_)) => return None,
+            Ok(Err(e)) => std::io::Error::other(e),
+            Err(e) => {
+                log::error!("Extracting from item '{item_id}' panicked: {e}");
+                std::io::Error::other(e)
+            }
+        };
+        log::warn!("Extracting from item '{item_id}' failed midway: {error}");
+        Some((Err(error), None))
+    });
+    let body = futures_util::stream::iter([Ok(first)]).chain(rest);
+    let filename = params.path.rsplit('/').next().unwrap_or_default();
+    Ok(Response::builder()
+        .header(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/octet-stream"),
+        )
+        .header(
+            header::CONTENT_DISPOSITION,
+            content_disposition("attachment", filename),
+        )
+        .header(
+            header::CONTENT_SECURITY_POLICY,
+            HeaderValue::from_static(CONTENT_SECURITY_POLICY),
+        )
+        .extension(RawBody)
+        .body(Body::from_stream(body))
+        .unwrap())
+}
+
+/// Chunks of an archive entry waiting to be sent, extracting blocks once they are all taken
+const ENTRY_CHUNKS: usize = 4;
+
+/// Bytes of an archive entry sent at once
+const ENTRY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Writer handing an extracted entry over to the response body
+struct BodyWriter(tokio::sync::mpsc::Sender<axum::body::Bytes>);
+
+impl std::io::Write for BodyWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // The client went away, stop extracting
+        self.0
+            .blocking_send(axum::body::Bytes::copy_from_slice(buf))
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Check the credential and read an item of the cabinet, decoded and verified
+///
+/// The slot returned is to be held until the archive was read, waiting for one when all are taken.
+async fn archive_content(
+    state: &ServerState,
+    cabinet_code: i64,
+    item_id: i64,
+    credential: CabinetCredential,
+) -> Result<(CabinetItem, Vec<u8>, tokio::sync::OwnedSemaphorePermit), DomainError> {
+    let _ = validate_cabinet_permission(state, cabinet_code, credential).await?;
+    let slot = state
+        .extraction_slots
+        .clone()
+        .acquire_owned()
+        .await
+        .map_err(|_| DomainError::InternalError)?;
+    let cabinet_service = create_cabinet_service(
+        state.connection.clone(),
+        &state.data_folder,
+        state.max_cabinet_number,
+    );
+    let mut item = cabinet_service
+        .get_item_by_id(item_id, true)
+        .await?
+        .filter(|item| item.cabinet_code == cabinet_code)
+        .ok_or(CabinetError::CabinetItemNotFound)?;
+    let content = item.content.take().unwrap_or_default();
+    item.verify(&content)?;
+    Ok((item, content, slot))
+}
+
+/// Read an item and build the response for the requested mode
+async fn item_content(
+    state: ServerState,
+    language: domain::error::Locale,
+    cabinet_code: i64,
+    item_id: i64,
+    params: CabinetItemContentParams,
+    headers: &axum::http::HeaderMap,
+    credential: CabinetCredential,
+) -> Result<axum::response::Response, InterfaceError> {
+    use axum::body::Body;
+    use axum::http::header::HeaderValue;
+    use axum::response::Response;
+    let _ = validate_cabinet_permission(&state, cabinet_code, credential)
+        .await
+        .map_err(|e| InterfaceError::new(language, e))?;
+    let cabinet_service = create_cabinet_service(
+        state.connection,
+        &state.data_folder,
+        state.max_cabinet_number,
+    );
+    // Get item, only of the cabinet the credential opened
+    let item = cabinet_service
+        .get_item_by_id(item_id, false)
+        .await
+        .map_err(|e| InterfaceError::new(language, e))?
+        .filter(|item| item.cabinet_code == cabinet_code)
+        .ok_or(InterfaceError::new(
+            language,
+            CabinetError::CabinetItemNotFound.into(),
+        ))?;
+    let content = cabinet_service
+        .get_item_stored_content(&item)
+        .await
+        .map_err(|e| InterfaceError::new(language, e))?;
+    // Compressed contents go out as stored when the client accepts their encoding, the
+    // compression layer leaves responses with a `Content-Encoding` alone
+    let content_encoding = item
+        .encoding
+        .as_deref()
+        .filter(|encoding| params.mode != "highlighted" && accepts_encoding(headers, encoding));
+    // The hash is of the decoded content, a passthrough is decoded too to check it
+    let stored = content_encoding.map(|_| content.clone());
+    let content = compression::decompress(item.encoding.as_deref(), content, item.size)
+        .map_err(|e| InterfaceError::new(language, e))?;
+    item.verify(&content)
+        .map_err(|e| InterfaceError::new(language, e))?;
+    let (content, digest) = match stored {
+        Some(stored) => {
+            let digest = CabinetItem::content_hash(&stored);
+            (stored, digest)
+        }
+        None => {
+            let digest = item
+                .hash
+                .clone()
+                .unwrap_or_else(|| CabinetItem::content_hash(&content));
+            (content, digest)
+        }
+    };
+    // A highlighted page is not the content, its digest would not match
+    let digest = (params.mode != "highlighted").then_some(digest);
+    let response: Result<Response, InterfaceError> = match params.mode.as_str() {
         "text" => {
-            if item.category != CabinetItemCategory::Text {
+            if item.category == CabinetItemCategory::File {
                 return Err(InterfaceError::new(
                     language,
                     CabinetError::ItemNotSupportMode(params.mode).into(),
@@ -401,11 +1049,46 @@ pub(crate) async fn get_item_content(
             Ok(Response::builder()
                 .header(
                     axum::http::header::CONTENT_TYPE,
-                    HeaderValue::from_static("text/plain"),
+                    HeaderValue::from_static(domain::entity::cabinet::TEXT_MIME_TYPE),
                 )
+                .header(
+                    axum::http::header::CONTENT_DISPOSITION,
+                    HeaderValue::from_static("attachment"),
+                )
+                .header(
+                    axum::http::header::CONTENT_SECURITY_POLICY,
+                    HeaderValue::from_static(CONTENT_SECURITY_POLICY),
+                )
+                .extension(RawBody)
                 .body(Body::from(content))
                 .unwrap())
         }
+        "highlighted" => {
+            if item.category != CabinetItemCategory::Text {
+                return Err(InterfaceError::new(
+                    language,
+                    CabinetError::ItemNotSupportMode(params.mode).into(),
+                ))?;
+            }
+            let code = String::from_utf8_lossy(&content);
+            let html = highlight::render_html(&item.name, item.language.as_deref(), &code);
+            Ok(Response::builder()
+                .header(
+                    axum::http::header::CONTENT_TYPE,
+                    HeaderValue::from_static("text/html; charset=utf-8"),
+                )
+                .header(
+                    axum::http::header::CONTENT_DISPOSITION,
+                    content_disposition("inline", &format!("{}.html", item.name)),
+                )
+                .header(
+                    axum::http::header::CONTENT_SECURITY_POLICY,
+                    HeaderValue::from_static(HIGHLIGHTED_CONTENT_SECURITY_POLICY),
+                )
+                .extension(RawBody)
+                .body(Body::from(html))
+                .unwrap())
+        }
         "file" => Ok(Response::builder()
             .header(
                 axum::http::header::CONTENT_TYPE,
@@ -413,15 +1096,143 @@ pub(crate) async fn get_item_content(
             )
             .header(
                 axum::http::header::CONTENT_DISPOSITION,
-                format!("attachment; filename={}", item.name),
+                content_disposition("attachment", &item.name),
+            )
+            .header(
+                axum::http::header::CONTENT_SECURITY_POLICY,
+                HeaderValue::from_static(CONTENT_SECURITY_POLICY),
             )
+            .extension(RawBody)
             .body(Body::from(content))
             .unwrap()),
+        "inline" => {
+            if !item.is_previewable() {
+                return Err(InterfaceError::new(
+                    language,
+                    CabinetError::ItemNotSupportMode(params.mode).into(),
+                ))?;
+            }
+            let content_type = HeaderValue::from_str(&item.mime_type).unwrap_or(
+                HeaderValue::from_static(domain::entity::cabinet::BINARY_MIME_TYPE),
+            );
+            Ok(Response::builder()
+                .header(axum::http::header::CONTENT_TYPE, content_type)
+                .header(
+                    axum::http::header::CONTENT_DISPOSITION,
+                    content_disposition("inline", &item.name),
+                )
+                .header(
+                    axum::http::header::CONTENT_SECURITY_POLICY,
+                    HeaderValue::from_static(CONTENT_SECURITY_POLICY),
+                )
+                .extension(RawBody)
+                .body(Body::from(content))
+                .unwrap())
+        }
         _ => Err(InterfaceError::new(
             language,
             CabinetError::ItemNotSupportMode(params.mode).into(),
         ))?,
+    };
+    let mut response = response?;
+    if let Some(encoding) = content_encoding {
+        response.headers_mut().insert(
+            axum::http::header::CONTENT_ENCODING,
+            HeaderValue::from_str(encoding).unwrap(),
+        );
+    }
+    if item.encoding.is_some() {
+        response.headers_mut().insert(
+            axum::http::header::VARY,
+            HeaderValue::from_static("accept-encoding"),
+        );
     }
+    if let Some(digest) = digest {
+        insert_digest(response.headers_mut(), &digest);
+    }
+    Ok(response)
+}
+
+/// Set `Repr-Digest` and the older `Digest` to the SHA-256 of the body as sent, given in hex
+///
+/// The compression layer leaves responses carrying them alone, re-encoding would break them.
+fn insert_digest(headers: &mut axum::http::HeaderMap, digest: &str) {
+    use axum::http::header::{HeaderName, HeaderValue};
+    use base64::Engine;
+    let Ok(digest) = hex::decode(digest) else {
+        return;
+    };
+    let digest = base64::engine::general_purpose::STANDARD.encode(digest);
+    headers.insert(
+        HeaderName::from_static("repr-digest"),
+        HeaderValue::from_str(&format!("sha-256=:{digest}:")).unwrap(),
+    );
+    headers.insert(
+        HeaderName::from_static("digest"),
+        HeaderValue::from_str(&format!("sha-256={digest}")).unwrap(),
+    );
+}
+
+/// Whether `Accept-Encoding` allows `encoding`, by name or by `*`, with a non-zero quality
+fn accepts_encoding(headers: &axum::http::HeaderMap, encoding: &str) -> bool {
+    let mut named = None;
+    let mut any = None;
+    for value in headers.get_all(axum::http::header::ACCEPT_ENCODING) {
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+        for coding in value.split(',') {
+            let mut parts = coding.split(';');
+            let name = parts.next().unwrap_or_default().trim();
+            let quality = parts
+                .find_map(|parameter| parameter.trim().strip_prefix("q="))
+                .and_then(|quality| quality.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if name.eq_ignore_ascii_case(encoding) {
+                named = Some(quality);
+            } else if name == "*" {
+                any = Some(quality);
+            }
+        }
+    }
+    named.or(any).is_some_and(|quality| quality > 0.0)
+}
+
+/// `disposition` with an ASCII `filename` for old clients and the exact UTF-8 `filename*` (RFC 6266)
+///
+/// Stored names are sanitized again, rows saved before uploads were sanitized may hold anything.
+fn content_disposition(disposition: &str, name: &str) -> String {
+    let name = CabinetItem::sanitize_name(name);
+    let fallback = name
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' && c != '%' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    let mut encoded = String::with_capacity(name.len() * 3);
+    for byte in name.bytes() {
+        match byte {
+            // attr-char of RFC 8187
+            b'a'..=b'z'
+            | b'A'..=b'Z'
+            | b'0'..=b'9'
+            | b'!'
+            | b'#'
+            | b'$'
+            | b'&'
+            | b'+'
+            | b'-'
+            | b'.'
+            | b'^'
+            | b'_'
+            | b'`'
+            | b'|'
+            | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    format!("{disposition}; filename=\"{fallback}\"; filename*=UTF-8''{encoded}")
 }
 
 /// Validate cabinet permission and return cabinet
@@ -460,7 +1271,7 @@ async fn validate_cabinet_permission(
 }
 
 /// Cabinet struct for view
-#[derive(Debug, serde::Serialize)]
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 pub struct CabinetView {
     pub code: i64,
     pub name: Option<String>,
@@ -468,6 +1279,29 @@ pub struct CabinetView {
     pub status: CabinetStatus,
     pub hold_token: Option<String>,
     pub expire_at: Option<DateTime<Local>>,
+    /// Bytes of metadata removed, only in the response of a `save` with `strip_metadata`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stripped_bytes: Option<usize>,
+    /// Files stored with their metadata as it was, they could not be stripped, only in the
+    /// response of a `save` with `strip_metadata`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata_kept: Option<Vec<String>>,
+}
+
+/// Hold token is redacted
+impl std::fmt::Debug for CabinetView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CabinetView")
+            .field("code", &self.code)
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .field("status", &self.status)
+            .field("hold_token", &domain::entity::redact(&self.hold_token))
+            .field("expire_at", &self.expire_at)
+            .field("stripped_bytes", &self.stripped_bytes)
+            .field("metadata_kept", &self.metadata_kept)
+            .finish()
+    }
 }
 
 impl From<Cabinet> for CabinetView {
@@ -479,11 +1313,14 @@ fn from(value: Cabinet) -> Self {
             status: value.status,
             hold_token: value.hold_token,
             expire_at: value.expire_at,
+            stripped_bytes: None,
+            metadata_kept: None,
         }
     }
 }
 
-#[derive(Debug, serde::Serialize)]
+/// Cabinet item struct for view
+#[derive(Debug, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 pub struct CabinetItemView {
     pub id: i64,
     pub cabinet_code: i64,
@@ -491,10 +1328,39 @@ pub struct CabinetItemView {
     pub name: String,
     pub size: i64,
     pub sort_order: i32,
+    /// Detected content type, e.g. `image/png`
+    #[serde(default = "default_mime_type")]
+    pub mime_type: String,
+    /// Whether `mode=inline` can serve the item
+    #[serde(default)]
+    pub previewable: bool,
+    /// Whether a thumbnail is generated for the item
+    #[serde(default)]
+    pub thumbnail: bool,
+    /// Syntax language of a text snippet, e.g. `rust`
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Target of a link, e.g. `https://example.com/`
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Host of a link, with its port when not the default, internationalized hosts in punycode
+    #[serde(default)]
+    pub host: Option<String>,
+    /// SHA-256 of the content, lowercase hex, checked on every download, none for items saved
+    /// by older versions
+    #[serde(default)]
+    pub hash: Option<String>,
+}
+
+fn default_mime_type() -> String {
+    String::from(domain::entity::cabinet::BINARY_MIME_TYPE)
 }
 
 impl From<CabinetItem> for CabinetItemView {
     fn from(value: CabinetItem) -> Self {
+        let previewable = value.is_previewable();
+        let thumbnail = thumbnail::is_supported(&value);
+        let host = value.url.as_deref().and_then(link::host);
         Self {
             id: value.id,
             cabinet_code: value.cabinet_code,
@@ -502,17 +1368,106 @@ fn from(value: CabinetItem) -> Self {
             name: value.name,
             size: value.size,
             sort_order: value.sort_order,
+            mime_type: value.mime_type,
+            previewable,
+            thumbnail,
+            language: value.language,
+            url: value.url,
+            host,
+            hash: value.hash,
         }
     }
 }
 
-#[derive(Debug, serde::Deserialize)]
+/// Credential to open a cabinet
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct CabinetCredential {
+    /// One-time public key from `GET /api/crypto/pk`
     pub public_key: String,
+    /// Cabinet password encrypted with the public key (SM2, C1C3C2, hex)
     pub password: String,
 }
 
-#[derive(Debug, serde::Deserialize)]
+/// Encrypted password is redacted
+impl std::fmt::Debug for CabinetCredential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CabinetCredential")
+            .field("public_key", &self.public_key)
+            .field("password", &domain::entity::REDACTED)
+            .finish()
+    }
+}
+
+/// File of an archive item
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct CabinetArchiveEntryView {
+    /// Relative, `/` separated path inside the archive
+    pub path: String,
+    /// Size once extracted, in bytes
+    pub size: u64,
+    /// Last modification time, when the archive records one
+    pub modified: Option<DateTime<Local>>,
+}
+
+impl From<archive::ArchiveEntry> for CabinetArchiveEntryView {
+    fn from(value: archive::ArchiveEntry) -> Self {
+        Self {
+            path: value.path,
+            size: value.size,
+            modified: value.modified,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CabinetArchiveEntryParams {
+    /// Path of the file, as listed by `entries`
+    pub path: String,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct CabinetItemContentParams {
+    /// `text` for text items and links, `highlighted` for text items as syntax highlighted HTML, `file` to
+    /// download any item, `inline` to show a previewable item with its own content type
     pub mode: String,
 }
+
+/// Multipart form of `save`
+#[derive(utoipa::ToSchema)]
+#[allow(dead_code)]
+pub(crate) struct CabinetSaveForm {
+    /// Hold token returned by `apply`
+    hold_token: String,
+    /// One-time public key from `GET /api/crypto/pk`
+    public_key: String,
+    /// Cabinet password encrypted with the public key (SM2, C1C3C2, hex)
+    password: String,
+    /// Hours to keep the cabinet (0-24), defaults to 1
+    hours: Option<i32>,
+    /// Text message, at most 2000 bytes
+    message: Option<String>,
+    /// Named text snippets, repeat the field for each, at most 64 KiB each
+    #[schema(value_type = Option<Vec<CabinetSnippet>>)]
+    snippets: Option<Vec<String>>,
+    /// Links, `http` or `https` addresses of at most 2048 bytes, repeat the field for each
+    links: Option<Vec<String>>,
+    /// Strip EXIF, XMP and IPTC from JPEG, PNG and WebP and properties from Office documents,
+    /// `true`, `1` or `on`
+    strip_metadata: Option<bool>,
+    /// Files, at most 2 MiB each and 10 MiB in total
+    #[schema(value_type = Option<Vec<String>>, format = Binary)]
+    files: Option<Vec<Vec<u8>>>,
+}
+
+/// Text snippet of `save`, sent as JSON in a `snippets` field
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct CabinetSnippet {
+    /// File name, defaults to `snippet-<n>.txt`
+    pub name: Option<String>,
+    /// Syntax language used by `mode=highlighted`, e.g. `rust`, `yaml` or `sh`
+    pub language: Option<String>,
+    pub content: String,
+}
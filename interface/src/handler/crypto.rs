@@ -1,3 +1,4 @@
+use crate::envelope::{Envelope, ErrorEnvelope};
 use crate::error::InterfaceError;
 use crate::extract::AcceptLanguage;
 use crate::handler::ServerState;
@@ -10,7 +11,18 @@ pub(crate) fn router() -> axum::Router<ServerState> {
 }
 
 /// Get public key
+#[utoipa::path(
+    get,
+    path = "/api/v1/crypto/pk",
+    tag = "crypto",
+    responses(
+        (status = 200, description = "One-time SM2 public key (uncompressed, hex)", body = Envelope<String>),
+        (status = "4XX", description = "Rejected request", body = ErrorEnvelope),
+        (status = "5XX", description = "Server error", body = ErrorEnvelope),
+    )
+)]
 #[axum::debug_handler]
+#[tracing::instrument(skip_all)]
 pub(crate) async fn public_key(
     State(state): State<ServerState>,
     AcceptLanguage(language): AcceptLanguage,
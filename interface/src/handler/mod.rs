@@ -1,8 +1,27 @@
-mod cabinet;
-mod crypto;
+pub(crate) mod cabinet;
+pub(crate) mod crypto;
+
+use crate::envelope::Envelope;
 
 /// Backend router
+///
+/// - `/v1/...` answers with `{"data": ...}` / `{"error": {...}}` envelopes
+/// - `/...` keeps the original bodies and is deprecated
 pub fn router() -> axum::Router<ServerState> {
+    use crate::envelope;
+    use axum::middleware::from_fn;
+    axum::Router::new()
+        .nest("/v1", routes().layer(from_fn(envelope::wrap)))
+        .merge(routes().layer(from_fn(envelope::deprecated)))
+        .route(
+            "/openapi.json",
+            axum::routing::get(crate::openapi::openapi_json),
+        )
+        .route_layer(from_fn(crate::metrics::track))
+}
+
+/// Api routes shared by every version
+fn routes() -> axum::Router<ServerState> {
     axum::Router::new()
         .route("/ping", axum::routing::get(ping))
         .nest("/cabinet", cabinet::router())
@@ -10,7 +29,13 @@ pub fn router() -> axum::Router<ServerState> {
 }
 
 /// ping the server
-/// GET /api/ping
+/// GET /api/v1/ping
+#[utoipa::path(
+    get,
+    path = "/api/v1/ping",
+    tag = "server",
+    responses((status = 200, description = "Server is up", body = Envelope<String>))
+)]
 pub(crate) async fn ping() -> String {
     "pong".to_string()
 }
@@ -22,8 +47,31 @@ pub struct ServerState {
     pub data_folder: std::path::PathBuf,
     pub max_cabinet_number: u64,
     pub max_keypair_number: u64,
+    /// Free bytes the data folder needs for the server to be ready
+    pub min_free_space: u64,
+    /// Scanner every saved item goes through, none skips scanning
+    pub scanner: Option<std::sync::Arc<dyn domain::service::scanner::ContentScanner>>,
+    /// Save items when the scanner fails instead of rejecting them
+    pub scan_fail_open: bool,
+    /// Fetcher naming links after their page, none keeps them named after their host
+    pub title_fetcher: Option<std::sync::Arc<dyn domain::service::link::TitleFetcher>>,
+    /// zstd level saved contents are compressed with, none stores them as is
+    pub compression_level: Option<i32>,
+    draining: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    /// Work left running after a response, e.g. thumbnails
+    background_tasks: tokio_util::task::TaskTracker,
+    /// Thumbnails generated at the same time
+    thumbnail_slots: std::sync::Arc<tokio::sync::Semaphore>,
+    /// Archives listed or extracted at the same time
+    extraction_slots: std::sync::Arc<tokio::sync::Semaphore>,
 }
 
+/// Thumbnails generated at the same time, decoding images is CPU bound
+const THUMBNAIL_SLOTS: usize = 2;
+
+/// Archives listed or extracted at the same time, each holds its item and decompresses it
+const EXTRACTION_SLOTS: usize = 4;
+
 impl ServerState {
     pub fn new(
         connection: sea_orm::DatabaseConnection,
@@ -35,6 +83,35 @@ pub fn new(
             data_folder,
             max_cabinet_number,
             max_keypair_number: max_cabinet_number * 10,
+            min_free_space: 0,
+            scanner: None,
+            scan_fail_open: false,
+            title_fetcher: None,
+            compression_level: None,
+            draining: Default::default(),
+            background_tasks: Default::default(),
+            thumbnail_slots: std::sync::Arc::new(tokio::sync::Semaphore::new(THUMBNAIL_SLOTS)),
+            extraction_slots: std::sync::Arc::new(tokio::sync::Semaphore::new(EXTRACTION_SLOTS)),
         }
     }
 }
+
+impl ServerState {
+    /// Report not ready from now on, called when graceful shutdown starts
+    pub fn start_draining(&self) {
+        use std::sync::atomic::Ordering;
+        self.draining.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether graceful shutdown has started
+    pub fn is_draining(&self) -> bool {
+        use std::sync::atomic::Ordering;
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Wait for the background tasks to finish, called once the listeners are closed
+    pub async fn wait_background_tasks(&self) {
+        self.background_tasks.close();
+        self.background_tasks.wait().await;
+    }
+}
@@ -0,0 +1,168 @@
+use crate::handler::ServerState;
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use std::collections::BTreeMap;
+
+/// Longest time a dependency check may take
+const CHECK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Health router, mounted at the root so orchestrators need no api prefix
+///
+/// - `/healthz` the process is up
+/// - `/readyz` the server can take traffic
+pub fn router() -> axum::Router<ServerState> {
+    use axum::routing::get;
+    axum::Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+}
+
+/// Liveness, answers as long as the process serves requests
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "server",
+    responses((status = 200, description = "Process is up", body = HealthView))
+)]
+pub(crate) async fn healthz() -> Json<HealthView> {
+    Json(HealthView {
+        status: HealthStatus::Up,
+        checks: BTreeMap::new(),
+    })
+}
+
+/// Readiness, checks the database, migrations, data folder and free disk space
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "server",
+    responses(
+        (status = 200, description = "Ready to take traffic", body = HealthView),
+        (status = 503, description = "Not ready, see the failing checks", body = HealthView),
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub(crate) async fn readyz(State(state): State<ServerState>) -> (StatusCode, Json<HealthView>) {
+    let mut checks = BTreeMap::new();
+    checks.insert("draining", check_draining(&state));
+    checks.insert("database", check_database(&state).await);
+    checks.insert("migrations", check_migrations(&state).await);
+    checks.insert("data_folder", check_data_folder(&state).await);
+    checks.insert("disk_space", check_disk_space(&state));
+    let ready = checks.values().all(|check| check.ok);
+    if !ready {
+        log::warn!("Not ready: {checks:?}");
+    }
+    let (code, status) = if ready {
+        (StatusCode::OK, HealthStatus::Ready)
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, HealthStatus::NotReady)
+    };
+    let checks = checks
+        .into_iter()
+        .map(|(name, check)| (name.to_string(), check))
+        .collect();
+    (code, Json(HealthView { status, checks }))
+}
+
+fn check_draining(state: &ServerState) -> CheckView {
+    if state.is_draining() {
+        CheckView::failed("shutting down")
+    } else {
+        CheckView::ok(None)
+    }
+}
+
+async fn check_database(state: &ServerState) -> CheckView {
+    match tokio::time::timeout(CHECK_TIMEOUT, state.connection.ping()).await {
+        Ok(Ok(())) => CheckView::ok(None),
+        Ok(Err(e)) => CheckView::failed(e.to_string()),
+        Err(_) => CheckView::failed("timed out"),
+    }
+}
+
+async fn check_migrations(state: &ServerState) -> CheckView {
+    use migration::{Migrator, MigratorTrait};
+    let pending = Migrator::get_pending_migrations(&state.connection);
+    match tokio::time::timeout(CHECK_TIMEOUT, pending).await {
+        Ok(Ok(pending)) if pending.is_empty() => CheckView::ok(None),
+        Ok(Ok(pending)) => CheckView::failed(format!("{} pending", pending.len())),
+        Ok(Err(e)) => CheckView::failed(e.to_string()),
+        Err(_) => CheckView::failed("timed out"),
+    }
+}
+
+async fn check_data_folder(state: &ServerState) -> CheckView {
+    // Probes may run concurrently, also from other replicas sharing the folder
+    let probe = state
+        .data_folder
+        .join(format!(".readyz-{:016x}", rand::random::<u64>()));
+    let write = {
+        let probe = probe.clone();
+        tokio::task::spawn_blocking(move || {
+            std::fs::write(&probe, b"ok").and_then(|_| std::fs::remove_file(&probe))
+        })
+    };
+    match tokio::time::timeout(CHECK_TIMEOUT, write).await {
+        Ok(Ok(Ok(()))) => CheckView::ok(None),
+        Ok(Ok(Err(e))) => {
+            CheckView::failed(format!("'{}' is not writable: {e}", probe.display()))
+        }
+        Ok(Err(e)) => CheckView::failed(e.to_string()),
+        Err(_) => CheckView::failed("timed out"),
+    }
+}
+
+fn check_disk_space(state: &ServerState) -> CheckView {
+    match fs4::available_space(&state.data_folder) {
+        Ok(free) if free >= state.min_free_space => {
+            CheckView::ok(Some(format!("{free} bytes free")))
+        }
+        Ok(free) => CheckView::failed(format!(
+            "{free} bytes free, {} required",
+            state.min_free_space
+        )),
+        Err(e) => CheckView::failed(e.to_string()),
+    }
+}
+
+/// Overall status
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Up,
+    Ready,
+    NotReady,
+}
+
+/// Health or readiness report
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct HealthView {
+    pub status: HealthStatus,
+    /// Result of each check by name, empty for liveness
+    pub checks: BTreeMap<String, CheckView>,
+}
+
+/// Result of a single check
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct CheckView {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl CheckView {
+    fn ok(detail: Option<String>) -> Self {
+        Self { ok: true, detail }
+    }
+
+    fn failed(detail: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            detail: Some(detail.into()),
+        }
+    }
+}
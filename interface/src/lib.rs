@@ -1,6 +1,14 @@
+pub mod envelope;
 pub(crate) mod extract;
 mod handler;
+pub mod health;
+pub mod metrics;
+pub mod openapi;
 
+pub use handler::cabinet::{
+    CabinetArchiveEntryParams, CabinetArchiveEntryView, CabinetCredential,
+    CabinetItemContentParams, CabinetItemView, CabinetSnippet, CabinetView,
+};
 pub use handler::{ServerState, router};
 
 pub mod error {
@@ -10,10 +18,30 @@ pub mod error {
     /// Server api error
     pub struct InterfaceError(Locale, DomainError);
 
+    /// Error body returned by the server api
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+    pub struct ErrorView {
+        /// Stable error code, see [`error_code`]
+        pub code: String,
+        /// Localized error message
+        pub message: String,
+    }
+
+    /// Stable code of a domain error, e.g. `Cabinet::InvalidFileSize`
+    ///
+    /// Values the error holds, such as a file name, are only part of the message.
+    pub fn error_code(error: &DomainError) -> &'static str {
+        error.code()
+    }
+
     impl InterfaceError {
         pub fn new(locale: Locale, domain_error: DomainError) -> Self {
             Self(locale, domain_error)
         }
+
+        pub(crate) fn domain_error(&self) -> &DomainError {
+            &self.1
+        }
     }
 
     impl From<(Locale, DomainError)> for InterfaceError {
@@ -26,20 +54,25 @@ impl axum::response::IntoResponse for InterfaceError {
         fn into_response(self) -> axum::response::Response {
             use axum::Json;
             use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+            let code = error_code(&self.1);
+            metrics::counter!("cabinet_errors_total", "code" => code).increment(1);
             let mut headers = HeaderMap::new();
             headers.append(
                 header::CONTENT_TYPE,
                 HeaderValue::from_static("application/json"),
             );
-            (
+            let mut response = (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 headers,
-                Json(serde_json::json!({
-                    "code": self.1.to_string(),
-                    "message": self.1.i18n_message(self.0),
-                })),
+                Json(ErrorView {
+                    code: code.to_string(),
+                    message: self.1.i18n_message(self.0).to_string(),
+                }),
             )
-                .into_response()
+                .into_response();
+            // Let the v1 envelope pick a precise status
+            response.extensions_mut().insert(self.1);
+            response
         }
     }
 }
@@ -0,0 +1,122 @@
+use crate::error::InterfaceError;
+use crate::handler::ServerState;
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use infrastructure::service::cabinet::create_cabinet_service;
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+use std::sync::OnceLock;
+
+/// Handle of the installed Prometheus recorder
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Latency buckets in seconds, from a cache hit to a slow upload
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Install the Prometheus recorder, metrics are dropped until this is called
+pub fn install() -> Result<(), BuildError> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full(String::from("http_request_duration_seconds")),
+            LATENCY_BUCKETS,
+        )?
+        .set_buckets_for_metric(
+            Matcher::Full(String::from("cabinet_ticker_duration_seconds")),
+            LATENCY_BUCKETS,
+        )?
+        .install_recorder()?;
+    let _ = HANDLE.set(handle);
+    Ok(())
+}
+
+/// Metrics router
+///
+/// - `/metrics` Prometheus text exposition
+pub fn router() -> axum::Router<ServerState> {
+    axum::Router::new().route("/metrics", axum::routing::get(render))
+}
+
+/// Render all metrics, cabinet gauges are refreshed on every scrape
+async fn render(State(state): State<ServerState>) -> Response {
+    let Some(handle) = HANDLE.get() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let service = create_cabinet_service(
+        state.connection,
+        &state.data_folder,
+        state.max_cabinet_number,
+    );
+    match service.usage().await {
+        Ok(usage) => {
+            metrics::gauge!("cabinet_capacity").set(usage.total as f64);
+            metrics::gauge!("cabinet_occupied").set(usage.used as f64);
+            metrics::gauge!("cabinet_held").set(usage.held as f64);
+        }
+        Err(e) => log::error!("Failed to collect cabinet usage: {e}"),
+    }
+    match service.stored_bytes().await {
+        Ok(bytes) => metrics::gauge!("cabinet_stored_bytes").set(bytes as f64),
+        Err(e) => log::error!("Failed to collect stored bytes: {e}"),
+    }
+    (
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; version=0.0.4"),
+        )],
+        handle.render(),
+    )
+        .into_response()
+}
+
+/// Record request latency per route
+pub(crate) async fn track(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| String::from("unknown"));
+    let start = std::time::Instant::now();
+    let response = next.run(request).await;
+    metrics::histogram!(
+        "http_request_duration_seconds",
+        "method" => method,
+        "route" => route,
+        "status" => response.status().as_u16().to_string(),
+    )
+    .record(start.elapsed().as_secs_f64());
+    response
+}
+
+/// Count the bytes of a download as they are sent, streamed bodies have no size up front
+pub(crate) fn count_download(response: Response) -> Response {
+    use axum::body::HttpBody;
+    use futures_util::StreamExt;
+    let (mut parts, body) = response.into_parts();
+    // Keep the length of a buffered body, the stream below no longer knows it
+    if let Some(size) = body.size_hint().exact() {
+        parts.headers.entry(header::CONTENT_LENGTH).or_insert(size.into());
+    }
+    let body = body.into_data_stream().inspect(|chunk| {
+        if let Ok(chunk) = chunk {
+            metrics::counter!("cabinet_download_bytes_total").increment(chunk.len() as u64);
+        }
+    });
+    Response::from_parts(parts, axum::body::Body::from_stream(body))
+}
+
+/// Outcome label of a handler result
+///
+/// - `success`
+/// - `rejected` the request was refused, e.g. a wrong password
+/// - `error` the server failed
+pub(crate) fn outcome<T>(result: &Result<T, InterfaceError>) -> &'static str {
+    match result {
+        Ok(_) => "success",
+        Err(e) if crate::envelope::status_of(e.domain_error()).is_server_error() => "error",
+        Err(_) => "rejected",
+    }
+}
@@ -0,0 +1,92 @@
+use crate::envelope::ErrorEnvelope;
+use crate::error::ErrorView;
+use crate::handler::cabinet::{self, CabinetSaveForm};
+use crate::handler::{self, crypto};
+use crate::health::{self, CheckView, HealthStatus, HealthView};
+use crate::{
+    CabinetArchiveEntryView, CabinetCredential, CabinetItemView, CabinetSnippet, CabinetView,
+};
+use domain::entity::cabinet::{CabinetItemCategory, CabinetStatus, CabinetUsage};
+
+/// OpenAPI document of the v1 backend api
+#[derive(utoipa::OpenApi)]
+#[openapi(
+    info(
+        title = "Temporary Cabinet",
+        description = "A simple web service for temporarily storing text and files",
+        license(name = "MIT")
+    ),
+    paths(
+        handler::ping,
+        cabinet::apply,
+        cabinet::usage,
+        cabinet::get_by_code,
+        cabinet::save,
+        cabinet::delete_cabinet,
+        cabinet::items,
+        cabinet::get_item_content,
+        cabinet::get_item_thumbnail,
+        cabinet::get_item_entries,
+        cabinet::get_item_entry,
+        crypto::public_key,
+        health::healthz,
+        health::readyz,
+    ),
+    components(schemas(
+        CabinetView,
+        CabinetItemView,
+        CabinetArchiveEntryView,
+        CabinetCredential,
+        CabinetSaveForm,
+        CabinetSnippet,
+        CabinetStatus,
+        CabinetItemCategory,
+        CabinetUsage,
+        ErrorView,
+        ErrorEnvelope,
+        HealthView,
+        HealthStatus,
+        CheckView,
+    )),
+    tags(
+        (name = "cabinet", description = "Apply, lock and pick up cabinets"),
+        (name = "crypto", description = "One-time keys to encrypt cabinet passwords"),
+        (name = "server", description = "Server status"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Raw item content
+#[derive(utoipa::ToSchema)]
+#[schema(value_type = String, format = Binary)]
+pub(crate) struct BinaryContent(#[allow(dead_code)] Vec<u8>);
+
+/// Get the OpenAPI document
+/// GET /api/openapi.json
+pub(crate) async fn openapi_json() -> axum::Json<utoipa::openapi::OpenApi> {
+    use utoipa::OpenApi;
+    axum::Json(ApiDoc::openapi())
+}
+
+/// Interactive api reference (Scalar), to be mounted next to the backend router
+///
+/// The page loads its script from a CDN, so it gets a Content-Security-Policy of its own.
+pub fn docs_router<S>(path: &str) -> axum::Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    use utoipa::OpenApi;
+    use utoipa_scalar::{Scalar, Servable};
+    axum::Router::new()
+        .merge(Scalar::with_url(path.to_string(), ApiDoc::openapi()))
+        .layer(axum::middleware::map_response(docs_policy))
+}
+
+async fn docs_policy(mut response: axum::response::Response) -> axum::response::Response {
+    use axum::http::{HeaderValue, header};
+    response.headers_mut().insert(
+        header::CONTENT_SECURITY_POLICY,
+        HeaderValue::from_static("default-src 'self' https: data: 'unsafe-inline'"),
+    );
+    response
+}
@@ -3,6 +3,12 @@
 mod m20251022_001_create_table_cabinet;
 mod m20251022_002_create_table_cabinet_item;
 mod m20251024_001_create_table_keypair;
+mod m20261019_001_add_cabinet_item_mime_type;
+mod m20261019_002_add_cabinet_item_language;
+mod m20261019_003_add_cabinet_item_url;
+mod m20261019_004_add_cabinet_item_encoding;
+mod m20261019_005_create_table_cabinet_object;
+mod m20261019_006_add_cabinet_item_hash;
 
 pub struct Migrator;
 
@@ -13,6 +19,12 @@ fn migrations() -> Vec<Box<dyn MigrationTrait>> {
             Box::new(m20251022_001_create_table_cabinet::Migration),
             Box::new(m20251022_002_create_table_cabinet_item::Migration),
             Box::new(m20251024_001_create_table_keypair::Migration),
+            Box::new(m20261019_001_add_cabinet_item_mime_type::Migration),
+            Box::new(m20261019_002_add_cabinet_item_language::Migration),
+            Box::new(m20261019_003_add_cabinet_item_url::Migration),
+            Box::new(m20261019_004_add_cabinet_item_encoding::Migration),
+            Box::new(m20261019_005_create_table_cabinet_object::Migration),
+            Box::new(m20261019_006_add_cabinet_item_hash::Migration),
         ]
     }
 }
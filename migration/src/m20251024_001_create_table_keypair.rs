@@ -13,7 +13,9 @@ async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
                     .if_not_exists()
                     .col(pk_uuid(Keypair::Id))
                     .col(string_len(Keypair::SecretKey, 1000))
-                    .col(string_len(Keypair::PublicKey, 1000))
+                    // Uncompressed SM2 keys are 130 hex chars, a longer column would push
+                    // the index below past MySQL's key length limit
+                    .col(string_len(Keypair::PublicKey, 200))
                     .col(date_time(Keypair::ExpireAt))
                     .col(date_time(Keypair::CreateAt))
                     .col(date_time(Keypair::UpdateAt))
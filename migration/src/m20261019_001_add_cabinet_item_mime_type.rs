@@ -0,0 +1,48 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CabinetItem::Table)
+                    .add_column(
+                        string_len(CabinetItem::MimeType, 100).default("application/octet-stream"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        // Messages saved before are text, files keep the generic type
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(CabinetItem::Table)
+                    .value(CabinetItem::MimeType, "text/plain; charset=utf-8")
+                    .and_where(Expr::col(CabinetItem::Category).eq("text"))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CabinetItem::Table)
+                    .drop_column(CabinetItem::MimeType)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum CabinetItem {
+    Table,
+    Category,
+    MimeType,
+}
@@ -0,0 +1,44 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CabinetObject::Table)
+                    .if_not_exists()
+                    // SHA-256 of the content, hex
+                    .col(string_len(CabinetObject::Hash, 64).primary_key())
+                    .col(string_len_null(CabinetObject::Encoding, 16))
+                    .col(big_integer(CabinetObject::Size))
+                    .col(integer(CabinetObject::RefCount))
+                    .col(date_time(CabinetObject::CreateAt))
+                    .col(date_time(CabinetObject::UpdateAt))
+                    .col(integer(CabinetObject::Version))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CabinetObject::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum CabinetObject {
+    Table,
+    Hash,
+    Encoding,
+    Size,
+    RefCount,
+    CreateAt,
+    UpdateAt,
+    Version,
+}
@@ -0,0 +1,63 @@
+use crate::arg::Command;
+
+/// Run a maintenance command against the configured database
+pub(crate) async fn run(
+    command: Command,
+    connection: sea_orm::DatabaseConnection,
+    data_folder: &std::path::Path,
+) {
+    match command {
+        Command::MigrateData { to } => migrate_data(connection, data_folder, &to).await,
+        Command::Verify => verify(connection, data_folder).await,
+    }
+}
+
+/// Copy cabinets and items into the database at `to`
+async fn migrate_data(
+    source: sea_orm::DatabaseConnection,
+    data_folder: &std::path::Path,
+    to: &str,
+) {
+    let target = crate::init::initialize_database(data_folder, Some(to)).await;
+    match infrastructure::database::copy_data(&source, &target, data_folder).await {
+        Ok(report) => log::info!(
+            "Copied {} cabinets, {} items and {} objects",
+            report.cabinets,
+            report.items,
+            report.objects
+        ),
+        Err(e) => {
+            eprintln!("Failed to copy data: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Check every stored item against its checksum
+async fn verify(connection: sea_orm::DatabaseConnection, data_folder: &std::path::Path) {
+    // The number of cabinets is not needed to read items
+    let cabinet_service =
+        infrastructure::service::cabinet::create_cabinet_service(connection, data_folder, 0);
+    let report = match cabinet_service.verify_items().await {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Failed to verify items: {e}");
+            std::process::exit(1);
+        }
+    };
+    for (item, e) in &report.failed {
+        eprintln!(
+            "Item {} '{}' of cabinet {} failed: {e}",
+            item.id, item.name, item.cabinet_code
+        );
+    }
+    log::info!(
+        "Verified {} items, {} failed, {} without checksum",
+        report.verified,
+        report.failed.len(),
+        report.unhashed
+    );
+    if !report.failed.is_empty() {
+        std::process::exit(1);
+    }
+}
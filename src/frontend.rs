@@ -0,0 +1,50 @@
+//! Embedded front-end
+
+use axum::body::Body;
+use axum::http::header;
+use axum::response::Response;
+
+/// `<base>` of the built `index.html`
+const INDEX_BASE: &str = r#"<base href="/""#;
+
+/// Serve the embedded front-end, `index.html` for every unknown path
+///
+/// The `<base>` of `index.html` is pointed at `base_path` on the fly, the front-end resolves its
+/// assets, routes and api calls against it, so one build runs under any prefix.
+pub(crate) fn router(base_path: &str) -> axum::Router {
+    let static_service = axum_embed::ServeEmbed::<web::WebAssets>::with_parameters(
+        Some("/".to_string()),
+        axum_embed::FallbackBehavior::Ok,
+        Some("index.html".to_string()),
+    );
+    let router = axum::Router::new().fallback_service(static_service);
+    if base_path.is_empty() {
+        return router;
+    }
+    let base = format!(r#"<base href="{base_path}/""#);
+    router.layer(axum::middleware::map_response(move |response| {
+        let base = base.clone();
+        async move { rewrite_base(response, &base).await }
+    }))
+}
+
+async fn rewrite_base(response: Response, base: &str) -> Response {
+    let is_html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+    if !is_html {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let html = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).replacen(INDEX_BASE, base, 1),
+        Err(e) => {
+            log::error!("Failed to read index.html: {e}");
+            String::new()
+        }
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(html))
+}
@@ -0,0 +1,102 @@
+//! Listeners the server accepts connections on, startup stops when one cannot be opened
+
+/// Bind a TCP listener on `port` of every host
+pub(crate) async fn bind_tcp(hosts: &[String], port: u16) -> Vec<tokio::net::TcpListener> {
+    let mut listeners = Vec::with_capacity(hosts.len());
+    for host in hosts {
+        // A tuple keeps IPv6 addresses such as `::` free of brackets
+        match tokio::net::TcpListener::bind((host.as_str(), port)).await {
+            Ok(listener) => listeners.push(listener),
+            Err(e) => {
+                log::error!("Failed to bind to {host} port {port}: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+    listeners
+}
+
+/// Bind the Unix socket at `path` with the given permissions and owner
+///
+/// A socket file nobody listens on is left by a crash and removed first.
+#[cfg(unix)]
+pub(crate) fn bind_unix(
+    path: &std::path::Path,
+    mode: u32,
+    owner: Option<&str>,
+) -> tokio::net::UnixListener {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            log::error!("'{}' exists and is not a socket", path.display());
+            std::process::exit(1);
+        }
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            log::error!("'{}' is in use by another process", path.display());
+            std::process::exit(1);
+        }
+        log::info!("Removing stale socket '{}'", path.display());
+        remove_unix_socket(path);
+    }
+    let listener = match tokio::net::UnixListener::bind(path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::error!("Failed to bind to '{}': {e}", path.display());
+            std::process::exit(1);
+        }
+    };
+    let permissions = std::fs::Permissions::from_mode(mode);
+    if let Err(e) = std::fs::set_permissions(path, permissions) {
+        log::error!("Failed to set mode {mode:o} on '{}': {e}", path.display());
+        remove_unix_socket(path);
+        std::process::exit(1);
+    }
+    if let Some(owner) = owner {
+        let result = resolve_owner(owner).and_then(|(uid, gid)| {
+            std::os::unix::fs::chown(path, uid, gid).map_err(|e| e.to_string())
+        });
+        if let Err(e) = result {
+            log::error!(
+                "Failed to change owner of '{}' to '{owner}': {e}",
+                path.display()
+            );
+            remove_unix_socket(path);
+            std::process::exit(1);
+        }
+    }
+    listener
+}
+
+/// Remove the socket file, a missing file is fine
+#[cfg(unix)]
+pub(crate) fn remove_unix_socket(path: &std::path::Path) {
+    match std::fs::remove_file(path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => log::warn!("Failed to remove socket '{}': {e}", path.display()),
+    }
+}
+
+/// Resolve `user` or `user:group` to ids, the group stays unchanged when omitted
+#[cfg(unix)]
+fn resolve_owner(owner: &str) -> Result<(Option<u32>, Option<u32>), String> {
+    use nix::unistd::{Group, User};
+
+    let (user, group) = match owner.split_once(':') {
+        Some((user, group)) => (user, Some(group)),
+        None => (owner, None),
+    };
+    let uid = match User::from_name(user).map_err(|e| e.to_string())? {
+        Some(user) => user.uid.as_raw(),
+        None => return Err(format!("no user named '{user}'")),
+    };
+    let gid = match group {
+        Some(group) => match Group::from_name(group).map_err(|e| e.to_string())? {
+            Some(group) => Some(group.gid.as_raw()),
+            None => return Err(format!("no group named '{group}'")),
+        },
+        None => None,
+    };
+    Ok((Some(uid), gid))
+}
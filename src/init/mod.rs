@@ -1,22 +1,37 @@
+pub(crate) mod listener;
+pub(crate) mod systemd;
 mod ticker;
+pub(crate) mod tls;
 
 /// Initialize logger
-pub(crate) fn initialize_logger(debug: bool) {
-    use simple_logger::SimpleLogger;
-    let result = SimpleLogger::new()
-        .with_level(if debug {
-            log::LevelFilter::Debug
-        } else {
-            log::LevelFilter::Info
-        })
-        .with_colors(true)
-        .with_local_timestamps()
-        .init();
+///
+/// `log` records from dependencies are forwarded to `tracing`.
+pub(crate) fn initialize_logger(debug: bool, format: crate::arg::LogFormat) {
+    use tracing_subscriber::EnvFilter;
+    use tracing_subscriber::fmt::time::ChronoLocal;
+
+    let level = if debug { "debug" } else { "info" };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_ansi(std::io::IsTerminal::is_terminal(&std::io::stdout()))
+        .with_timer(ChronoLocal::rfc_3339());
+    let result = match format {
+        crate::arg::LogFormat::Text => builder.try_init(),
+        crate::arg::LogFormat::Json => builder.json().with_span_list(true).try_init(),
+    };
     if let Err(e) = result {
         eprintln!("Failed to initialize logger: {e}");
     }
 }
 
+/// Initialize metrics recorder
+pub(crate) fn initialize_metrics() {
+    if let Err(e) = interface::metrics::install() {
+        log::error!("Failed to install metrics recorder: {e}");
+    }
+}
+
 /// Initialize data folder
 pub(crate) fn initialize_data_folder(data_folder: Option<String>) -> std::path::PathBuf {
     use std::path::PathBuf;
@@ -51,14 +66,23 @@ pub(crate) fn initialize_data_folder(data_folder: Option<String>) -> std::path::
 }
 
 /// Connect to database and migrate
+///
+/// Uses `db.sqlite` in the data folder when no url is given.
 pub(crate) async fn initialize_database(
     data_folder: &std::path::Path,
+    database_url: Option<&str>,
 ) -> sea_orm::DatabaseConnection {
     use migration::{Migrator, MigratorTrait};
     use sea_orm::{ConnectOptions, Database};
 
-    let database_file = data_folder.join("db.sqlite");
-    let database_url = format!("sqlite://{}?mode=rwc", database_file.display());
+    let database_url = match database_url {
+        Some(url) => url.to_string(),
+        None => {
+            let database_file = data_folder.join("db.sqlite");
+            format!("sqlite://{}?mode=rwc", database_file.display())
+        }
+    };
+    let backend = database_backend(&database_url);
     let mut connect_opts = ConnectOptions::new(database_url);
     connect_opts
         .max_connections(10)
@@ -66,7 +90,8 @@ pub(crate) async fn initialize_database(
         .connect_timeout(std::time::Duration::from_secs(10))
         .idle_timeout(std::time::Duration::from_secs(10))
         .sqlx_logging_level(log::LevelFilter::Debug);
-    log::debug!("Connecting to database '{}'...", database_file.display());
+    // The url may carry a password, only name the backend
+    log::debug!("Connecting to {backend} database...");
     let connection = Database::connect(connect_opts).await;
     if let Err(e) = connection {
         eprintln!("Failed to connect to database: {e}");
@@ -83,11 +108,49 @@ pub(crate) async fn initialize_database(
     connection
 }
 
-/// Initialize tickers
+/// Name the backend of a database url, exit if this build has no driver for it
+fn database_backend(database_url: &str) -> &'static str {
+    let scheme = database_url.split(':').next().unwrap_or_default();
+    let (backend, feature, supported) = match scheme {
+        "sqlite" => ("SQLite", "", true),
+        "postgres" | "postgresql" => ("PostgreSQL", "postgres", cfg!(feature = "postgres")),
+        "mysql" => ("MySQL", "mysql", cfg!(feature = "mysql")),
+        _ => {
+            eprintln!("Unsupported database url scheme '{scheme}'");
+            std::process::exit(1);
+        }
+    };
+    if !supported {
+        eprintln!("{backend} support is not built in, rebuild with `--features {feature}`");
+        std::process::exit(1);
+    }
+    backend
+}
+
+/// Initialize tickers, they stop between runs once `cancel_token` is cancelled
 pub(crate) fn initialize_tickers(
     state: &interface::ServerState,
     cancel_token: &tokio_util::sync::CancellationToken,
-) {
-    ticker::initialize_public_key_clean_ticker(state, cancel_token);
-    ticker::initialize_cabinet_clean_ticker(state, cancel_token);
+) -> Vec<tokio::task::JoinHandle<()>> {
+    let mut tickers = vec![
+        ticker::initialize_public_key_clean_ticker(state, cancel_token),
+        ticker::initialize_cabinet_clean_ticker(state, cancel_token),
+    ];
+    tickers.extend(systemd::initialize_watchdog_ticker(state, cancel_token));
+    tickers
+}
+
+/// Remove content left behind by interrupted writes
+pub(crate) async fn clean_temporary_files(state: &interface::ServerState) {
+    use infrastructure::service::cabinet::create_cabinet_service;
+    let cabinet_service = create_cabinet_service(
+        state.connection.clone(),
+        &state.data_folder,
+        state.max_cabinet_number,
+    );
+    match cabinet_service.delete_temporary_files().await {
+        Ok(0) => {}
+        Ok(count) => log::info!("Deleted {count} temporary files"),
+        Err(e) => log::error!("Failed to delete temporary files: {e}"),
+    }
 }
@@ -0,0 +1,87 @@
+//! systemd integration, every function is a no-op when not started by systemd
+
+/// Tell systemd the server is ready (`Type=notify`)
+pub(crate) fn notify_ready() {
+    notify(&[sd_notify::NotifyState::Ready]);
+}
+
+/// Tell systemd the server is shutting down
+pub(crate) fn notify_stopping() {
+    notify(&[sd_notify::NotifyState::Stopping]);
+}
+
+fn notify(state: &[sd_notify::NotifyState]) {
+    if let Err(e) = sd_notify::notify(false, state) {
+        log::warn!("Failed to notify systemd: {e}");
+    }
+}
+
+/// Take the TCP listeners passed by socket activation (`LISTEN_FDS`), by index
+///
+/// Reads the environment once, listenfd clears it after the first read. Sockets that can not
+/// be used are `None`.
+pub(crate) fn activated_listeners() -> Vec<Option<tokio::net::TcpListener>> {
+    let mut listen_fd = listenfd::ListenFd::from_env();
+    (0..listen_fd.len())
+        .map(|index| activated_listener(&mut listen_fd, index))
+        .collect()
+}
+
+fn activated_listener(
+    listen_fd: &mut listenfd::ListenFd,
+    index: usize,
+) -> Option<tokio::net::TcpListener> {
+    let listener = match listen_fd.take_tcp_listener(index) {
+        Ok(listener) => listener?,
+        Err(e) => {
+            log::error!("Failed to take activated socket {index}: {e}");
+            return None;
+        }
+    };
+    let listener = listener
+        .set_nonblocking(true)
+        .and_then(|_| tokio::net::TcpListener::from_std(listener));
+    match listener {
+        Ok(listener) => Some(listener),
+        Err(e) => {
+            log::error!("Failed to use activated socket {index}: {e}");
+            None
+        }
+    }
+}
+
+/// Initialize watchdog ticker when the unit sets `WatchdogSec`
+///
+/// Pings are skipped while the database is unreachable, so systemd restarts a stuck server.
+pub(crate) fn initialize_watchdog_ticker(
+    state: &interface::ServerState,
+    cancel_token: &tokio_util::sync::CancellationToken,
+) -> Option<tokio::task::JoinHandle<()>> {
+    use tokio::time::Duration;
+
+    let mut usec = 0;
+    if !sd_notify::watchdog_enabled(false, &mut usec) {
+        return None;
+    }
+    log::info!("Starting watchdog ticker...");
+    let mut interval = tokio::time::interval(Duration::from_micros(usec / 2));
+    let connection = state.connection.clone();
+    let cancel_token = cancel_token.clone();
+    Some(tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    match tokio::time::timeout(Duration::from_secs(2), connection.ping()).await {
+                        Ok(Ok(())) => notify(&[sd_notify::NotifyState::Watchdog]),
+                        Ok(Err(e)) => log::warn!("Skipping watchdog ping, database unreachable: {e}"),
+                        Err(_) => log::warn!("Skipping watchdog ping, database ping timed out"),
+                    }
+                }
+                _ = cancel_token.cancelled() => {
+                    log::info!("Stopping watchdog ticker...");
+                    break;
+                }
+            }
+        }
+    }))
+}
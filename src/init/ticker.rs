@@ -1,9 +1,8 @@
-
 /// Initialize public key clean ticker
 pub(crate) fn initialize_public_key_clean_ticker(
     state: &interface::ServerState,
     cancel_token: &tokio_util::sync::CancellationToken,
-) {
+) -> tokio::task::JoinHandle<()> {
     use infrastructure::service::crypto::create_sm2_crypto_service;
     use tokio::time::Duration;
 
@@ -17,7 +16,10 @@ pub(crate) fn initialize_public_key_clean_ticker(
         loop {
             tokio::select! {
                 _ = interval.tick() => {
-                    match crypto_service.delete_expired().await {
+                    let start = std::time::Instant::now();
+                    let result = crypto_service.delete_expired().await;
+                    record_run("public_key", start, &result);
+                    match result {
                         Ok(count) => {
                             if log::log_enabled!(log::Level::Debug) {
                                 log::debug!("Deleted {} expired public keys", count);
@@ -37,15 +39,14 @@ pub(crate) fn initialize_public_key_clean_ticker(
                 }
             }
         }
-    });
+    })
 }
 
-
 /// Initialize cabinets clean ticker
 pub(crate) fn initialize_cabinet_clean_ticker(
     state: &interface::ServerState,
     cancel_token: &tokio_util::sync::CancellationToken,
-) {
+) -> tokio::task::JoinHandle<()> {
     use infrastructure::service::cabinet::create_cabinet_service;
     use tokio::time::Duration;
 
@@ -61,7 +62,10 @@ pub(crate) fn initialize_cabinet_clean_ticker(
         loop {
             tokio::select! {
                 _ = interval.tick() => {
-                     match cabinet_service.delete_expired().await {
+                    let start = std::time::Instant::now();
+                    let result = cabinet_service.delete_expired().await;
+                    record_run("cabinet", start, &result);
+                    match result {
                         Ok(count) => {
                             if log::log_enabled!(log::Level::Debug) {
                                 log::debug!("Deleted {} expired cabinets", count);
@@ -81,5 +85,19 @@ pub(crate) fn initialize_cabinet_clean_ticker(
 
             }
         }
-    });
-}
\ No newline at end of file
+    })
+}
+
+/// Record the duration and deletions of a ticker run
+fn record_run<E>(ticker: &'static str, start: std::time::Instant, result: &Result<u64, E>) {
+    metrics::histogram!("cabinet_ticker_duration_seconds", "ticker" => ticker)
+        .record(start.elapsed().as_secs_f64());
+    match result {
+        Ok(count) => {
+            metrics::counter!("cabinet_ticker_deleted_total", "ticker" => ticker).increment(*count)
+        }
+        Err(_) => {
+            metrics::counter!("cabinet_ticker_failures_total", "ticker" => ticker).increment(1)
+        }
+    }
+}
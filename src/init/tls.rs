@@ -0,0 +1,78 @@
+use axum_server::tls_rustls::RustlsConfig;
+use std::path::{Path, PathBuf};
+
+/// How often certificate files are checked for changes
+const RELOAD_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Load the PEM certificate chain and private key
+pub(crate) async fn load_config(cert: &Path, key: &Path) -> RustlsConfig {
+    // Several dependencies enable rustls, pick the provider explicitly
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    match RustlsConfig::from_pem_file(cert, key).await {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!(
+                "Failed to load TLS certificate '{}' and key '{}': {e}",
+                cert.display(),
+                key.display()
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Initialize certificate reload ticker
+///
+/// The files are polled rather than watched, renewals replace them through renames that
+/// inotify-style watchers easily miss.
+pub(crate) fn initialize_reload_ticker(
+    config: RustlsConfig,
+    cert: PathBuf,
+    key: PathBuf,
+    cancel_token: &tokio_util::sync::CancellationToken,
+) -> tokio::task::JoinHandle<()> {
+    log::info!("Starting certificate reload ticker...");
+    let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+    let mut loaded = modified(&cert, &key);
+    let cancel_token = cancel_token.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let current = modified(&cert, &key);
+                    if current.is_none() || current == loaded {
+                        continue;
+                    }
+                    if reload(&config, &cert, &key).await {
+                        loaded = current;
+                    }
+                }
+                _ = cancel_token.cancelled() => {
+                    log::info!("Stopping certificate reload ticker...");
+                    break;
+                }
+            }
+        }
+    })
+}
+
+/// Reload the certificate and key, the previous ones stay in use when this fails
+pub(crate) async fn reload(config: &RustlsConfig, cert: &Path, key: &Path) -> bool {
+    match config.reload_from_pem_file(cert, key).await {
+        Ok(()) => {
+            log::info!("Reloaded TLS certificate '{}'", cert.display());
+            true
+        }
+        Err(e) => {
+            log::error!("Failed to reload TLS certificate: {e}");
+            false
+        }
+    }
+}
+
+/// Modification times of the certificate and key, `None` while either is missing
+fn modified(cert: &Path, key: &Path) -> Option<(std::time::SystemTime, std::time::SystemTime)> {
+    let cert = std::fs::metadata(cert).and_then(|m| m.modified()).ok()?;
+    let key = std::fs::metadata(key).and_then(|m| m.modified()).ok()?;
+    Some((cert, key))
+}
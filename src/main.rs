@@ -1,58 +1,443 @@
 mod arg;
+mod command;
+mod frontend;
 mod init;
+mod security;
 
 /// Application entrypoint
-/// - Initialization Log (simple_logger)
+/// - Initialization Log (tracing)
+/// - Install the metrics recorder
 /// - Build routes
 /// - Start the server and listen for the ports in the configuration
 #[tokio::main]
 async fn main() {
     use tokio_util::sync::CancellationToken;
     let args = arg::parse();
-    init::initialize_logger(args.debug);
-    let data_folder = init::initialize_data_folder(args.data_dir);
-    let connection = init::initialize_database(&data_folder).await;
-    let state = interface::ServerState::new(connection, data_folder, args.cabinet_number);
-    let serv_addr = format!("{}:{}", args.host, args.port);
-    let listener = tokio::net::TcpListener::bind(&serv_addr).await;
-    if let Err(e) = listener {
-        log::error!("Failed to bind to {}: {}", &serv_addr, e);
+    init::initialize_logger(args.debug, args.log_format);
+    init::initialize_metrics();
+    let data_folder = init::initialize_data_folder(args.data_dir.clone());
+    let connection = init::initialize_database(&data_folder, args.database_url.as_deref()).await;
+    if let Some(command) = args.command {
+        command::run(command, connection, &data_folder).await;
         return;
     }
+    let mut state = interface::ServerState::new(connection, data_folder, args.cabinet_number);
+    state.min_free_space = args.min_free_space * 1024 * 1024;
+    state.compression_level = args.compression_level;
+    if let Some(address) = &args.clamd {
+        log::info!("Scanning saved items with clamd at {address}");
+        let timeout = std::time::Duration::from_secs(args.clamd_timeout);
+        let scanner = infrastructure::clamd::ClamdScanner::new(address.clone(), timeout);
+        state.scanner = Some(std::sync::Arc::new(scanner));
+        state.scan_fail_open = args.scan_fail_open;
+    }
+    if args.fetch_link_titles {
+        log::info!("Fetching the titles of saved links");
+        let timeout = std::time::Duration::from_secs(args.link_title_timeout);
+        let fetcher = infrastructure::link_title::HttpTitleFetcher::new(timeout);
+        state.title_fetcher = Some(std::sync::Arc::new(fetcher));
+    }
+    let tls = match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => Some(init::tls::load_config(cert, key).await),
+        _ => None,
+    };
+    // Sockets passed by systemd come first: 0 serves the app, 1 the metrics
+    let mut activated = init::systemd::activated_listeners().into_iter();
+    let (activated_app, activated_metrics) =
+        (activated.next().flatten(), activated.next().flatten());
+    let listeners = match activated_app {
+        Some(listener) => vec![listener],
+        None => init::listener::bind_tcp(&args.host, args.port).await,
+    };
+    #[cfg(unix)]
+    let unix_listener = args.unix_socket.as_deref().map(|path| {
+        let owner = args.unix_socket_owner.as_deref();
+        init::listener::bind_unix(path, args.unix_socket_mode, owner)
+    });
+    // Side listeners stay off the network when the server only listens on a Unix socket
+    let side_hosts = if args.host.is_empty() {
+        vec![String::from("127.0.0.1")]
+    } else {
+        args.host.clone()
+    };
+    let metrics_listeners = match (activated_metrics, args.metrics_port) {
+        (Some(listener), _) => vec![listener],
+        (None, Some(port)) => init::listener::bind_tcp(&side_hosts, port).await,
+        (None, None) => Vec::new(),
+    };
+    let redirect_listeners = match args.tls_redirect_port {
+        Some(port) => init::listener::bind_tcp(&side_hosts, port).await,
+        None => Vec::new(),
+    };
+    // Leftovers of a crash, shutdown cleans up after itself
+    init::clean_temporary_files(&state).await;
     let cancel_token = CancellationToken::new();
-    init::initialize_tickers(&state, &cancel_token);
-    log::info!("Serving on {}", &serv_addr);
-    axum::serve(listener.unwrap(), router(state))
-        .with_graceful_shutdown(shutdown_signal(cancel_token))
+    let mut tickers = init::initialize_tickers(&state, &cancel_token);
+    if let (Some(config), Some(cert), Some(key)) = (&tls, &args.tls_cert, &args.tls_key) {
+        tickers.push(init::tls::initialize_reload_ticker(
+            config.clone(),
+            cert.clone(),
+            key.clone(),
+            &cancel_token,
+        ));
+    }
+    #[cfg(unix)]
+    tickers.push(hangup_handler(
+        tls.clone()
+            .zip(args.tls_cert.clone())
+            .zip(args.tls_key.clone()),
+        &cancel_token,
+    ));
+    let separate_metrics = !metrics_listeners.is_empty();
+    for listener in metrics_listeners {
+        log::info!("Serving metrics on {}", local_addr(&listener));
+        let router = interface::metrics::router().with_state(state.clone());
+        spawn_plain("Metrics", listener, router, cancel_token.clone());
+    }
+    // The bound port, socket activation and binding port 0 leave `--port` unused
+    let https_port = args
+        .tls_public_port
+        .or_else(|| {
+            listeners
+                .first()
+                .and_then(|l| l.local_addr().ok())
+                .map(|a| a.port())
+        })
+        .unwrap_or(args.port);
+    // Before anything is served, a signal arriving right after startup still drains
+    let signals = ShutdownSignals::install();
+    for listener in redirect_listeners {
+        log::info!(
+            "Redirecting to HTTPS on port {https_port} from {}",
+            local_addr(&listener)
+        );
+        spawn_plain(
+            "Redirect",
+            listener,
+            redirect_router(https_port),
+            cancel_token.clone(),
+        );
+    }
+    let router = router(state.clone(), &args, separate_metrics);
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    let mut servers = tokio::task::JoinSet::new();
+    for listener in listeners {
+        log::info!("Serving {scheme} on {}", local_addr(&listener));
+        let cancel_token = cancel_token.clone();
+        servers.spawn(serve(listener, router.clone(), tls.clone(), cancel_token));
+    }
+    #[cfg(unix)]
+    if let (Some(listener), Some(path)) = (unix_listener, &args.unix_socket) {
+        log::info!("Serving http on unix:{}", path.display());
+        let server = axum::serve(listener, router.clone())
+            .with_graceful_shutdown(cancel_token.clone().cancelled_owned());
+        servers.spawn(async move { server.await });
+    }
+    init::systemd::notify_ready();
+    tokio::spawn(shutdown_signal(
+        signals,
+        state.clone(),
+        cancel_token.clone(),
+        std::time::Duration::from_secs(args.drain_delay),
+    ));
+    let servers = async {
+        while let Some(result) = servers.join_next().await {
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => log::error!("Server failed: {e}"),
+                Err(e) => log::error!("Server task failed: {e}"),
+            }
+        }
+        // Thumbnails of the last saves, they count against the shutdown timeout too
+        state.wait_background_tasks().await;
+    };
+    let drain_timeout = std::time::Duration::from_secs(args.shutdown_timeout);
+    tokio::select! {
+        _ = servers => {}
+        _ = drain_deadline(&cancel_token, drain_timeout) => {
+            log::warn!(
+                "In-flight requests still running after {}s, closing them",
+                args.shutdown_timeout
+            );
+        }
+    }
+
+    // Stop tickers between runs and wait for a running one to finish
+    cancel_token.cancel();
+    for ticker in tickers {
+        if let Err(e) = ticker.await {
+            log::error!("Ticker failed: {e}");
+        }
+    }
+    init::clean_temporary_files(&state).await;
+    #[cfg(unix)]
+    if let Some(path) = &args.unix_socket {
+        init::listener::remove_unix_socket(path);
+    }
+    log::info!("Shutdown complete");
+}
+
+/// Serve over HTTPS when a TLS config is given, until `cancel_token` is cancelled
+async fn serve(
+    listener: tokio::net::TcpListener,
+    router: axum::Router,
+    tls: Option<axum_server::tls_rustls::RustlsConfig>,
+    cancel_token: tokio_util::sync::CancellationToken,
+) -> std::io::Result<()> {
+    let Some(tls) = tls else {
+        return axum::serve(listener, router)
+            .with_graceful_shutdown(cancel_token.cancelled_owned())
+            .await;
+    };
+    let handle = axum_server::Handle::new();
+    let shutdown = handle.clone();
+    tokio::spawn(async move {
+        cancel_token.cancelled().await;
+        shutdown.graceful_shutdown(None);
+    });
+    axum_server::tls_rustls::from_tcp_rustls(listener.into_std()?, tls)?
+        .handle(handle)
+        .serve(router.into_make_service())
         .await
-        .unwrap();
+}
+
+/// Serve `router` over plain HTTP in the background until `cancel_token` is cancelled
+fn spawn_plain(
+    name: &'static str,
+    listener: tokio::net::TcpListener,
+    router: axum::Router,
+    cancel_token: tokio_util::sync::CancellationToken,
+) {
+    tokio::spawn(async move {
+        let result = axum::serve(listener, router)
+            .with_graceful_shutdown(cancel_token.cancelled_owned())
+            .await;
+        if let Err(e) = result {
+            log::error!("{name} server failed: {e}");
+        }
+    });
+}
+
+/// Answer every plain HTTP request with a permanent redirect to HTTPS on `https_port`
+fn redirect_router(https_port: u16) -> axum::Router {
+    use axum::http::{StatusCode, Uri, header};
+    use axum::response::{IntoResponse, Redirect};
+
+    let redirect = move |headers: axum::http::HeaderMap, uri: Uri| async move {
+        let Some(host) = headers.get(header::HOST).and_then(|h| h.to_str().ok()) else {
+            return StatusCode::BAD_REQUEST.into_response();
+        };
+        // Drop the port of the plain listener, keep IPv6 brackets
+        let host = match host.rsplit_once(':') {
+            Some((name, port)) if !name.is_empty() && port.parse::<u16>().is_ok() => name,
+            _ => host,
+        };
+        let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+        let location = if https_port == 443 {
+            format!("https://{host}{path}")
+        } else {
+            format!("https://{host}:{https_port}{path}")
+        };
+        Redirect::permanent(&location).into_response()
+    };
+    axum::Router::new().fallback(redirect)
 }
 
 /// Merge front-end and back-end routes and configure middleware
-fn router(state: interface::ServerState) -> axum::Router {
+///
+/// Metrics are served here unless they have a listener of their own. Everything is mounted
+/// under the base path and carries the security headers.
+fn router(state: interface::ServerState, args: &arg::Args, separate_metrics: bool) -> axum::Router {
     use axum::extract::DefaultBodyLimit;
+    use tower_http::compression::predicate::{DefaultPredicate, Predicate};
+    use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+    use tower_http::trace::TraceLayer;
     use tower_http::{compression::CompressionLayer, decompression::RequestDecompressionLayer};
 
-    let static_service = axum_embed::ServeEmbed::<web::WebAssets>::with_parameters(
-        Some("/".to_string()),
-        axum_embed::FallbackBehavior::Ok,
-        Some("index.html".to_string()),
-    );
-    axum::Router::new()
-        .nest("/api", interface::router().with_state(state))
-        .fallback_service(static_service)
+    let mut api = interface::router();
+    if args.api_docs {
+        api = api.merge(interface::openapi::docs_router("/docs"));
+    }
+    let mut router =
+        axum::Router::new().merge(interface::health::router().with_state(state.clone()));
+    if !separate_metrics {
+        router = router.merge(interface::metrics::router().with_state(state.clone()));
+    }
+    router = router
+        .nest("/api", api.with_state(state))
+        .merge(frontend::router(&args.base_path));
+    if !args.base_path.is_empty() {
+        // `nest` leaves out the prefix with a trailing slash, the home page of the front-end.
+        // Without the slash the front-end would resolve its assets against the parent folder.
+        let home = format!("{}/", args.base_path);
+        let redirect = {
+            let home = home.clone();
+            move |uri: axum::http::Uri| async move {
+                let location = match uri.query() {
+                    Some(query) => format!("{home}?{query}"),
+                    None => home,
+                };
+                axum::response::Redirect::permanent(&location)
+            }
+        };
+        router = axum::Router::new()
+            .nest(&args.base_path, router)
+            .route(&args.base_path, axum::routing::any(redirect))
+            .route_service(&home, frontend::router(&args.base_path).into_service());
+    }
+    security::apply(router, args)
         .layer(
             tower::ServiceBuilder::new()
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                .layer(TraceLayer::new_for_http().make_span_with(request_span))
+                .layer(PropagateRequestIdLayer::x_request_id())
                 .layer(RequestDecompressionLayer::new())
-                .layer(CompressionLayer::new()),
+                .layer(
+                    CompressionLayer::new()
+                        .compress_when(DefaultPredicate::new().and(has_no_digest)),
+                ),
         )
         .layer(DefaultBodyLimit::max(20 * 1024 * 1024))
 }
 
-async fn shutdown_signal(cancel_token: tokio_util::sync::CancellationToken) {
-    tokio::signal::ctrl_c()
-        .await
-        .expect("Failed to install CTRL+C signal handler");
+/// Whether a response may be compressed, not when it carries a digest of its body
+fn has_no_digest(
+    _: axum::http::StatusCode,
+    _: axum::http::Version,
+    headers: &axum::http::HeaderMap,
+    _: &axum::http::Extensions,
+) -> bool {
+    !headers.contains_key("repr-digest")
+}
+
+/// Span of a request, carrying the `X-Request-Id` set or propagated by the request id layer
+///
+/// Only the path is recorded, query strings may carry credentials.
+fn request_span(request: &axum::http::Request<axum::body::Body>) -> tracing::Span {
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or_default();
+    tracing::info_span!(
+        "request",
+        id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    )
+}
+
+fn local_addr(listener: &tokio::net::TcpListener) -> String {
+    listener
+        .local_addr()
+        .map(|addr| addr.to_string())
+        .unwrap_or_else(|_| String::from("unknown address"))
+}
+
+/// Wait for SIGINT or SIGTERM, then mark the server as draining
+///
+/// The server keeps serving for `drain_delay` before its listeners close, a second signal ends
+/// the wait early.
+async fn shutdown_signal(
+    mut signals: ShutdownSignals,
+    state: interface::ServerState,
+    cancel_token: tokio_util::sync::CancellationToken,
+    drain_delay: std::time::Duration,
+) {
+    signals.recv().await;
     log::info!("Shutting down...");
+    init::systemd::notify_stopping();
+    state.start_draining();
+    if !drain_delay.is_zero() {
+        log::info!("Draining, still serving for {}s", drain_delay.as_secs_f64());
+        tokio::select! {
+            _ = tokio::time::sleep(drain_delay) => {}
+            _ = signals.recv() => log::info!("Skipping the drain delay"),
+        }
+    }
     cancel_token.cancel();
 }
+
+/// SIGINT and SIGTERM handlers, signals are queued from their installation on
+///
+/// Installed lazily the default action of an early signal would end the process without draining,
+/// and a second signal sent between two waits would be lost.
+struct ShutdownSignals {
+    #[cfg(unix)]
+    interrupt: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+    #[cfg(not(unix))]
+    ctrl_c: tokio::signal::windows::CtrlC,
+}
+
+impl ShutdownSignals {
+    fn install() -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{SignalKind, signal};
+            Self {
+                interrupt: signal(SignalKind::interrupt())
+                    .expect("Failed to install SIGINT signal handler"),
+                terminate: signal(SignalKind::terminate())
+                    .expect("Failed to install SIGTERM signal handler"),
+            }
+        }
+        #[cfg(not(unix))]
+        Self {
+            ctrl_c: tokio::signal::windows::ctrl_c()
+                .expect("Failed to install CTRL+C signal handler"),
+        }
+    }
+
+    /// Resolve on the next SIGINT or SIGTERM
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        tokio::select! {
+            _ = self.interrupt.recv() => log::info!("Received SIGINT"),
+            _ = self.terminate.recv() => log::info!("Received SIGTERM"),
+        }
+        #[cfg(not(unix))]
+        self.ctrl_c.recv().await;
+    }
+}
+
+/// Handle SIGHUP until shutdown, it reloads the TLS certificate or is logged and ignored
+///
+/// Left to its default action the signal would end the process without draining. Once installed
+/// the handler outlives the stream, so the signal stays harmless through cleanup.
+#[cfg(unix)]
+fn hangup_handler(
+    tls: Option<(
+        (axum_server::tls_rustls::RustlsConfig, std::path::PathBuf),
+        std::path::PathBuf,
+    )>,
+    cancel_token: &tokio_util::sync::CancellationToken,
+) -> tokio::task::JoinHandle<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+    let mut hangup = signal(SignalKind::hangup()).expect("Failed to install SIGHUP signal handler");
+    let cancel_token = cancel_token.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = hangup.recv() => match &tls {
+                    Some(((config, cert), key)) => {
+                        log::info!("Received SIGHUP, reloading TLS certificate");
+                        init::tls::reload(config, cert, key).await;
+                    }
+                    None => log::info!("Received SIGHUP, nothing to reload"),
+                },
+                _ = cancel_token.cancelled() => break,
+            }
+        }
+    })
+}
+
+/// Resolve once shutdown has started and `timeout` has passed
+async fn drain_deadline(
+    cancel_token: &tokio_util::sync::CancellationToken,
+    timeout: std::time::Duration,
+) {
+    cancel_token.cancelled().await;
+    tokio::time::sleep(timeout).await;
+}
@@ -0,0 +1,85 @@
+//! Security headers and CORS
+
+use axum::http::{HeaderName, HeaderValue, Method, header};
+use axum::response::Response;
+
+/// Add the security headers and the CORS policy to every response
+///
+/// Headers a handler sets itself are kept, e.g. the sandbox policy of item contents.
+pub(crate) fn apply(router: axum::Router, args: &crate::arg::Args) -> axum::Router {
+    let headers = std::sync::Arc::new(headers(args));
+    let router = router.layer(axum::middleware::map_response(
+        move |mut response: Response| {
+            let headers = headers.clone();
+            async move {
+                for (name, value) in headers.iter() {
+                    if !response.headers().contains_key(name) {
+                        response.headers_mut().insert(name.clone(), value.clone());
+                    }
+                }
+                response
+            }
+        },
+    ));
+    match cors(&args.cors_origin) {
+        Some(cors) => router.layer(cors),
+        None => router,
+    }
+}
+
+/// Configured headers, empty values are left out
+fn headers(args: &crate::arg::Args) -> Vec<(HeaderName, HeaderValue)> {
+    let mut headers = vec![(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    )];
+    let configured = [
+        (
+            header::CONTENT_SECURITY_POLICY,
+            &args.content_security_policy,
+        ),
+        (header::X_FRAME_OPTIONS, &args.frame_options),
+        (header::REFERRER_POLICY, &args.referrer_policy),
+    ];
+    for (name, value) in configured {
+        // Values are checked when parsing the arguments
+        if let Ok(value) = HeaderValue::from_str(value)
+            && !value.is_empty()
+        {
+            headers.push((name, value));
+        }
+    }
+    if args.tls_cert.is_some() && args.hsts_max_age > 0 {
+        let value = format!("max-age={}", args.hsts_max_age);
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.push((header::STRICT_TRANSPORT_SECURITY, value));
+        }
+    }
+    headers
+}
+
+/// CORS for the allowed origins, `None` leaves browsers at the same-origin policy
+fn cors(origins: &[String]) -> Option<tower_http::cors::CorsLayer> {
+    use tower_http::cors::{AllowOrigin, CorsLayer};
+
+    if origins.is_empty() {
+        return None;
+    }
+    let origins = origins
+        .iter()
+        .filter_map(|origin| HeaderValue::from_str(origin).ok())
+        .collect::<Vec<_>>();
+    let request_id = HeaderName::from_static("x-request-id");
+    Some(
+        CorsLayer::new()
+            .allow_origin(AllowOrigin::list(origins))
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
+            .allow_headers([
+                header::CONTENT_TYPE,
+                header::ACCEPT_LANGUAGE,
+                request_id.clone(),
+            ])
+            .expose_headers([header::CONTENT_DISPOSITION, request_id])
+            .max_age(std::time::Duration::from_secs(60 * 60)),
+    )
+}
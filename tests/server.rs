@@ -0,0 +1,557 @@
+//! The server binary, started the way it is deployed
+
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// A running server with a fresh data directory, killed on drop
+struct Server {
+    child: Child,
+    url: String,
+    data_dir: std::path::PathBuf,
+}
+
+impl Server {
+    /// Start the server on a free port of 127.0.0.1 with extra arguments
+    async fn start(args: &[&str]) -> Self {
+        let port = free_port();
+        let mut command = Self::command(args);
+        command.args(["-H", "127.0.0.1", "-p", &port.to_string()]);
+        let scheme = if args.contains(&"--tls-cert") {
+            "https"
+        } else {
+            "http"
+        };
+        Self::spawn(command, format!("{scheme}://127.0.0.1:{port}")).await
+    }
+
+    /// Command running the server in a fresh data directory, without listeners
+    fn command(args: &[&str]) -> Command {
+        let data_dir = std::env::temp_dir().join(format!(
+            "temporary-cabinet-server-{}",
+            uuid::Uuid::new_v4().simple()
+        ));
+        let mut command = Command::new(env!("CARGO_BIN_EXE_temporary-cabinet"));
+        command
+            .arg("--data-dir")
+            .arg(&data_dir)
+            .args(["--min-free-space", "0"])
+            .args(args)
+            .env_remove("LISTEN_FDS")
+            .env_remove("LISTEN_PID")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        command
+    }
+
+    /// Spawn `command` and wait until `url` answers `/healthz` under the base path
+    async fn spawn(mut command: Command, url: String) -> Self {
+        let base_path = arg(&command, "--base-path").unwrap_or_default();
+        let health = format!("{url}{base_path}/healthz");
+        let data_dir = arg(&command, "--data-dir").unwrap().into();
+        let child = command.spawn().unwrap();
+        let mut server = Self {
+            child,
+            url,
+            data_dir,
+        };
+        server.wait_healthy(&health).await;
+        server
+    }
+
+    /// Wait until `health` answers
+    async fn wait_healthy(&mut self, health: &str) {
+        let deadline = Instant::now() + Duration::from_secs(30);
+        loop {
+            if let Ok(response) = client().get(health).send().await
+                && response.status().is_success()
+            {
+                return;
+            }
+            if let Some(status) = self.child.try_wait().unwrap() {
+                panic!("Server exited with {status}");
+            }
+            assert!(Instant::now() < deadline, "Server did not come up");
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    /// Send a signal to the server
+    #[cfg(unix)]
+    fn signal(&self, signal: libc::c_int) {
+        let result = unsafe { libc::kill(self.child.id() as libc::pid_t, signal) };
+        assert_eq!(result, 0);
+    }
+
+    /// Wait for the server to exit
+    async fn wait_exit(&mut self, timeout: Duration) -> std::process::ExitStatus {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(status) = self.child.try_wait().unwrap() {
+                return status;
+            }
+            assert!(Instant::now() < deadline, "Server did not exit");
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.data_dir);
+    }
+}
+
+/// Client following no redirects and trusting the test certificate
+fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap()
+}
+
+/// Value of an argument of a server command
+fn arg(command: &Command, name: &str) -> Option<String> {
+    command
+        .get_args()
+        .skip_while(|arg| *arg != name)
+        .nth(1)
+        .map(|value| value.to_string_lossy().to_string())
+}
+
+/// A port nothing listens on right now
+fn free_port() -> u16 {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn draining_keeps_serving_for_the_drain_delay() {
+    let mut server = Server::start(&["--drain-delay", "2"]).await;
+    let response = reqwest::get(format!("{}/readyz", server.url))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+    let start = Instant::now();
+    server.signal(libc::SIGTERM);
+    let deadline = Instant::now() + Duration::from_secs(1);
+    loop {
+        let response = reqwest::get(format!("{}/readyz", server.url))
+            .await
+            .unwrap();
+        if response.status() == reqwest::StatusCode::SERVICE_UNAVAILABLE {
+            let report: serde_json::Value = response.json().await.unwrap();
+            assert_eq!(report["checks"]["draining"]["ok"], false);
+            break;
+        }
+        assert!(Instant::now() < deadline, "Not draining after SIGTERM");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    // Requests are still served while load balancers catch up
+    let response = reqwest::get(format!("{}/api/v1/ping", server.url))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+    let status = server.wait_exit(Duration::from_secs(10)).await;
+    assert!(status.success(), "{status}");
+    assert!(start.elapsed() >= Duration::from_secs(2));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn second_signal_skips_the_drain_delay() {
+    let mut server = Server::start(&["--drain-delay", "600"]).await;
+    server.signal(libc::SIGTERM);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    server.signal(libc::SIGINT);
+    let status = server.wait_exit(Duration::from_secs(10)).await;
+    assert!(status.success(), "{status}");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn hangup_is_ignored_without_tls() {
+    let mut server = Server::start(&["--drain-delay", "0"]).await;
+    server.signal(libc::SIGHUP);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(
+        server.child.try_wait().unwrap().is_none(),
+        "Exited on SIGHUP"
+    );
+    let response = client()
+        .get(format!("{}/readyz", server.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    server.signal(libc::SIGTERM);
+    let status = server.wait_exit(Duration::from_secs(10)).await;
+    assert!(status.success(), "{status}");
+}
+
+/// Pass `listeners` as fds 3, 4, ... the way systemd socket activation does
+#[cfg(unix)]
+fn pass_listeners(command: &mut Command, listeners: &[&std::net::TcpListener]) {
+    use std::os::fd::AsRawFd;
+    use std::os::unix::process::CommandExt;
+
+    let fds = listeners
+        .iter()
+        .map(|listener| listener.as_raw_fd())
+        .collect::<Vec<_>>();
+    command.env("LISTEN_FDS", fds.len().to_string());
+    unsafe {
+        command.pre_exec(move || {
+            // Move the sockets out of the way first, they may sit on the target fds
+            let mut moved = Vec::with_capacity(fds.len());
+            for fd in &fds {
+                let high = libc::fcntl(*fd, libc::F_DUPFD, 100);
+                if high < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                moved.push(high);
+            }
+            for (index, fd) in moved.into_iter().enumerate() {
+                // dup2 clears close-on-exec on the copy
+                if libc::dup2(fd, 3 + index as libc::c_int) < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                libc::close(fd);
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn activated_sockets_serve_the_app_and_the_metrics() {
+    let app = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let metrics = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let app_url = format!("http://{}", app.local_addr().unwrap());
+    let metrics_url = format!("http://{}", metrics.local_addr().unwrap());
+
+    let mut command = Server::command(&[]);
+    pass_listeners(&mut command, &[&app, &metrics]);
+    let server = Server::spawn(command, app_url.clone()).await;
+    drop((app, metrics));
+
+    let response = reqwest::get(format!("{}/api/v1/ping", server.url))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    // Metrics moved to their own socket, the front-end answers instead
+    let response = reqwest::get(format!("{app_url}/metrics")).await.unwrap();
+    assert!(!response.text().await.unwrap().contains("cabinet_capacity"));
+    let response = reqwest::get(format!("{metrics_url}/metrics"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert!(response.text().await.unwrap().contains("cabinet_capacity"));
+}
+
+/// Self-signed certificate for `localhost` and `127.0.0.1`
+const CERT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/localhost.pem");
+const KEY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/localhost.key");
+
+/// Another certificate for the same names, as a renewal gives
+const RENEWED_CERT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/renewed.pem");
+const RENEWED_KEY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/renewed.key");
+
+/// Certificate `url` presents, over a new connection
+async fn peer_certificate(url: &str) -> Vec<u8> {
+    let response = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .tls_info(true)
+        .build()
+        .unwrap()
+        .get(url)
+        .send()
+        .await
+        .unwrap();
+    let info = response
+        .extensions()
+        .get::<reqwest::tls::TlsInfo>()
+        .unwrap();
+    info.peer_certificate().unwrap().to_vec()
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn plain_http_redirects_to_the_bound_https_port() {
+    // Socket activation leaves `--port` at its default, the redirect must not use it
+    let https = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let https_port = https.local_addr().unwrap().port();
+    let redirect_port = free_port().to_string();
+    let mut command = Server::command(&[
+        "--tls-cert",
+        CERT,
+        "--tls-key",
+        KEY,
+        "--tls-redirect-port",
+        &redirect_port,
+    ]);
+    pass_listeners(&mut command, &[&https]);
+    let server = Server::spawn(command, format!("https://127.0.0.1:{https_port}")).await;
+    drop(https);
+
+    let http = client();
+    let response = http
+        .get(format!("http://127.0.0.1:{redirect_port}/cabinet/1?a=b"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(
+        response.headers()["location"],
+        format!("https://127.0.0.1:{https_port}/cabinet/1?a=b").as_str()
+    );
+    let response = http
+        .get(format!("{}/api/v1/ping", server.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn redirect_points_at_the_public_port() {
+    let redirect_port = free_port().to_string();
+    let server = Server::start(&[
+        "--tls-cert",
+        CERT,
+        "--tls-key",
+        KEY,
+        "--tls-redirect-port",
+        &redirect_port,
+        "--tls-public-port",
+        "443",
+    ])
+    .await;
+    let response = client()
+        .get(format!("http://localhost:{redirect_port}/"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.headers()["location"], "https://localhost/");
+    let response = client()
+        .get(format!("{}/api/v1/ping", server.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn every_host_is_served() {
+    let server = Server::start(&["-H", "127.0.0.2"]).await;
+    let port = server.url.rsplit_once(':').unwrap().1;
+    for host in ["127.0.0.1", "127.0.0.2"] {
+        let response = reqwest::get(format!("http://{host}:{port}/api/v1/ping"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK, "{host}");
+    }
+}
+
+#[tokio::test]
+async fn startup_fails_when_a_host_cannot_be_bound() {
+    let port = free_port().to_string();
+    // TEST-NET-1, not assigned to any interface
+    let mut command = Server::command(&["-H", "127.0.0.1", "-H", "192.0.2.1", "-p", &port]);
+    let mut child = command.spawn().unwrap();
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        assert!(Instant::now() < deadline, "Server did not exit");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    };
+    let _ = std::fs::remove_dir_all(arg(&command, "--data-dir").unwrap());
+    assert!(!status.success());
+}
+
+#[tokio::test]
+async fn everything_is_served_under_the_base_path() {
+    let server = Server::start(&["--base-path", "/prefix"]).await;
+    let http = client();
+
+    let response = http
+        .get(format!("{}/prefix/", server.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let index = response.text().await.unwrap();
+    assert!(index.contains(r#"<base href="/prefix/""#), "{index}");
+    // Front-end routes get the rewritten index too
+    let response = http
+        .get(format!("{}/prefix/cabinet/100000", server.url))
+        .send()
+        .await
+        .unwrap();
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains(r#"<base href="/prefix/""#)
+    );
+
+    let response = http
+        .get(format!("{}/prefix/api/v1/ping", server.url))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body, serde_json::json!({ "data": "pong" }));
+    let response = http
+        .get(format!("{}/prefix/readyz", server.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let response = http
+        .get(format!("{}/api/v1/ping", server.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+    let response = http
+        .get(format!("{}/prefix?lang=en", server.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.headers()["location"], "/prefix/?lang=en");
+}
+
+#[tokio::test]
+async fn cors_preflight_answers_allowed_origins_only() {
+    let server = Server::start(&["--cors-origin", "https://app.example.com"]).await;
+    let http = client();
+    let preflight = |origin: &'static str| {
+        http.request(
+            reqwest::Method::OPTIONS,
+            format!("{}/api/v1/cabinet/apply", server.url),
+        )
+        .header("origin", origin)
+        .header("access-control-request-method", "POST")
+        .header("access-control-request-headers", "content-type")
+        .send()
+    };
+
+    let response = preflight("https://app.example.com").await.unwrap();
+    assert!(response.status().is_success());
+    let headers = response.headers();
+    assert_eq!(
+        headers["access-control-allow-origin"],
+        "https://app.example.com"
+    );
+    let methods = headers["access-control-allow-methods"].to_str().unwrap();
+    assert!(methods.contains("POST"), "{methods}");
+    let allowed = headers["access-control-allow-headers"].to_str().unwrap();
+    assert!(allowed.contains("content-type"), "{allowed}");
+
+    let response = preflight("https://evil.example.com").await.unwrap();
+    assert!(
+        response
+            .headers()
+            .get("access-control-allow-origin")
+            .is_none()
+    );
+
+    // Simple requests get the header for allowed origins only as well
+    let response = http
+        .get(format!("{}/api/v1/ping", server.url))
+        .header("origin", "https://evil.example.com")
+        .send()
+        .await
+        .unwrap();
+    assert!(
+        response
+            .headers()
+            .get("access-control-allow-origin")
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn security_headers_are_set_on_api_and_static_responses() {
+    let server = Server::start(&[]).await;
+    let http = client();
+    for path in ["/api/v1/ping", "/api/v1/cabinet/100000", "/", "/index.html"] {
+        let response = http
+            .get(format!("{}{path}", server.url))
+            .send()
+            .await
+            .unwrap();
+        let headers = response.headers();
+        assert_eq!(headers["x-content-type-options"], "nosniff", "{path}");
+        assert_eq!(headers["x-frame-options"], "DENY", "{path}");
+        assert_eq!(headers["referrer-policy"], "no-referrer", "{path}");
+        assert!(headers.contains_key("content-security-policy"), "{path}");
+        // Plain HTTP, browsers would ignore it anyway
+        assert!(!headers.contains_key("strict-transport-security"), "{path}");
+        // Without `--cors-origin` browsers stay at the same-origin policy
+        assert!(
+            !headers.contains_key("access-control-allow-origin"),
+            "{path}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn hsts_is_sent_over_https() {
+    let server = Server::start(&["--tls-cert", CERT, "--tls-key", KEY]).await;
+    let response = client()
+        .get(format!("{}/api/v1/ping", server.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers()["strict-transport-security"],
+        "max-age=31536000"
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn hangup_reloads_the_certificate() {
+    let files = std::env::temp_dir().join(format!(
+        "temporary-cabinet-tls-{}",
+        uuid::Uuid::new_v4().simple()
+    ));
+    std::fs::create_dir_all(&files).unwrap();
+    let (cert, key) = (files.join("cert.pem"), files.join("key.pem"));
+    std::fs::copy(CERT, &cert).unwrap();
+    std::fs::copy(KEY, &key).unwrap();
+    let server = Server::start(&[
+        "--tls-cert",
+        cert.to_str().unwrap(),
+        "--tls-key",
+        key.to_str().unwrap(),
+    ])
+    .await;
+    let health = format!("{}/healthz", server.url);
+    let before = peer_certificate(&health).await;
+
+    // Files are otherwise only checked every 30 seconds
+    std::fs::copy(RENEWED_CERT, &cert).unwrap();
+    std::fs::copy(RENEWED_KEY, &key).unwrap();
+    server.signal(libc::SIGHUP);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while peer_certificate(&health).await == before {
+        assert!(Instant::now() < deadline, "Certificate not reloaded");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let _ = std::fs::remove_dir_all(&files);
+}
@@ -1,4 +1,5 @@
 import request from '@/utils/request';
+import { basePath } from '@/utils/base';
 
 /**
  * Apply for a cabinet
@@ -13,7 +14,7 @@ import request from '@/utils/request';
  */
 export function applyCabinet() {
   return request({
-    url: '/api/cabinet/apply',
+    url: '/api/v1/cabinet/apply',
     method: 'post',
   });
 }
@@ -32,7 +33,7 @@ export function applyCabinet() {
  */
 export function getCabinetByCode(cabinetCode) {
   return request({
-    url: `/api/cabinet/${cabinetCode}`,
+    url: `/api/v1/cabinet/${cabinetCode}`,
     method: 'get',
   });
 }
@@ -47,7 +48,7 @@ export function getCabinetByCode(cabinetCode) {
  */
 export function getCabinetsUsage() {
   return request({
-    url: '/api/cabinet/usage',
+    url: '/api/v1/cabinet/usage',
     method: 'get',
   });
 }
@@ -61,6 +62,7 @@ export function getCabinetsUsage() {
  * @param {File} form.files - Files to save in the cabinet
  * @param {string} form.password - Password to encrypt the cabinet
  * @param {number} form.hours - Hours to hold the cabinet
+ * @param {boolean} form.strip_metadata - Strip photo and document metadata before saving
  * @param {string} form.hold_token - Hold token
  * @returns {object} cabinet
  * @returns {number} cabinet.code - Cabinet code
@@ -69,10 +71,11 @@ export function getCabinetsUsage() {
  * @returns {string} cabinet.status - Cabinet status
  * @returns {string} cabinet.hold_token - Cabinet hold token
  * @returns {string} cabinet.expire_at - Cabinet hold/occupied expiration time
+ * @returns {number} cabinet.stripped_bytes - Bytes of metadata removed, when stripping was asked
  */
 export function saveCabinet(cabinetCode, form) {
   return request({
-    url: `/api/cabinet/${cabinetCode}`,
+    url: `/api/v1/cabinet/${cabinetCode}`,
     method: 'post',
     data: form,
   });
@@ -88,7 +91,7 @@ export function saveCabinet(cabinetCode, form) {
  */
 export function deleteCabinet(cabinetCode, credential) {
   return request({
-    url: `/api/cabinet/${cabinetCode}`,
+    url: `/api/v1/cabinet/${cabinetCode}`,
     method: 'delete',
     data: credential,
   });
@@ -105,7 +108,7 @@ export function deleteCabinet(cabinetCode, credential) {
  */
 export function getCabinetItems(cabinetCode, credential) {
   return request({
-    url: `/api/cabinet/${cabinetCode}/items`,
+    url: `/api/v1/cabinet/${cabinetCode}/items`,
     method: 'post',
     data: credential,
   });
@@ -116,21 +119,22 @@ export function getCabinetItems(cabinetCode, credential) {
  *
  * @param {number} cabinetCode - Cabinet Code
  * @param {number} itemId - Item Id
- * @param {string} mode - download mode, can be 'text' or 'file'
+ * @param {string} mode - download mode, can be 'text', 'file', 'inline' for previewable items or
+ * 'highlighted' for text items as highlighted HTML
  * @param {object} credential - Credential
  * @param {string} credential.password - Password to decrypt the cabinet
  * @param {string} credential.public_key - Public key to encrypt the password
  * @returns text or download link
  */
 export function getCabinetItemContent(cabinetCode, itemId, mode, credential) {
-  const url = `/api/cabinet/${cabinetCode}/item/${itemId}/content?mode=${mode}`;
+  const url = `/api/v1/cabinet/${cabinetCode}/item/${itemId}/content?mode=${mode}`;
   if (mode === 'text') {
     return request({
       url,
       method: 'post',
       data: credential,
     });
-  } else if (mode === 'file') {
+  } else if (mode === 'file' || mode === 'inline' || mode === 'highlighted') {
     return request({
       url,
       method: 'post',
@@ -139,3 +143,16 @@ export function getCabinetItemContent(cabinetCode, itemId, mode, credential) {
     });
   }
 }
+
+/**
+ * Url of an image item's thumbnail, for an <img> that loads it directly
+ *
+ * @param {number} cabinetCode - Cabinet Code
+ * @param {number} itemId - Item Id
+ * @param {object} credential - Credential, its public key is used up by the request
+ * @returns {string} thumbnail url
+ */
+export function getCabinetItemThumbnailUrl(cabinetCode, itemId, credential) {
+  const query = new URLSearchParams(credential);
+  return `${basePath}api/v1/cabinet/${cabinetCode}/item/${itemId}/thumbnail?${query}`;
+}
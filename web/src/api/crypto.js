@@ -7,7 +7,7 @@ import request from '@/utils/request';
  */
 export function getPublicKey() {
   return request({
-    url: '/api/crypto/pk',
+    url: '/api/v1/crypto/pk',
     method: 'get',
   });
 }
@@ -16,10 +16,16 @@ export default {
   'expire-time': 'Expire time',
   'pickup-items': 'I want to collect my item',
   'label:store-some-message': 'Store a message',
+  'label:store-some-snippets': 'Or store code snippets',
+  'label:store-some-links': 'Or share links',
   'label:store-some-files': 'Or store files',
   'tips:input-password': 'Enter a 4–20 character password',
   'tips:keep-hours': 'Hours to keep',
   'tips:input-some-text': 'Enter some text…',
+  'tips:snippet-name': 'File name, e.g. nginx.conf',
+  'tips:snippet-language': 'Language',
+  'tips:snippet-content': 'Paste code or a command…',
+  'tips:input-link': 'https://…',
   'tips:input-cabinet-code': 'Enter cabinet ID',
   'tips:input-pickup-password': 'Enter pickup password',
   'upload-file-placeholder': 'Drag or click to upload file',
@@ -33,6 +39,13 @@ export default {
   open: 'Open',
   copy: 'Copy',
   download: 'Download',
+  preview: 'Preview',
+  highlighted: 'Highlighted',
+  'add-snippet': 'Add snippet',
+  'add-link': 'Add link',
+  'strip-metadata': 'Remove location and other metadata from photos and documents',
+  'metadata-stripped': 'Metadata removed: {size} bytes',
+  'metadata-kept': 'Metadata could not be removed from: {names}',
   'browser-not-support-copy': "Your browser doesn't support copying",
   'cleanup-and-delete-cabinet': 'Empty and reclaim',
   'back-to-home': 'Back to home',
@@ -42,6 +55,7 @@ export default {
   'error:cabinet:file-exceeds': "File '{filename}' exceeds 2 MB ({size}MB)",
   'error:cabinet:total-file-exceeds': 'Total file size exceeds 10MB ({size}MB)',
   'error:cabinet:lock-failed': 'Failed to lock the cabinet, please try again',
+  'error:cabinet:invalid-link': '{link} is not an http or https link',
   'error:pickup-failed': 'Pickup failed',
   'error:delete-cabinet-failed': 'Failed to delete cabinet',
 };
@@ -15,10 +15,16 @@ export default {
   'expire-time': 'Expire time',
   'pickup-items': 'I want to pick up my item',
   'label:store-some-message': 'Store a message',
+  'label:store-some-snippets': 'Or store code snippets',
+  'label:store-some-links': 'Or share links',
   'label:store-some-files': 'Or store files',
   'tips:input-password': 'Enter 4-20 digit password',
   'tips:keep-hours': 'Save for (hours)',
   'tips:input-some-text': 'Type a message…',
+  'tips:snippet-name': 'File name, e.g. nginx.conf',
+  'tips:snippet-language': 'Language',
+  'tips:snippet-content': 'Paste code or a command…',
+  'tips:input-link': 'https://…',
   'tips:input-cabinet-code': 'Enter cabinet ID',
   'tips:input-pickup-password': 'Enter pickup code',
   'upload-file-placeholder': 'Drag or click to upload',
@@ -32,6 +38,13 @@ export default {
   open: 'Open',
   copy: 'Copy',
   download: 'Download',
+  preview: 'Preview',
+  highlighted: 'Highlighted',
+  'add-snippet': 'Add snippet',
+  'add-link': 'Add link',
+  'strip-metadata': 'Remove location and other metadata from photos and documents',
+  'metadata-stripped': 'Metadata removed: {size} bytes',
+  'metadata-kept': 'Metadata could not be removed from: {names}',
   'browser-not-support-copy': 'Copy not supported in your browser',
   'cleanup-and-delete-cabinet': 'Empty and reclaim',
   'back-to-home': 'Home',
@@ -41,6 +54,7 @@ export default {
   'error:cabinet:file-exceeds': "File '{filename}' is larger than 2MB ({size}MB)",
   'error:cabinet:total-file-exceeds': 'Total file size is over 10MB ({size}MB)',
   'error:cabinet:lock-failed': "Couldn't lock the cabinet, please try again",
+  'error:cabinet:invalid-link': "{link} isn't an http or https link",
   'error:pickup-failed': 'Pickup failed',
   'error:delete-cabinet-failed': 'Could not delete locker',
 };
@@ -15,10 +15,16 @@ export default {
   'expire-time': '到期时间',
   'pickup-items': '我要取件',
   'label:store-some-message': '存放一些消息',
+  'label:store-some-snippets': '也可以存放代码片段',
+  'label:store-some-links': '也可以分享链接',
   'label:store-some-files': '也可以存放一些文件',
   'tips:input-password': '输入 4-20 位密码',
   'tips:keep-hours': '保存小时数',
   'tips:input-some-text': '输入一段文字…',
+  'tips:snippet-name': '文件名，例如 nginx.conf',
+  'tips:snippet-language': '语言',
+  'tips:snippet-content': '粘贴代码或命令…',
+  'tips:input-link': 'https://…',
   'tips:input-cabinet-code': '输入柜子编号',
   'tips:input-pickup-password': '输入取件密码',
   'upload-file-placeholder': '拖拽或点击上传文件',
@@ -32,6 +38,13 @@ export default {
   open: '打开',
   copy: '复制',
   download: '下载',
+  preview: '预览',
+  highlighted: '高亮查看',
+  'add-snippet': '添加代码片段',
+  'add-link': '添加链接',
+  'strip-metadata': '移除照片和文档中的位置等元数据',
+  'metadata-stripped': '已移除元数据：{size} 字节',
+  'metadata-kept': '无法移除元数据，已原样保存：{names}',
   'browser-not-support-copy': '你的浏览器不支持复制',
   'cleanup-and-delete-cabinet': '清空并回收',
   'back-to-home': '返回首页',
@@ -41,6 +54,7 @@ export default {
   'error:cabinet:file-exceeds': "文件 '{filename}' 大小超过 2MB ({size}MB)",
   'error:cabinet:total-file-exceeds': '文件总大小超过 10MB ({size}MB)',
   'error:cabinet:lock-failed': '锁柜失败，请重试',
+  'error:cabinet:invalid-link': '{link} 不是 http 或 https 链接',
   'error:pickup-failed': '取件失败',
   'error:delete-cabinet-failed': '柜子删除失败',
 };
@@ -15,10 +15,16 @@ export default {
   'expire-time': '到期時間',
   'pickup-items': '我要攞件',
   'label:store-some-message': '可以存訊息',
+  'label:store-some-snippets': '亦可以放程式碼片段',
+  'label:store-some-links': '亦可以分享連結',
   'label:store-some-files': '亦可以放檔案',
   'tips:input-password': '輸入 4–20 位密碼',
   'tips:keep-hours': '儲存小時數',
   'tips:input-some-text': '輸入一段文字…',
+  'tips:snippet-name': '檔案名，例如 nginx.conf',
+  'tips:snippet-language': '語言',
+  'tips:snippet-content': '貼上程式碼或指令…',
+  'tips:input-link': 'https://…',
   'tips:input-cabinet-code': '輸入櫃編號',
   'tips:input-pickup-password': '輸入攞件密碼',
   'upload-file-placeholder': '拖曳或點擊上載檔案',
@@ -32,6 +38,13 @@ export default {
   open: '打開',
   copy: '複製',
   download: '下載',
+  preview: '預覽',
+  highlighted: '高亮顯示',
+  'add-snippet': '加程式碼片段',
+  'add-link': '加連結',
+  'strip-metadata': '移除相片和文件中的位置等中繼資料',
+  'metadata-stripped': '已移除中繼資料：{size} 位元組',
+  'metadata-kept': '無法移除中繼資料，已原樣保存：{names}',
   'browser-not-support-copy': '您的瀏覽器不支援複製',
   'cleanup-and-delete-cabinet': '清空並回收',
   'back-to-home': '返回首頁',
@@ -41,6 +54,7 @@ export default {
   'error:cabinet:file-exceeds': "檔案 '{filename}'  大小超過 2MB ({size}MB)",
   'error:cabinet:total-file-exceeds': '檔案總大小超過 10MB ({size}MB)',
   'error:cabinet:lock-failed': '鎖櫃失敗，請重試',
+  'error:cabinet:invalid-link': '{link} 唔係 http 或者 https 連結',
   'error:pickup-failed': '攞件失敗',
   'error:delete-cabinet-failed': '櫃刪除失敗',
 };
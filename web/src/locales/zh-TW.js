@@ -15,10 +15,16 @@ export default {
   'expire-time': '到期時間',
   'pickup-items': '我要取件',
   'label:store-some-message': '可存放訊息',
+  'label:store-some-snippets': '也可放程式碼片段',
+  'label:store-some-links': '也可分享連結',
   'label:store-some-files': '也可放檔案',
   'tips:input-password': '請輸入 4–20 位密碼',
   'tips:keep-hours': '保存小時數',
   'tips:input-some-text': '輸入一段文字…',
+  'tips:snippet-name': '檔案名稱，例如 nginx.conf',
+  'tips:snippet-language': '語言',
+  'tips:snippet-content': '貼上程式碼或指令…',
+  'tips:input-link': 'https://…',
   'tips:input-cabinet-code': '請輸入櫃子編號',
   'tips:input-pickup-password': '輸入取件密碼',
   'upload-file-placeholder': '拖曳或點擊上傳檔案',
@@ -32,6 +38,13 @@ export default {
   open: '打開',
   copy: '複製',
   download: '下載',
+  preview: '預覽',
+  highlighted: '醒目顯示',
+  'add-snippet': '新增程式碼片段',
+  'add-link': '新增連結',
+  'strip-metadata': '移除照片和文件中的位置等中繼資料',
+  'metadata-stripped': '已移除中繼資料：{size} 位元組',
+  'metadata-kept': '無法移除中繼資料，已原樣保存：{names}',
   'browser-not-support-copy': '你嘅瀏覽器唔支援複製',
   'cleanup-and-delete-cabinet': '清空並回收',
   'back-to-home': '返回首頁',
@@ -41,6 +54,7 @@ export default {
   'error:cabinet:file-exceeds': "檔案 '{filename}' 大小超過 2MB ({size}MB)",
   'error:cabinet:total-file-exceeds': '檔案總大小超過 10MB ({size}MB)',
   'error:cabinet:lock-failed': '鎖櫃失敗，請再試一次',
+  'error:cabinet:invalid-link': '{link} 不是 http 或 https 連結',
   'error:pickup-failed': '取件失敗',
   'error:delete-cabinet-failed': '櫃子刪除失敗',
 };
@@ -2,9 +2,10 @@ import { createRouter, createWebHistory } from 'vue-router';
 import Home from './views/Home.vue';
 import Pick from './views/Pick.vue';
 import i18n from './locales';
+import { basePath } from './utils/base';
 
 const router = createRouter({
-  history: createWebHistory(),
+  history: createWebHistory(basePath),
   routes: [
     { path: '/', name: 'home', component: Home, meta: { title: 'title:temporary-cabinet' } },
     {
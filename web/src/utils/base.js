@@ -0,0 +1,6 @@
+/**
+ * Path the app is served under, always ending with '/'
+ *
+ * Read from the <base> of index.html, which the server sets from `--base-path`.
+ */
+export const basePath = document.querySelector('base')?.getAttribute('href') || '/';
@@ -1,8 +1,9 @@
 import axios from 'axios';
 import { ElMessage } from 'element-plus';
+import { basePath } from './base';
 
 const request = axios.create({
-  baseURL: import.meta.env.VITE_BASE_API || '/',
+  baseURL: import.meta.env.VITE_BASE_API || basePath,
   timeout: 10 * 1000,
 });
 
@@ -12,17 +13,21 @@ request.interceptors.response.use(
     if (response.status === 200) {
       if (response.request.responseType === 'blob') {
         return {
-          filename: response.headers['content-disposition'].split('filename=')[1],
+          filename: dispositionFilename(response.headers['content-disposition']),
           data: response.data,
         };
       }
+      // JSON bodies of the v1 api are wrapped in `{ data }`, item contents are not
+      if (response.headers['content-type']?.startsWith('application/json')) {
+        return response.data.data;
+      }
       return response.data;
     }
-    const { message } = response.data;
+    const { message } = response.data.error || {};
     return Promise.reject(new Error(message));
   },
   (error) => {
-    const message = error.response?.data?.message || error.message;
+    const message = error.response?.data?.error?.message || error.message;
     if (!message) {
       ElMessage.error('Network error');
     }
@@ -30,4 +35,19 @@ request.interceptors.response.use(
   }
 );
 
+/**
+ * File name of a `Content-Disposition` header, preferring the UTF-8 `filename*`
+ *
+ * @param {string} disposition - Header value
+ * @returns {string} file name
+ */
+function dispositionFilename(disposition = '') {
+  const extended = /filename\*=UTF-8''([^;]+)/i.exec(disposition);
+  if (extended) {
+    return decodeURIComponent(extended[1]);
+  }
+  const plain = /filename="?([^";]+)"?/i.exec(disposition);
+  return plain ? plain[1] : 'download';
+}
+
 export default request;
@@ -4,6 +4,8 @@ import { resolve } from 'path';
 
 // https://vite.dev/config/
 export default defineConfig({
+  // Relative asset urls, the server sets <base> to the path it is mounted at
+  base: './',
   plugins: [vue()],
   resolve: {
     alias: {